@@ -0,0 +1,144 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+#[derive(Debug)]
+pub struct Browser {
+    root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    entries: Vec<Entry>,
+    selected: usize,
+}
+
+impl Browser {
+    pub fn new(root: PathBuf) -> Self {
+        let mut browser = Self {
+            root,
+            expanded: HashSet::new(),
+            entries: Vec::new(),
+            selected: 0,
+        };
+        browser.refresh();
+        browser
+    }
+
+    pub fn refresh(&mut self) {
+        let mut entries = Vec::new();
+        self.list(&self.root, 0, &mut entries);
+        self.entries = entries;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    fn list(&self, dir: &Path, depth: usize, entries: &mut Vec<Entry>) {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut children: Vec<Entry> = read_dir
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                (name != ".git").then(|| Entry {
+                    is_dir: entry.path().is_dir(),
+                    path: entry.path(),
+                    name,
+                    depth,
+                })
+            })
+            .collect();
+        children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        for child in children {
+            let expanded = child.is_dir && self.expanded.contains(&child.path);
+            let path = child.path.clone();
+            entries.push(child);
+            if expanded {
+                self.list(&path, depth.saturating_add(1), entries);
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selection(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn is_expanded(&self, path: &Path) -> bool {
+        self.expanded.contains(path)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected.saturating_add(1) < self.entries.len() {
+            self.selected = self.selected.saturating_add(1);
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select(&mut self, path: &Path) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = index;
+        }
+    }
+
+    pub fn expand(&mut self) {
+        if let Some(entry) = self.selection().filter(|entry| entry.is_dir) {
+            self.expanded.insert(entry.path.clone());
+            self.refresh();
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        let Some(entry) = self.selection().filter(|entry| entry.is_dir) else {
+            return;
+        };
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.refresh();
+    }
+
+    pub fn collapse(&mut self) {
+        let Some(entry) = self.selection() else {
+            return;
+        };
+        let path = entry.path.clone();
+        if self.expanded.remove(&path) {
+            self.refresh();
+        } else if let Some(parent) = path.parent().filter(|parent| *parent != self.root) {
+            let parent = parent.to_path_buf();
+            self.expanded.remove(&parent);
+            self.refresh();
+            self.select(&parent);
+        }
+    }
+
+    pub fn target_dir(&self) -> PathBuf {
+        match self.selection() {
+            Some(entry) if entry.is_dir && self.is_expanded(&entry.path) => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map_or_else(|| self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CaseChange {
+    Upper,
+    Lower,
+    Title,
+}
+
+impl CaseChange {
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Self::Upper => text.to_uppercase(),
+            Self::Lower => text.to_lowercase(),
+            Self::Title => text
+                .split_word_bounds()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase()
+                    })
+                })
+                .collect(),
+        }
+    }
+}
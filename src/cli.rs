@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "Usage: hecto [OPTIONS] [FILE[:LINE[:COLUMN]]]... [+LINE FILE]...
+
+Arguments:
+  FILE                 Files or directories to open, - reads from stdin
+
+Options:
+  -R, --readonly       Open files read-only
+  -d, --diff           Compare two files side by side
+  -c, --config <PATH>  Read configuration from PATH
+  -t, --theme <NAME>   Use the theme NAME
+  -h, --help           Print help
+  -V, --version        Print version";
+
+#[derive(Debug, Clone)]
+pub struct FileArg {
+    pub path: String,
+    pub location: Option<(usize, Option<usize>)>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub read_only: bool,
+    pub diff: bool,
+    pub config: Option<PathBuf>,
+    pub theme: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Action {
+    Run(Args),
+    Help,
+    Version,
+}
+
+pub fn usage() -> &'static str {
+    USAGE
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Action, String> {
+    let mut parsed = Args::default();
+    let mut line = None;
+    let mut only_files = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !only_files && arg.len() > 1 && arg.starts_with('-') {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = |name: &str| {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+            match flag {
+                "--" => only_files = true,
+                "-R" | "--readonly" => parsed.read_only = true,
+                "-d" | "--diff" => parsed.diff = true,
+                "-c" | "--config" => parsed.config = Some(PathBuf::from(value(flag)?)),
+                "-t" | "--theme" => parsed.theme = Some(value(flag)?),
+                "-h" | "--help" => return Ok(Action::Help),
+                "-V" | "--version" => return Ok(Action::Version),
+                _ => return Err(format!("Unknown option: {}", arg)),
+            }
+            continue;
+        }
+        if let Some(number) = arg.strip_prefix('+').and_then(|number| number.parse().ok()) {
+            line = Some(number);
+            continue;
+        }
+        let (path, location) = parse_location(&arg);
+        parsed.files.push(FileArg {
+            path: path.to_string(),
+            location: location.or_else(|| line.take().map(|line| (line, None))),
+        });
+    }
+    if parsed.diff && parsed.files.len() != 2 {
+        return Err("--diff requires exactly two files".to_string());
+    }
+    Ok(Action::Run(parsed))
+}
+
+fn parse_location(arg: &str) -> (&str, Option<(usize, Option<usize>)>) {
+    if Path::new(arg).exists() {
+        return (arg, None);
+    }
+    let trimmed = arg.strip_suffix(':').unwrap_or(arg);
+    let Some((rest, last)) = trimmed
+        .rsplit_once(':')
+        .and_then(|(rest, last)| Some((rest, last.parse().ok()?)))
+    else {
+        return (arg, None);
+    };
+    match rest
+        .rsplit_once(':')
+        .and_then(|(file, line)| Some((file, line.parse().ok()?)))
+    {
+        Some((file, line)) => (file, Some((line, Some(last)))),
+        None => (rest, Some((last, None))),
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{lsp, ColorSupport, KeymapPreset};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbers {
+    Off,
+    Absolute,
+    Relative,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatusBarConfig {
+    pub left: String,
+    pub right: String,
+}
+
+impl Default for StatusBarConfig {
+    fn default() -> Self {
+        Self {
+            left: String::from("{filename}{readonly} - {lines} lines{modified}"),
+            right: String::from(
+                "{loading} | {diagnostics} | {filetype} | {encoding} | {eol} | {line}:{col} | {percent}",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FileTypeConfig {
+    pub tab_width: Option<usize>,
+    pub expand_tab: Option<bool>,
+    pub color_column: Option<usize>,
+    pub format: Option<String>,
+    pub keywords: Vec<String>,
+    pub types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub tab_width: usize,
+    pub expand_tab: bool,
+    pub auto_indent: bool,
+    pub auto_close: bool,
+    pub theme: String,
+    pub color_support: Option<ColorSupport>,
+    pub line_numbers: LineNumbers,
+    pub soft_wrap: bool,
+    pub scrolloff: usize,
+    pub show_whitespace: bool,
+    pub indent_guides: bool,
+    pub cursor_line: bool,
+    pub inline_diagnostics: bool,
+    pub color_column: Option<usize>,
+    pub color_columns: HashMap<String, usize>,
+    pub quit_times: u8,
+    pub auto_save: Option<u64>,
+    pub swap_interval: Option<u64>,
+    pub persistent_undo: bool,
+    pub undo_levels: usize,
+    pub spell_check: bool,
+    pub spell_dictionary: Option<String>,
+    pub keymap: KeymapPreset,
+    pub status_bar: StatusBarConfig,
+    pub lsp: HashMap<String, String>,
+    pub format: HashMap<String, String>,
+    pub format_on_save: bool,
+    pub filetypes: HashMap<String, FileTypeConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            expand_tab: false,
+            auto_indent: true,
+            auto_close: true,
+            theme: String::from("default"),
+            color_support: None,
+            line_numbers: LineNumbers::Off,
+            soft_wrap: false,
+            scrolloff: 0,
+            show_whitespace: false,
+            indent_guides: false,
+            cursor_line: true,
+            inline_diagnostics: false,
+            color_column: None,
+            color_columns: HashMap::new(),
+            quit_times: 3,
+            auto_save: None,
+            swap_interval: Some(4),
+            persistent_undo: false,
+            undo_levels: 1000,
+            spell_check: false,
+            spell_dictionary: None,
+            keymap: KeymapPreset::Default,
+            status_bar: StatusBarConfig::default(),
+            lsp: HashMap::new(),
+            format: HashMap::new(),
+            format_on_save: false,
+            filetypes: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn dir() -> Option<PathBuf> {
+        let base = if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+            PathBuf::from(dir)
+        } else {
+            PathBuf::from(env::var_os("HOME")?).join(".config")
+        };
+        Some(base.join("hecto"))
+    }
+
+    pub fn state_dir() -> Option<PathBuf> {
+        let base = if let Some(dir) = env::var_os("XDG_STATE_HOME") {
+            PathBuf::from(dir)
+        } else {
+            PathBuf::from(env::var_os("HOME")?)
+                .join(".local")
+                .join("state")
+        };
+        Some(base.join("hecto"))
+    }
+
+    pub fn state_file(kind: &str, file_name: &str, extension: &str) -> Option<PathBuf> {
+        let absolute = env::current_dir().ok()?.join(file_name);
+        let name = absolute
+            .to_string_lossy()
+            .replace('%', "%25")
+            .replace('/', "%2F");
+        Some(
+            Self::state_dir()?
+                .join(kind)
+                .join(format!("{}.{}", name, extension)),
+        )
+    }
+
+    pub fn file_type(&self, file_type: &str) -> Option<&FileTypeConfig> {
+        self.filetypes.get(&lsp::language_id(file_type))
+    }
+
+    pub fn tab_width(&self, file_type: &str) -> usize {
+        self.file_type(file_type)
+            .and_then(|config| config.tab_width)
+            .unwrap_or(self.tab_width)
+    }
+
+    pub fn expand_tab(&self, file_type: &str) -> bool {
+        self.file_type(file_type)
+            .and_then(|config| config.expand_tab)
+            .unwrap_or(self.expand_tab)
+    }
+
+    pub fn color_column(&self, file_type: &str) -> Option<usize> {
+        self.file_type(file_type)
+            .and_then(|config| config.color_column)
+            .or_else(|| {
+                self.color_columns
+                    .get(&lsp::language_id(file_type))
+                    .copied()
+            })
+            .or(self.color_column)
+            .filter(|column| *column > 0)
+    }
+
+    pub fn format_command(&self, file_type: &str) -> Option<&String> {
+        self.file_type(file_type)
+            .and_then(|config| config.format.as_ref())
+            .or_else(|| self.format.get(&lsp::language_id(file_type)))
+    }
+
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let explicit = path.is_some();
+        let path = if let Some(path) = path {
+            path.to_path_buf()
+        } else if let Some(dir) = Self::dir() {
+            dir.join("config.toml")
+        } else {
+            return Ok(Self::default());
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound && !explicit => {
+                return Ok(Self::default())
+            }
+            Err(error) => return Err(format!("Could not read {}: {}", path.display(), error)),
+        };
+        toml::from_str(&contents).map_err(|error| format!("Invalid {}: {}", path.display(), error))
+    }
+}
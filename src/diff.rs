@@ -0,0 +1,324 @@
+use std::{ops::Range, path::Path, process::Command};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_EDITS: usize = 2000;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Change {
+    Added,
+    Modified,
+    Removed,
+}
+
+pub fn index_contents(file_name: &str) -> Option<Vec<String>> {
+    let path = Path::new(file_name).canonicalize().ok()?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path.parent()?)
+        .arg("show")
+        .arg(format!(":./{}", path.file_name()?.to_str()?))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+pub fn changes(base: &[String], current: &[&str]) -> Vec<Option<Change>> {
+    let mut result = vec![None; current.len()];
+    let prefix = base
+        .iter()
+        .zip(current)
+        .take_while(|(a, b)| a == *b)
+        .count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(a, b)| a == *b)
+        .count();
+    let base = &base[prefix..base.len() - suffix];
+    let middle = &current[prefix..current.len() - suffix];
+    if base.is_empty() && middle.is_empty() {
+        return result;
+    }
+    let Some((inserted, deleted)) = edit_script(base, middle) else {
+        let change = if base.is_empty() {
+            Change::Added
+        } else {
+            Change::Modified
+        };
+        for marker in &mut result[prefix..prefix + middle.len()] {
+            *marker = Some(change);
+        }
+        return result;
+    };
+    let mut y = 0;
+    while y <= middle.len() {
+        if y < middle.len() && inserted[y] {
+            let start = y;
+            let mut removed = deleted[y];
+            while y < middle.len() && inserted[y] {
+                y += 1;
+                removed += deleted[y];
+            }
+            for (offset, marker) in result[prefix + start..prefix + y].iter_mut().enumerate() {
+                *marker = Some(if offset < removed {
+                    Change::Modified
+                } else {
+                    Change::Added
+                });
+            }
+        } else {
+            if deleted[y] > 0 && !current.is_empty() {
+                let line = removed_line(prefix + y, current.len());
+                result[line].get_or_insert(Change::Removed);
+            }
+            y += 1;
+        }
+    }
+    result
+}
+
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+pub fn align(base: &[String], current: &[&str]) -> Vec<Option<usize>> {
+    let prefix = base
+        .iter()
+        .zip(current)
+        .take_while(|(a, b)| a == *b)
+        .count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(a, b)| a == *b)
+        .count();
+    let mut result: Vec<Option<usize>> = (0..current.len()).map(Some).collect();
+    for (offset, pair) in result[current.len() - suffix..].iter_mut().enumerate() {
+        *pair = Some(base.len() - suffix + offset);
+    }
+    let base_middle = &base[prefix..base.len() - suffix];
+    let middle = &current[prefix..current.len() - suffix];
+    let Some((inserted, deleted)) = edit_script(base_middle, middle) else {
+        for (offset, pair) in result[prefix..prefix + middle.len()].iter_mut().enumerate() {
+            *pair = (offset < base_middle.len()).then_some(prefix + offset);
+        }
+        return result;
+    };
+    let mut x = prefix;
+    let mut y = 0;
+    while y < middle.len() {
+        let start = y;
+        let mut removed = deleted[y];
+        while y < middle.len() && inserted[y] {
+            y += 1;
+            removed += deleted[y];
+        }
+        for (offset, pair) in result[prefix + start..prefix + y].iter_mut().enumerate() {
+            *pair = (offset < removed).then_some(x + offset);
+        }
+        x += removed;
+        if y < middle.len() {
+            result[prefix + y] = Some(x);
+            x += 1;
+            y += 1;
+        }
+    }
+    result
+}
+
+pub fn changed_range(base: &str, current: &str) -> Option<Range<usize>> {
+    if base == current {
+        return None;
+    }
+    let old: Vec<&str> = base.graphemes(true).collect();
+    let new: Vec<&str> = current.graphemes(true).collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let limit = old.len().min(new.len()).saturating_sub(prefix);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(limit)
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(prefix..new.len().saturating_sub(suffix))
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    left: usize,
+    right: usize,
+    revisions: Option<(usize, usize)>,
+    left_pairs: Vec<Option<usize>>,
+    right_pairs: Vec<Option<usize>>,
+    left_changes: Vec<Option<Range<usize>>>,
+    right_changes: Vec<Option<Range<usize>>>,
+}
+
+impl Comparison {
+    pub fn new(left: usize, right: usize) -> Self {
+        Self {
+            left,
+            right,
+            revisions: None,
+            left_pairs: Vec::new(),
+            right_pairs: Vec::new(),
+            left_changes: Vec::new(),
+            right_changes: Vec::new(),
+        }
+    }
+
+    pub fn buffers(&self) -> (usize, usize) {
+        (self.left, self.right)
+    }
+
+    pub fn is_current(&self, revisions: (usize, usize)) -> bool {
+        self.revisions == Some(revisions)
+    }
+
+    pub fn update(&mut self, left: &[String], right: &[String], revisions: (usize, usize)) {
+        let left_lines: Vec<&str> = left.iter().map(String::as_str).collect();
+        let right_lines: Vec<&str> = right.iter().map(String::as_str).collect();
+        self.left_pairs = align(right, &left_lines);
+        self.right_pairs = align(left, &right_lines);
+        self.left_changes = inline_changes(&self.left_pairs, right, &left_lines);
+        self.right_changes = inline_changes(&self.right_pairs, left, &right_lines);
+        self.revisions = Some(revisions);
+    }
+
+    pub fn other(&self, buffer: usize) -> Option<usize> {
+        if buffer == self.left {
+            Some(self.right)
+        } else if buffer == self.right {
+            Some(self.left)
+        } else {
+            None
+        }
+    }
+
+    pub fn counterpart(&self, buffer: usize, line: usize) -> usize {
+        let pairs = if buffer == self.left {
+            &self.left_pairs
+        } else {
+            &self.right_pairs
+        };
+        let line = line.min(pairs.len().saturating_sub(1));
+        (0..=line)
+            .rev()
+            .find_map(|y| {
+                let x = pairs.get(y).copied().flatten()?;
+                Some(x.saturating_add(usize::from(y < line)))
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn changes(&self, buffer: usize) -> &[Option<Range<usize>>] {
+        if buffer == self.left {
+            &self.left_changes
+        } else if buffer == self.right {
+            &self.right_changes
+        } else {
+            &[]
+        }
+    }
+}
+
+fn inline_changes(
+    pairs: &[Option<usize>],
+    base: &[String],
+    current: &[&str],
+) -> Vec<Option<Range<usize>>> {
+    pairs
+        .iter()
+        .zip(current)
+        .map(|(pair, line)| changed_range(base.get((*pair)?)?, line))
+        .collect()
+}
+
+fn removed_line(line: usize, len: usize) -> usize {
+    line.saturating_sub(1).min(len.saturating_sub(1))
+}
+
+#[allow(
+    clippy::arithmetic_side_effects,
+    clippy::indexing_slicing,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+fn edit_script(a: &[String], b: &[&str]) -> Option<(Vec<bool>, Vec<usize>)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+    let index = |k: isize| (k + max + 1) as usize;
+    let mut v = vec![0_isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+    for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, d, n, m, index));
+            }
+        }
+    }
+    None
+}
+
+#[allow(
+    clippy::arithmetic_side_effects,
+    clippy::indexing_slicing,
+    clippy::cast_sign_loss
+)]
+fn backtrack(
+    trace: &[Vec<isize>],
+    d: isize,
+    n: isize,
+    m: isize,
+    index: impl Fn(isize) -> usize,
+) -> (Vec<bool>, Vec<usize>) {
+    let mut inserted = vec![false; m as usize];
+    let mut deleted = vec![0; m as usize + 1];
+    let (mut x, mut y) = (n, m);
+    for d in (1..=d).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[index(previous_k)];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+        }
+        if x == previous_x {
+            inserted[previous_y as usize] = true;
+        } else {
+            deleted[previous_y as usize] += 1;
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    (inserted, deleted)
+}
@@ -1,35 +1,476 @@
-use std::{fs, io::Write};
+use std::{
+    cmp,
+    collections::BTreeSet,
+    fs,
+    io::{BufRead, BufReader, Error, ErrorKind, Write},
+    ops::Range,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
 
-use crate::{FileType, Position, Row, SearchDirection};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    diff::{self, Change},
+    highlighting,
+    lsp::{self, Diagnostic, SemanticToken},
+    status_bar,
+    swap::{self, Swap},
+    undo::{Edit, EditKind, Travel, UndoHistory},
+    Encoding, FileType, GapBuffer, HighlightingOptions, Matcher, Position, Row, SearchDirection,
+};
+
+const LAZY_LOAD_THRESHOLD: u64 = 8 * 1024 * 1024;
+const LOAD_CHUNK_LINES: usize = 16 * 1024;
+const LOAD_BUDGET: Duration = Duration::from_millis(50);
+const BRACKET_SCAN_ROWS: usize = 5000;
+const SYNC_HIGHLIGHT_ROWS: usize = 2000;
+const HIGHLIGHT_WINDOW_ROWS: usize = 256;
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(index) if text[..index].ends_with('\r') => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Loader {
+    receiver: Receiver<(Vec<String>, u64, bool)>,
+    loaded: u64,
+    total: u64,
+    lossy: bool,
+}
+
+#[derive(Debug)]
+struct HighlightJob {
+    generation: usize,
+    receiver: Receiver<(usize, Vec<Row>)>,
+}
+
+#[derive(Debug, Default)]
+struct Highlighter {
+    generation: usize,
+    changed: Option<Range<usize>>,
+    job: Option<HighlightJob>,
+}
 
 #[derive(Debug, Default)]
 pub struct Document {
-    rows: Vec<Row>,
+    rows: GapBuffer<Row>,
     pub file_name: Option<String>,
     dirty: bool,
     file_type: FileType,
+    file_type_pinned: bool,
+    highlighted: usize,
+    highlighter: Highlighter,
+    loader: Option<Loader>,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    git_branch: Option<String>,
+    modified: Option<SystemTime>,
+    read_only: bool,
+    diff_base: Option<Vec<String>>,
+    diff: Vec<Option<Change>>,
+    diff_stale: bool,
+    diff_pinned: bool,
+    revision: usize,
+    diagnostics: Vec<Diagnostic>,
+    history: UndoHistory,
+    undo_levels: Option<usize>,
+    bracket_match: Option<(usize, Position, Option<Position>)>,
 }
 
 impl Document {
-    pub fn open(filename: &str) -> Result<Self, std::io::Error> {
-        let contents = fs::read_to_string(filename)?;
-        let file_type = FileType::from(filename);
-        let mut rows = Vec::new();
-        for value in contents.lines() {
-            rows.push(Row::from(value));
+    pub fn open(filename: &str, file_types: &[FileType]) -> Result<Self, std::io::Error> {
+        let total = fs::metadata(filename)?.len();
+        if total > LAZY_LOAD_THRESHOLD {
+            if let Some(document) = Self::open_lazily(filename, total, file_types)? {
+                return Ok(document);
+            }
         }
+        let bytes = fs::read(filename)?;
+        let encoding = Encoding::detect(&bytes);
+        let contents = encoding.decode(&bytes);
+        let file_type = FileType::detect(filename, contents.lines().next(), file_types);
+        let rows = contents.lines().map(Row::from).collect();
         Ok(Self {
             rows,
             file_name: Some(filename.to_string()),
             dirty: false,
             file_type,
+            file_type_pinned: false,
+            highlighted: 0,
+            highlighter: Highlighter::default(),
+            loader: None,
+            line_ending: LineEnding::detect(&contents),
+            encoding,
+            git_branch: status_bar::git_branch(filename),
+            modified: modified_time(filename),
+            read_only: is_read_only(filename),
+            diff_base: diff::index_contents(filename),
+            diff: Vec::new(),
+            diff_stale: true,
+            diff_pinned: false,
+            revision: 0,
+            diagnostics: Vec::new(),
+            history: UndoHistory::default(),
+            undo_levels: None,
+            bracket_match: None,
         })
     }
 
+    pub fn from_bytes(bytes: &[u8], file_types: &[FileType]) -> Self {
+        let encoding = Encoding::detect(bytes);
+        let contents = encoding.decode(bytes);
+        Self {
+            rows: contents.lines().map(Row::from).collect(),
+            file_type: FileType::detect("", contents.lines().next(), file_types),
+            line_ending: LineEnding::detect(&contents),
+            encoding,
+            ..Self::default()
+        }
+    }
+
+    fn open_lazily(
+        filename: &str,
+        total: u64,
+        file_types: &[FileType],
+    ) -> Result<Option<Self>, std::io::Error> {
+        let mut reader = BufReader::new(fs::File::open(filename)?);
+        let bom = Encoding::from_bom(reader.fill_buf()?);
+        if matches!(bom, Some(Encoding::Utf16Le | Encoding::Utf16Be)) {
+            return Ok(None);
+        }
+        let mut bytes = Vec::new();
+        let mut loaded = reader.read_until(b'\n', &mut bytes)? as u64;
+        let encoding = bom.unwrap_or_else(|| Encoding::detect(&bytes));
+        let mut line = encoding.decode(&bytes);
+        let file_type = FileType::detect(filename, line.lines().next(), file_types);
+        let line_ending = LineEnding::detect(&line);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut lines = Vec::new();
+            let mut lossy = false;
+            #[allow(clippy::arithmetic_side_effects)]
+            while !line.is_empty() {
+                lines.push(line.lines().next().unwrap_or_default().to_string());
+                if lines.len() >= LOAD_CHUNK_LINES
+                    && sender
+                        .send((std::mem::take(&mut lines), loaded, lossy))
+                        .is_err()
+                {
+                    return;
+                }
+                bytes.clear();
+                loaded += reader.read_until(b'\n', &mut bytes).unwrap_or(0) as u64;
+                lossy = lossy || !encoding.is_valid(&bytes);
+                line = encoding.decode(&bytes);
+            }
+            let _ = sender.send((lines, total, lossy));
+        });
+        Ok(Some(Self {
+            rows: GapBuffer::default(),
+            file_name: Some(filename.to_string()),
+            dirty: false,
+            file_type,
+            file_type_pinned: false,
+            highlighted: 0,
+            highlighter: Highlighter::default(),
+            loader: Some(Loader {
+                receiver,
+                loaded: 0,
+                total,
+                lossy: false,
+            }),
+            line_ending,
+            encoding,
+            git_branch: status_bar::git_branch(filename),
+            modified: modified_time(filename),
+            read_only: is_read_only(filename),
+            diff_base: diff::index_contents(filename),
+            diff: Vec::new(),
+            diff_stale: true,
+            diff_pinned: false,
+            revision: 0,
+            diagnostics: Vec::new(),
+            history: UndoHistory::default(),
+            undo_levels: None,
+            bracket_match: None,
+        }))
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.dirty = true;
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn git_branch(&self) -> Option<&str> {
+        self.git_branch.as_deref()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn changed_on_disk(&self) -> bool {
+        let Some(file_name) = &self.file_name else {
+            return false;
+        };
+        self.modified.is_some() && modified_time(file_name) != self.modified
+    }
+
+    pub fn ignore_disk_change(&mut self) {
+        if let Some(file_name) = &self.file_name {
+            self.modified = modified_time(file_name);
+        }
+    }
+
+    pub fn has_diff(&self) -> bool {
+        self.diff_base.is_some()
+    }
+
+    pub fn diff_change(&self, index: usize) -> Option<Change> {
+        self.diff.get(index).copied().flatten()
+    }
+
+    pub fn set_diff_base(&mut self, base: Vec<String>) {
+        self.diff_base = Some(base);
+        self.diff_pinned = true;
+        self.diff_stale = true;
+    }
+
+    pub fn reload_diff_base(&mut self) {
+        if self.diff_pinned {
+            return;
+        }
+        self.diff_base = self.file_name.as_deref().and_then(diff::index_contents);
+        self.diff_stale = true;
+    }
+
+    pub fn update_diff(&mut self) {
+        if !self.diff_stale || self.is_loading() {
+            return;
+        }
+        self.diff_stale = false;
+        let Some(base) = &self.diff_base else {
+            self.diff.clear();
+            return;
+        };
+        let current: Vec<&str> = self.rows.iter().map(Row::as_str).collect();
+        self.diff = diff::changes(base, &current);
+    }
+
+    pub fn hunk(&self, from: usize, direction: SearchDirection) -> Option<usize> {
+        let is_start = |index: usize| {
+            self.diff_change(index).is_some()
+                && (index == 0 || self.diff_change(index.saturating_sub(1)).is_none())
+        };
+        match direction {
+            SearchDirection::Forward => {
+                (from.saturating_add(1)..self.diff.len()).find(|index| is_start(*index))
+            }
+            SearchDirection::Backword => (0..from).rev().find(|index| is_start(*index)),
+        }
+    }
+
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| row.as_str().to_string())
+            .collect()
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows.iter() {
+            text.push_str(row.as_str());
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn set_semantic_tokens(&mut self, tokens: &[SemanticToken]) {
+        let mut spans = vec![Vec::new(); self.rows.len()];
+        for token in tokens {
+            let (Some(hl_type), Some(row), Some(row_spans)) = (
+                highlighting::Type::from_semantic(&token.kind),
+                self.rows.get(token.line),
+                spans.get_mut(token.line),
+            ) else {
+                continue;
+            };
+            let start = lsp::grapheme_column(row.as_str(), token.start);
+            let end = lsp::grapheme_column(row.as_str(), token.start.saturating_add(token.length));
+            row_spans.push((start..end, hl_type));
+        }
+        for (row, row_spans) in self.rows.iter_mut().zip(spans) {
+            row.set_semantic(row_spans);
+        }
+    }
+
+    pub fn diagnostic(&self, line: usize) -> Option<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.line == line)
+            .min_by_key(|diagnostic| diagnostic.severity)
+    }
+
+    pub fn next_diagnostic(&self, from: usize, direction: SearchDirection) -> Option<&Diagnostic> {
+        match direction {
+            SearchDirection::Forward => self
+                .diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.line > from)
+                .min_by_key(|diagnostic| (diagnostic.line, diagnostic.severity)),
+            SearchDirection::Backword => self
+                .diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.line < from)
+                .max_by_key(|diagnostic| (diagnostic.line, cmp::Reverse(diagnostic.severity))),
+        }
+    }
+
+    pub fn has_signs(&self) -> bool {
+        self.has_diff() || !self.diagnostics.is_empty()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    pub fn loading_progress(&self) -> Option<u64> {
+        let loader = self.loader.as_ref()?;
+        Some(loader.loaded * 100 / cmp::max(loader.total, 1))
+    }
+
+    pub fn receive_rows(&mut self) -> bool {
+        let loader = if let Some(loader) = &mut self.loader {
+            loader
+        } else {
+            return false;
+        };
+        let mut lossy = false;
+        let started = Instant::now();
+        while started.elapsed() < LOAD_BUDGET {
+            match loader.receiver.try_recv() {
+                Ok((lines, loaded, lost)) => {
+                    loader.loaded = loaded;
+                    for line in lines {
+                        self.rows.push(Row::from(line.as_str()));
+                    }
+                    self.bracket_match = None;
+                    if lost && !loader.lossy {
+                        loader.lossy = true;
+                        self.read_only = true;
+                        lossy = true;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.loader = None;
+                    break;
+                }
+            }
+        }
+        lossy
+    }
+
     pub fn file_type(&self) -> String {
         self.file_type.name()
     }
 
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.file_type = file_type;
+        self.file_type_pinned = true;
+        self.unhighlight_rows(0);
+    }
+
+    pub fn indents_after(&self, c: char) -> bool {
+        self.file_type.indents_after(c)
+    }
+
+    pub fn is_prose(&self) -> bool {
+        self.file_type.is_plain() || self.file_type.highlighting_options().markdown()
+    }
+
+    pub fn highlighting_options(&self) -> &HighlightingOptions {
+        self.file_type.highlighting_options()
+    }
+
+    pub fn closing_pair(&self, c: char) -> Option<char> {
+        self.file_type.closing_pair(c)
+    }
+
+    pub fn is_closing_pair(&self, c: char) -> bool {
+        self.file_type.is_closing_pair(c)
+    }
+
+    pub fn collect_words(&self, prefix: &str, words: &mut BTreeSet<String>) {
+        for row in self.rows.iter() {
+            for word in row
+                .as_str()
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+            {
+                if word.len() > prefix.len() && word.starts_with(prefix) {
+                    words.insert(word.to_string());
+                }
+            }
+        }
+    }
+
     pub fn row(&self, index: usize) -> Option<&Row> {
         self.rows.get(index)
     }
@@ -53,7 +494,7 @@ impl Document {
         #[allow(clippy::indexing_slicing)]
         let current_row = &mut self.rows[at.y];
         let new_row = current_row.split(at.x);
-        #[allow(clippy::integer_arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         self.rows.insert(at.y + 1, new_row);
     }
 
@@ -62,65 +503,558 @@ impl Document {
             return;
         }
         self.dirty = true;
-        if c == '\n' {
+        self.revision = self.revision.wrapping_add(1);
+        let removed = self.lines_in(at.y..at.y.saturating_add(1));
+        let (kind, after) = if c == '\n' {
             self.insert_newline(at);
-        } else if at.y == self.rows.len() {
-            let mut row = Row::default();
-            row.insert(0, c);
-            self.rows.push(row);
+            let after = Position {
+                x: 0,
+                y: at.y.saturating_add(1),
+            };
+            (EditKind::Other, after)
         } else {
-            #[allow(clippy::indexing_slicing)]
-            let row = &mut self.rows[at.y];
-            row.insert(at.x, c);
-        }
-        self.unhighlight_rows(at.y);
+            if at.y == self.rows.len() {
+                let mut row = Row::default();
+                row.insert(0, c);
+                self.rows.push(row);
+            } else {
+                #[allow(clippy::indexing_slicing)]
+                let row = &mut self.rows[at.y];
+                row.insert(at.x, c);
+            }
+            let after = Position {
+                x: at.x.saturating_add(1),
+                y: at.y,
+            };
+            (EditKind::Insert, after)
+        };
+        let inserted = if c == '\n' && !removed.is_empty() {
+            2
+        } else {
+            1
+        };
+        self.record(kind, at.y, removed, inserted, at.clone(), after);
+        self.rows_changed(at.y);
     }
 
-    fn unhighlight_rows(&mut self, start: usize) {
-        let start = start.saturating_sub(1);
-        for row in self.rows.iter_mut().skip(start) {
-            row.is_highlighted = false;
-        }
+    fn lines_in(&self, range: Range<usize>) -> Vec<String> {
+        range
+            .filter_map(|index| self.rows.get(index))
+            .map(|row| row.as_str().to_string())
+            .collect()
+    }
+
+    fn record(
+        &mut self,
+        kind: EditKind,
+        start: usize,
+        removed: Vec<String>,
+        inserted: usize,
+        before: Position,
+        after: Position,
+    ) {
+        let inserted = self.lines_in(start..start.saturating_add(inserted));
+        self.history.record(
+            kind,
+            Edit {
+                start,
+                removed,
+                inserted,
+            },
+            before,
+            after,
+        );
+    }
+
+    pub fn unhighlight_rows(&mut self, start: usize) {
+        self.bracket_match = None;
+        self.highlighted = cmp::min(self.highlighted, start.saturating_sub(1));
+        self.highlighter.generation = self.highlighter.generation.wrapping_add(1);
+        self.diff_stale = true;
+    }
+
+    fn rows_changed(&mut self, start: usize) {
+        self.highlighter.changed = Some(match self.highlighter.changed.take() {
+            Some(changed) => cmp::min(changed.start, start)..usize::MAX,
+            None => start..start.saturating_add(1),
+        });
+        self.highlighter.generation = self.highlighter.generation.wrapping_add(1);
+        self.diff_stale = true;
     }
 
-    #[allow(clippy::integer_arithmetic, clippy::indexing_slicing)]
+    #[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
     pub fn delete(&mut self, at: &Position) {
         let len = self.rows.len();
         if at.y > len {
             return;
         }
         self.dirty = true;
-        if at.x == self.rows[at.y].len() && at.y + 1 < len {
-            let next_row = self.rows.remove(at.y + 1);
-            let row = &mut self.rows[at.y];
-            row.append(&next_row);
+        self.revision = self.revision.wrapping_add(1);
+        let joining = at.x == self.rows[at.y].len() && at.y + 1 < len;
+        let removed = self.lines_in(at.y..at.y + if joining { 2 } else { 1 });
+        if joining {
+            if let Some(next_row) = self.rows.remove(at.y + 1) {
+                self.rows[at.y].append(&next_row);
+            }
         } else {
             let row = &mut self.rows[at.y];
             row.delete(at.x);
         }
-        self.unhighlight_rows(at.y);
+        let kind = if joining {
+            EditKind::Other
+        } else {
+            EditKind::Delete
+        };
+        self.record(kind, at.y, removed, 1, at.clone(), at.clone());
+        self.rows_changed(at.y);
+    }
+
+    #[allow(clippy::indexing_slicing, clippy::arithmetic_side_effects)]
+    pub fn insert_str(&mut self, at: &Position, string: &str) -> Position {
+        if at.y > self.rows.len() {
+            return at.clone();
+        }
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        let removed = self.lines_in(at.y..at.y + 1);
+        if at.y == self.rows.len() {
+            self.rows.push(Row::default());
+        }
+        let tail = self.rows[at.y].split(at.x);
+        let mut position = at.clone();
+        for (index, line) in string.split('\n').enumerate() {
+            if index > 0 {
+                position.y += 1;
+                position.x = 0;
+                self.rows.insert(position.y, Row::default());
+            }
+            let row = &mut self.rows[position.y];
+            row.insert_str(position.x, line);
+            position.x += line.graphemes(true).count();
+        }
+        self.rows[position.y].append(&tail);
+        self.record(
+            EditKind::Other,
+            at.y,
+            removed,
+            position.y - at.y + 1,
+            at.clone(),
+            position.clone(),
+        );
+        self.rows_changed(at.y);
+        position
+    }
+
+    pub fn extract_range(&self, start: &Position, end: &Position) -> String {
+        let mut result = String::new();
+        for y in start.y..=end.y {
+            let row = if let Some(row) = self.rows.get(y) {
+                row
+            } else {
+                break;
+            };
+            if y > start.y {
+                result.push('\n');
+            }
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { row.len() };
+            result.push_str(&row.slice(from, to));
+        }
+        result
     }
 
-    pub fn save(&mut self) -> Result<(), std::io::Error> {
+    #[allow(clippy::indexing_slicing, clippy::arithmetic_side_effects)]
+    pub fn delete_range(&mut self, start: &Position, end: &Position) {
+        let len = self.rows.len();
+        if start.y >= len {
+            return;
+        }
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        let removed = self.lines_in(start.y..cmp::max(start.y, cmp::min(end.y, len - 1)) + 1);
+        if start.y == end.y {
+            self.rows[start.y].delete_range(start.x, end.x);
+        } else {
+            let last = cmp::min(end.y, len - 1);
+            let tail = if end.y < len {
+                self.rows[last].split(end.x)
+            } else {
+                Row::default()
+            };
+            self.rows.remove_range(start.y + 1..last + 1);
+            let row = &mut self.rows[start.y];
+            row.split(start.x);
+            row.append(&tail);
+        }
+        self.record(
+            EditKind::Other,
+            start.y,
+            removed,
+            1,
+            start.clone(),
+            start.clone(),
+        );
+        self.rows_changed(start.y);
+    }
+
+    pub fn delete_lines(&mut self, lines: Range<usize>) -> String {
+        let lines = lines.start..cmp::min(lines.end, self.rows.len());
+        if lines.is_empty() {
+            return String::new();
+        }
+        let mut text = String::new();
+        for row in lines.clone().filter_map(|index| self.rows.get(index)) {
+            text.push_str(row.as_str());
+            text.push('\n');
+        }
+        let removed = self.lines_in(lines.clone());
+        self.rows.remove_range(lines.clone());
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        let position = Position {
+            x: 0,
+            y: lines.start,
+        };
+        self.record(
+            EditKind::Other,
+            lines.start,
+            removed,
+            0,
+            position.clone(),
+            position,
+        );
+        self.rows_changed(lines.start);
+        text
+    }
+
+    pub fn delete_to_line_end(&mut self, at: &Position, lines: usize) -> String {
+        let y = at.y.saturating_add(lines.saturating_sub(1));
+        let end = match self.rows.get(y) {
+            Some(row) if at.x < row.len() || lines > 1 => Position { x: row.len(), y },
+            Some(_) => Position {
+                x: 0,
+                y: y.saturating_add(1),
+            },
+            None => return String::new(),
+        };
+        let text = self.extract_range(at, &end);
+        self.delete_range(at, &end);
+        text
+    }
+
+    pub fn join_lines(&mut self, y: usize) -> Option<Position> {
+        let row = self.rows.get(y)?;
+        let next = self.rows.get(y.saturating_add(1))?;
+        let kept = row.as_str().trim_end();
+        let joined = next.as_str().trim_start();
+        let start = Position {
+            x: kept.graphemes(true).count(),
+            y,
+        };
+        let end = Position {
+            x: next.len().saturating_sub(joined.graphemes(true).count()),
+            y: y.saturating_add(1),
+        };
+        let separator = if kept.is_empty() || joined.is_empty() || joined.starts_with(')') {
+            ""
+        } else {
+            " "
+        };
+        self.replace(&start, &end, separator);
+        Some(start)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn move_lines(&mut self, lines: Range<usize>, direction: SearchDirection) -> bool {
+        if lines.is_empty() || lines.end > self.rows.len() {
+            return false;
+        }
+        let (from, to) = match direction {
+            SearchDirection::Forward if lines.end < self.rows.len() => (lines.end, lines.start),
+            SearchDirection::Backword if lines.start > 0 => (lines.start - 1, lines.end - 1),
+            _ => return false,
+        };
+        let first = cmp::min(from, to);
+        let removed = self.lines_in(first..cmp::max(from, to) + 1);
+        let Some(row) = self.rows.remove(from) else {
+            return false;
+        };
+        self.rows.insert(to, row);
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        let position = Position { x: 0, y: first };
+        self.record(
+            EditKind::Other,
+            first,
+            removed,
+            from.abs_diff(to) + 1,
+            position.clone(),
+            position,
+        );
+        self.rows_changed(first);
+        true
+    }
+
+    pub fn duplicate_lines(&mut self, lines: Range<usize>) {
+        if lines.is_empty() || lines.end > self.rows.len() {
+            return;
+        }
+        let copies: Vec<Row> = lines
+            .clone()
+            .filter_map(|index| self.rows.get(index))
+            .map(|row| Row::from(row.as_str()))
+            .collect();
+        let count = copies.len();
+        for (offset, row) in copies.into_iter().enumerate() {
+            self.rows.insert(lines.end.saturating_add(offset), row);
+        }
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        let position = Position { x: 0, y: lines.end };
+        self.record(
+            EditKind::Other,
+            lines.end,
+            Vec::new(),
+            count,
+            position.clone(),
+            position,
+        );
+        self.rows_changed(lines.end);
+    }
+
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::indexing_slicing,
+        clippy::cast_possible_wrap
+    )]
+    pub fn replace_lines(&mut self, lines: &[&str]) -> Option<Vec<Option<usize>>> {
+        let base = self.lines_in(0..self.rows.len());
+        let alignment = diff::align(&base, lines);
+        let mut kept: Vec<(usize, usize)> = alignment
+            .iter()
+            .enumerate()
+            .filter_map(|(y, x)| x.filter(|x| base[*x] == lines[y]).map(|x| (x, y)))
+            .collect();
+        kept.push((base.len(), lines.len()));
+        let mut hunks = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        for (next_x, next_y) in kept {
+            if next_x > x || next_y > y {
+                hunks.push((x, next_x - x, y, next_y - y));
+            }
+            x = next_x + 1;
+            y = next_y + 1;
+        }
+        let first = hunks.first()?.0;
+        let mut shift = 0_isize;
+        for (x, removed, y, inserted) in hunks {
+            let start = x.saturating_add_signed(shift);
+            shift += inserted as isize - removed as isize;
+            let old = self.lines_in(start..start + removed);
+            self.rows.remove_range(start..start + removed);
+            for (offset, line) in lines[y..y + inserted].iter().enumerate() {
+                self.rows.insert(start + offset, Row::from(*line));
+            }
+            let position = Position { x: 0, y: start };
+            self.record(
+                EditKind::Other,
+                start,
+                old,
+                inserted,
+                position.clone(),
+                position,
+            );
+        }
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        self.rows_changed(first);
+        Some(alignment)
+    }
+
+    pub fn replace(&mut self, start: &Position, end: &Position, replacement: &str) -> Position {
+        self.delete_range(start, end);
+        self.insert_str(start, replacement)
+    }
+
+    pub fn save(&mut self, file_types: &[FileType]) -> Result<(), std::io::Error> {
+        if self.is_loading() {
+            return Err(Error::new(ErrorKind::WouldBlock, "file is still loading"));
+        }
         if let Some(file_name) = &self.file_name {
-            let mut file = fs::File::create(file_name)?;
-            self.file_type = FileType::from(file_name);
-            for row in &mut self.rows {
-                file.write_all(row.as_bytes())?;
-                file.write_all(b"\n")?;
+            let mut bytes = self.encoding.bom().to_vec();
+            for row in self.rows.iter() {
+                self.encoding.encode(row.as_str(), &mut bytes)?;
+                self.encoding
+                    .encode(self.line_ending.as_str(), &mut bytes)?;
+            }
+            fs::File::create(file_name)?.write_all(&bytes)?;
+            self.modified = modified_time(file_name);
+            swap::remove(file_name);
+            if let Some(levels) = self.undo_levels {
+                let _ = self.history.write(file_name, &self.text(), levels);
             }
-            self.file_type = FileType::from(file_name);
+            if !self.file_type_pinned {
+                let first_line = self.rows.first().map(Row::as_str);
+                self.file_type = FileType::detect(file_name, first_line, file_types);
+            }
+            self.git_branch = status_bar::git_branch(file_name);
+            if !self.diff_pinned {
+                self.diff_base = diff::index_contents(file_name);
+            }
+            self.unhighlight_rows(0);
+            self.history.mark_saved();
             self.dirty = false;
         }
         Ok(())
     }
 
+    pub fn write_swap(&self) -> Result<(), std::io::Error> {
+        let Some(file_name) = &self.file_name else {
+            return Ok(());
+        };
+        swap::write(file_name, &self.text())
+    }
+
+    fn swap(&self) -> Option<Swap> {
+        self.file_name
+            .as_deref()
+            .and_then(swap::read)
+            .filter(|swap| !swap.is_foreign())
+    }
+
+    pub fn has_swap(&self) -> bool {
+        self.swap().is_some()
+    }
+
+    pub fn is_swap_stale(&self) -> bool {
+        match (self.swap(), &self.file_name) {
+            (Some(swap), Some(file_name)) => swap.is_stale(file_name),
+            _ => false,
+        }
+    }
+
+    pub fn restore_swap(&mut self) {
+        let Some(swap) = self.swap() else {
+            return;
+        };
+        let removed = self.lines_in(0..self.rows.len());
+        self.rows = swap.contents.lines().map(Row::from).collect();
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        self.record(
+            EditKind::Other,
+            0,
+            removed,
+            self.rows.len(),
+            Position::default(),
+            Position::default(),
+        );
+        self.history.seal();
+        self.unhighlight_rows(0);
+    }
+
+    pub fn remove_swap(&self) {
+        if let Some(file_name) = &self.file_name {
+            swap::remove(file_name);
+        }
+    }
+
+    pub fn undo_levels(&self) -> Option<usize> {
+        self.undo_levels
+    }
+
+    pub fn enable_persistent_undo(&mut self, levels: usize) {
+        self.undo_levels = Some(levels);
+        if self.is_loading() || self.dirty {
+            return;
+        }
+        if let Some(history) = self
+            .file_name
+            .as_deref()
+            .and_then(|file_name| UndoHistory::load(file_name, &self.text()))
+        {
+            self.history = history;
+        }
+    }
+
+    pub fn seal_undo(&mut self) {
+        self.history.seal();
+    }
+
+    pub fn undo(&mut self) -> Option<Position> {
+        let group = self.history.undo()?;
+        for edit in group.edits.iter().rev() {
+            self.splice(edit.start, edit.inserted.len(), &edit.removed);
+        }
+        self.edits_applied(group.edits.iter().map(|edit| edit.start).min());
+        Some(group.before)
+    }
+
+    pub fn redo(&mut self) -> Option<Position> {
+        let group = self.history.redo()?;
+        for edit in &group.edits {
+            self.splice(edit.start, edit.removed.len(), &edit.inserted);
+        }
+        self.edits_applied(group.edits.iter().map(|edit| edit.start).min());
+        Some(group.after)
+    }
+
+    pub fn travel(&mut self, travel: Travel) -> Option<Position> {
+        let steps = self.history.travel(travel);
+        let mut position = None;
+        let mut start: Option<usize> = None;
+        for (group, direction) in &steps {
+            if *direction == SearchDirection::Forward {
+                for edit in &group.edits {
+                    self.splice(edit.start, edit.removed.len(), &edit.inserted);
+                }
+                position = Some(group.after.clone());
+            } else {
+                for edit in group.edits.iter().rev() {
+                    self.splice(edit.start, edit.inserted.len(), &edit.removed);
+                }
+                position = Some(group.before.clone());
+            }
+            for edit in &group.edits {
+                start = Some(start.map_or(edit.start, |start| cmp::min(start, edit.start)));
+            }
+        }
+        if !steps.is_empty() {
+            self.edits_applied(start);
+        }
+        position
+    }
+
+    pub fn undo_tree(&self) -> (Vec<String>, usize) {
+        self.history.tree()
+    }
+
+    fn splice(&mut self, start: usize, count: usize, lines: &[String]) {
+        self.rows
+            .remove_range(start..cmp::min(start.saturating_add(count), self.rows.len()));
+        for (offset, line) in lines.iter().enumerate() {
+            self.rows
+                .insert(start.saturating_add(offset), Row::from(line.as_str()));
+        }
+    }
+
+    fn edits_applied(&mut self, start: Option<usize>) {
+        self.dirty = !self.history.is_saved();
+        self.revision = self.revision.wrapping_add(1);
+        self.rows_changed(start.unwrap_or_default());
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
 
     #[allow(clippy::indexing_slicing)]
-    pub fn find(&self, query: &str, at: &Position, direction: SearchDirection) -> Option<Position> {
+    pub fn find(
+        &self,
+        matcher: &Matcher,
+        at: &Position,
+        direction: SearchDirection,
+    ) -> Option<Range<Position>> {
         if at.y > self.rows.len() {
             return None;
         }
@@ -139,9 +1073,16 @@ impl Document {
 
         for _ in start..end {
             if let Some(row) = self.rows.get(position.y) {
-                if let Some(x) = row.find(query, position.x, direction) {
-                    position.x = x;
-                    return Some(position);
+                if let Some(range) = row.find(matcher, position.x, direction) {
+                    return Some(
+                        Position {
+                            x: range.start,
+                            y: position.y,
+                        }..Position {
+                            x: range.end,
+                            y: position.y,
+                        },
+                    );
                 }
                 if direction == SearchDirection::Forward {
                     position.y = position.y.saturating_add(1);
@@ -157,8 +1098,87 @@ impl Document {
         None
     }
 
-    pub fn highlight(&mut self, word: &Option<String>, until: Option<usize>) {
-        let mut start_with_comment = false;
+    pub fn expand(&self, matcher: &Matcher, found: &Range<Position>, replacement: &str) -> String {
+        self.rows.get(found.start.y).map_or_else(
+            || replacement.to_string(),
+            |row| row.expand(matcher, &(found.start.x..found.end.x), replacement),
+        )
+    }
+
+    pub fn find_all(&self, matcher: &Matcher) -> Vec<Range<Position>> {
+        let mut matches = Vec::new();
+        for (y, row) in self.rows.iter().enumerate() {
+            let mut x = 0;
+            while let Some(range) = row.find(matcher, x, SearchDirection::Forward) {
+                x = range.end;
+                matches.push(Position { x: range.start, y }..Position { x: range.end, y });
+            }
+        }
+        matches
+    }
+
+    pub fn matching_bracket(&mut self, at: &Position) -> Option<Position> {
+        if let Some((revision, position, result)) = &self.bracket_match {
+            if *revision == self.revision && position == at {
+                return result.clone();
+            }
+        }
+        let result = self.find_matching_bracket(at);
+        self.bracket_match = Some((self.revision, at.clone(), result.clone()));
+        result
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn find_matching_bracket(&mut self, at: &Position) -> Option<Position> {
+        self.highlight(None, Some(at.y));
+        let (_, bracket) = self
+            .rows
+            .get(at.y)?
+            .brackets()
+            .into_iter()
+            .find(|(x, _)| *x == at.x)?;
+        let (target, forward) = BRACKET_PAIRS.iter().find_map(|(open, close)| {
+            if bracket == *open {
+                Some((*close, true))
+            } else if bracket == *close {
+                Some((*open, false))
+            } else {
+                None
+            }
+        })?;
+        let mut depth: usize = 0;
+        let rows: Vec<usize> = if forward {
+            (at.y..cmp::min(self.rows.len(), at.y + BRACKET_SCAN_ROWS)).collect()
+        } else {
+            (at.y.saturating_sub(BRACKET_SCAN_ROWS)..=at.y)
+                .rev()
+                .collect()
+        };
+        for y in rows {
+            self.highlight(None, Some(y));
+            let mut brackets = self.rows.get(y)?.brackets();
+            if !forward {
+                brackets.reverse();
+            }
+            for (x, c) in brackets {
+                if y == at.y && (if forward { x <= at.x } else { x >= at.x }) {
+                    continue;
+                }
+                if c == bracket {
+                    depth += 1;
+                } else if c == target {
+                    if depth == 0 {
+                        return Some(Position { x, y });
+                    }
+                    depth -= 1;
+                }
+            }
+        }
+        None
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn highlight(&mut self, matcher: Option<&Matcher>, until: Option<usize>) {
         let until = if let Some(until) = until {
             if until.saturating_add(1) < self.rows.len() {
                 until.saturating_add(1)
@@ -168,13 +1188,165 @@ impl Document {
         } else {
             self.rows.len()
         };
-        #[allow(clippy::indexing_slicing)]
-        for row in &mut self.rows[..until] {
-            start_with_comment = row.highlight(
-                &self.file_type.highlighting_options(),
-                word,
-                start_with_comment,
-            );
+        self.receive_highlighting();
+        if let Some(changed) = self.highlighter.changed.take() {
+            self.rehighlight_changed(changed, until);
+        }
+        if matcher.is_none() && until > self.highlighted.saturating_add(SYNC_HIGHLIGHT_ROWS) {
+            self.highlight_window(until);
+            return;
+        }
+        let start = if matcher.is_none() {
+            cmp::min(self.highlighted, until)
+        } else {
+            0
+        };
+        let mut state = start
+            .checked_sub(1)
+            .and_then(|index| self.rows.get(index))
+            .map_or(highlighting::State::Normal, |row| row.state().clone());
+        for (index, row) in self.rows.iter_mut().enumerate().take(until).skip(start) {
+            if index >= self.highlighted {
+                row.is_highlighted = false;
+            }
+            state = row.highlight(self.file_type.highlighting_options(), matcher, &state);
+        }
+        self.highlighted = cmp::max(self.highlighted, until);
+    }
+
+    pub fn todos(&mut self) -> Vec<Position> {
+        self.receive_highlighting();
+        if let Some(changed) = self.highlighter.changed.take() {
+            self.rehighlight_changed(changed, self.rows.len());
+        }
+        let mut state = highlighting::State::Normal;
+        let mut todos = Vec::new();
+        for (y, row) in self.rows.iter_mut().enumerate() {
+            state = row.highlight(self.file_type.highlighting_options(), None, &state);
+            todos.extend(row.todos().into_iter().map(|x| Position { x, y }));
+        }
+        self.highlighted = self.rows.len();
+        todos
+    }
+
+    fn rehighlight_changed(&mut self, changed: Range<usize>, until: usize) {
+        let end = cmp::min(self.highlighted, self.rows.len());
+        let mut state = changed
+            .start
+            .checked_sub(1)
+            .and_then(|index| self.rows.get(index))
+            .map_or(highlighting::State::Normal, |row| row.state().clone());
+        for (index, row) in self
+            .rows
+            .iter_mut()
+            .enumerate()
+            .take(end)
+            .skip(changed.start)
+        {
+            if index >= changed.end && row.is_highlighted_from(&state) {
+                break;
+            }
+            if index >= until {
+                self.highlighted = index;
+                break;
+            }
+            state = row.highlight(self.file_type.highlighting_options(), None, &state);
+        }
+    }
+
+    fn highlight_window(&mut self, until: usize) {
+        if self.highlighter.job.is_none() {
+            self.spawn_highlighter();
         }
+        let start = cmp::max(
+            self.highlighted,
+            until.saturating_sub(HIGHLIGHT_WINDOW_ROWS),
+        );
+        let mut state = start
+            .checked_sub(1)
+            .and_then(|index| self.rows.get(index))
+            .map_or(highlighting::State::Normal, |row| row.state().clone());
+        for row in self.rows.iter_mut().take(until).skip(start) {
+            state = row.highlight(self.file_type.highlighting_options(), None, &state);
+        }
+    }
+
+    fn spawn_highlighter(&mut self) {
+        let start = self.highlighted;
+        let lines: Vec<String> = self
+            .rows
+            .iter()
+            .skip(start)
+            .map(|row| row.as_str().to_string())
+            .collect();
+        let mut state = start
+            .checked_sub(1)
+            .and_then(|index| self.rows.get(index))
+            .map_or(highlighting::State::Normal, |row| row.state().clone());
+        let options = self.file_type.highlighting_options().clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let rows = lines
+                .iter()
+                .map(|line| {
+                    let mut row = Row::from(line.as_str());
+                    state = row.highlight(&options, None, &state);
+                    row
+                })
+                .collect();
+            let _ = sender.send((start, rows));
+        });
+        self.highlighter.job = Some(HighlightJob {
+            generation: self.highlighter.generation,
+            receiver,
+        });
+    }
+
+    fn receive_highlighting(&mut self) {
+        let Some(job) = &self.highlighter.job else {
+            return;
+        };
+        let (start, rows) = match job.receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.highlighter.job = None;
+                return;
+            }
+        };
+        let current = job.generation == self.highlighter.generation;
+        self.highlighter.job = None;
+        if !current || start > self.highlighted {
+            return;
+        }
+        let mut end = start;
+        for row in rows {
+            if end >= self.rows.len() {
+                break;
+            }
+            if end >= self.highlighted {
+                #[allow(clippy::indexing_slicing)]
+                let target = &mut self.rows[end];
+                let mut row = row;
+                row.set_semantic(target.semantic().to_vec());
+                *target = row;
+            }
+            end = end.saturating_add(1);
+        }
+        self.highlighted = cmp::max(self.highlighted, end);
+    }
+
+    pub fn is_highlighting(&self) -> bool {
+        self.highlighter.job.is_some()
     }
 }
+
+fn modified_time(file_name: &str) -> Option<SystemTime> {
+    fs::metadata(file_name)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn is_read_only(file_name: &str) -> bool {
+    fs::OpenOptions::new().write(true).open(file_name).is_err()
+}
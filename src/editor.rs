@@ -1,20 +1,54 @@
 use std::{
-    cmp, env,
+    cmp,
+    collections::{BTreeSet, HashMap, VecDeque},
+    env,
+    fs::{self, File},
+    io::{self, Read, Write},
+    iter,
+    ops::Range,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
-    usize,
 };
 
-use crate::{Document, Row, Terminal};
+use crate::{
+    browser::Browser,
+    case::CaseChange,
+    cli::{Args, FileArg},
+    completion, describe_keys,
+    diff::{Change, Comparison},
+    finder::Finder,
+    grep::{self, Quickfix},
+    hex::{self, HexView},
+    history,
+    jump_list::{Jump, JumpList},
+    lsp::{self, Client, Location, Response, Severity},
+    matcher::Case,
+    pager::Pager,
+    script::{Action, Hook, Script},
+    shell,
+    spell::SpellChecker,
+    status_bar,
+    tags::{self, Tag},
+    undo::{self, Travel},
+    Command, Config, Document, FileType, Key, Keymap, Layout, LineEnding, LineNumbers, Lookup,
+    Matcher, Rect, Row, SearchOptions, SplitDirection, Terminal, Theme, Window,
+};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
-    style,
-    terminal::enable_raw_mode,
+    cursor::CursorShape,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
+    style::Color,
 };
 
-const STATUS_FG_COLOR: style::Color = style::Color::Black;
-const STATUS_BG_COLOR: style::Color = style::Color::Grey;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
-const QUIT_TIMES: u8 = 3;
+const LOAD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const FINDER_HEIGHT: usize = 10;
+const POPUP_HEIGHT: usize = 8;
+const HOVER_HEIGHT: usize = 10;
+const MESSAGE_LOG_LENGTH: usize = 1000;
+const STATUS_MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum SearchDirection {
@@ -22,7 +56,99 @@ pub enum SearchDirection {
     Backword,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum ReplaceChoice {
+    Yes,
+    No,
+    All,
+    Each,
+    Quit,
+}
+
+#[derive(Debug, Clone)]
+struct VisualLine {
+    row: usize,
+    columns: Range<usize>,
+}
+
+#[derive(Debug)]
+struct View<'a> {
+    document: &'a Document,
+    cursor_position: &'a Position,
+    offset: &'a Position,
+    wrap_offset: usize,
+    rect: Rect,
+    config: &'a Config,
+    theme: &'a Theme,
+    line_numbers: LineNumbers,
+    soft_wrap: bool,
+    show_whitespace: bool,
+    indent_guides: bool,
+    changes: &'a [Option<Range<usize>>],
+    color_column: Option<usize>,
+    tab_width: usize,
+    active: bool,
+}
+
+impl View<'_> {
+    fn gutter_width(&self) -> usize {
+        let signs = usize::from(self.document.has_signs());
+        if self.line_numbers == LineNumbers::Off {
+            signs
+        } else {
+            self.document
+                .len()
+                .to_string()
+                .len()
+                .saturating_add(1)
+                .saturating_add(signs)
+        }
+    }
+
+    fn text_width(&self) -> usize {
+        self.rect.width.saturating_sub(self.gutter_width())
+    }
+
+    fn text_height(&self) -> usize {
+        self.rect.height.saturating_sub(1)
+    }
+
+    fn visual_lines(&self) -> Vec<VisualLine> {
+        let height = self.text_height();
+        let width = self.text_width();
+        let mut lines = Vec::new();
+        let mut index = self.offset.y;
+        let mut skip = self.wrap_offset;
+        while lines.len() < height {
+            let row = if let Some(row) = self.document.row(index) {
+                row
+            } else {
+                break;
+            };
+            if self.soft_wrap {
+                let tab_width = self.tab_width;
+                for range in row.wrap(width, tab_width).into_iter().skip(skip) {
+                    lines.push(VisualLine {
+                        row: index,
+                        columns: row.column(range.start, tab_width)
+                            ..row.column(range.end, tab_width),
+                    });
+                }
+            } else {
+                lines.push(VisualLine {
+                    row: index,
+                    columns: self.offset.x..self.offset.x.saturating_add(width),
+                });
+            }
+            skip = 0;
+            index = index.saturating_add(1);
+        }
+        lines.truncate(height);
+        lines
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -43,6 +169,14 @@ impl StatusMessage {
     }
 }
 
+#[derive(Debug, Default)]
+struct Buffer {
+    document: Document,
+    cursor_position: Position,
+    offset: Position,
+    wrap_offset: usize,
+}
+
 #[derive(Debug)]
 pub struct Editor {
     should_quit: bool,
@@ -52,246 +186,3497 @@ pub struct Editor {
     document: Document,
     status_message: StatusMessage,
     quit_times: u8,
-    highlighted_word: Option<String>,
+    highlighted_match: Option<Matcher>,
+    search_status: Option<String>,
+    search_options: SearchOptions,
+    last_search: Option<String>,
+    selection: Option<Position>,
+    matching_bracket: Option<Position>,
+    register: String,
+    recording: Option<Vec<KeyEvent>>,
+    last_macro: Vec<KeyEvent>,
+    replaying: VecDeque<KeyEvent>,
+    count: Option<usize>,
+    last_input: Instant,
+    last_swap: Instant,
+    finder: Option<Finder>,
+    quickfix: Option<Quickfix>,
+    quickfix_open: bool,
+    jump_list: JumpList,
+    comparison: Option<Comparison>,
+    pipe: Option<File>,
+    script: Script,
+    running_hook: bool,
+    browser: Option<Browser>,
+    start_dir: Option<PathBuf>,
+    hex: Option<HexView>,
+    start_hex: Vec<String>,
+    pager: Option<Pager>,
+    messages: Vec<String>,
+    logged: Option<Instant>,
+    lsp: HashMap<String, Option<Client>>,
+    word_popup: Option<Finder>,
+    hover: Option<Vec<String>>,
+    spell: Option<SpellChecker>,
+    config: Config,
+    keymap: Keymap,
+    pending_keys: Vec<Key>,
+    file_types: Vec<FileType>,
+    theme: Theme,
+    line_numbers: LineNumbers,
+    soft_wrap: bool,
+    show_whitespace: bool,
+    indent_guides: bool,
+    wrap_offset: usize,
+    buffers: Vec<Buffer>,
+    current_buffer: usize,
+    windows: Vec<Window>,
+    layout: Layout,
+    active_window: usize,
 }
 
 impl Editor {
-    pub fn run(&mut self) {
-        enable_raw_mode().unwrap();
+    pub fn run(&mut self) -> Result<(), crossterm::ErrorKind> {
+        self.recover_swaps()?;
+        self.center_cursor();
+        self.record_history();
+        self.run_hook(Hook::Open)?;
+        if let Some(dir) = self.start_dir.take() {
+            self.browse(dir)?;
+        }
+        for file_name in std::mem::take(&mut self.start_hex) {
+            self.hex_edit(&file_name)?;
+        }
+
+        loop {
+            self.log_status_message();
+            self.refresh_screen()?;
+            if self.should_quit {
+                self.record_history();
+                self.remove_swaps();
+                self.write_pipe();
+                return Ok(());
+            }
+            self.process_event()?;
+        }
+    }
+
+    pub fn new(args: &Args) -> Result<Self, crossterm::ErrorKind> {
+        let mut initial_status = String::new();
+        let pipe = Terminal::redirect_stdout().unwrap_or_else(|error| {
+            initial_status = format!("ERR: Could not open terminal: {}", error);
+            None
+        });
+        let mut config = Config::load(args.config.as_deref()).unwrap_or_else(|error| {
+            initial_status = format!("ERR: {}", error);
+            Config::default()
+        });
+        if let Some(theme) = &args.theme {
+            config.theme.clone_from(theme);
+        }
+        let (mut file_types, errors) = FileType::load();
+        if !errors.is_empty() {
+            initial_status = format!("ERR: {}", errors.join("; "));
+        }
+        for file_type in &mut file_types {
+            if let Some(overrides) = config.file_type(&file_type.name()) {
+                file_type.add_keywords(overrides);
+            }
+        }
+        let spell = config
+            .spell_check
+            .then(|| SpellChecker::load(config.spell_dictionary.as_deref()))
+            .and_then(|result| {
+                result
+                    .map_err(|error| initial_status = format!("ERR: {}", error))
+                    .ok()
+            });
+        let mut buffers = Vec::new();
+        let mut start_dir = None;
+        let mut start_hex = Vec::new();
+        for FileArg {
+            path: file_name,
+            location,
+        } in &args.files
+        {
+            if file_name == "-" {
+                let mut bytes = Vec::new();
+                if let Err(error) = io::stdin().read_to_end(&mut bytes) {
+                    initial_status = format!("ERR: Could not read stdin: {}", error);
+                }
+                buffers.push(Buffer {
+                    document: Document::from_bytes(&bytes, &file_types),
+                    ..Buffer::default()
+                });
+                continue;
+            }
+            if Path::new(file_name).is_dir() {
+                start_dir = Some(PathBuf::from(file_name));
+                continue;
+            }
+            if !args.diff && hex::is_binary(file_name) {
+                start_hex.push(file_name.clone());
+                continue;
+            }
+            if let Ok(mut document) = Document::open(file_name, &file_types) {
+                if args.read_only {
+                    document.set_read_only(true);
+                }
+                if config.persistent_undo {
+                    document.enable_persistent_undo(config.undo_levels);
+                }
+                let cursor_position = location
+                    .map(|(line, column)| Position {
+                        x: column.unwrap_or_default().saturating_sub(1),
+                        y: line.saturating_sub(1),
+                    })
+                    .or_else(|| history::find(file_name))
+                    .map(|position| clamp_position(&document, position))
+                    .unwrap_or_default();
+                buffers.push(Buffer {
+                    document,
+                    cursor_position,
+                    ..Buffer::default()
+                });
+            } else {
+                initial_status = format!("ERR: Could not open file: {}", file_name);
+            }
+        }
+        if buffers.is_empty() {
+            buffers.push(Buffer::default());
+        }
+        let (windows, layout, comparison) = if args.diff && buffers.len() == 2 {
+            let windows = buffers
+                .iter()
+                .enumerate()
+                .map(|(buffer, state)| Window {
+                    buffer,
+                    cursor_position: state.cursor_position.clone(),
+                    ..Window::default()
+                })
+                .collect();
+            let mut layout = Layout::default();
+            layout.split(0, 1, SplitDirection::Vertical);
+            (windows, layout, Some(Comparison::new(0, 1)))
+        } else {
+            (vec![Window::default()], Layout::default(), None)
+        };
+        let document = std::mem::take(&mut buffers[0].document);
+        let mut theme = Theme::load(&config.theme).unwrap_or_else(|error| {
+            initial_status = format!("ERR: {}", error);
+            Theme::default()
+        });
+        theme.adapt(config.color_support.unwrap_or_else(Terminal::color_support));
+        let mut keymap = Keymap::new(config.keymap);
+        let script = Script::load().unwrap_or_else(|error| {
+            initial_status = format!("ERR: {}", error);
+            Script::default()
+        });
+        for (keys, command) in script.bindings.iter().rev() {
+            keymap.bind(keys.clone(), *command);
+        }
+        if initial_status.is_empty() {
+            initial_status = format!(
+                "HELP: {} = help | {} = find | {} = replace | {} = save | {} = open | {} = select | {} = quit",
+                keymap.describe(Command::Help),
+                keymap.describe(Command::Find),
+                keymap.describe(Command::Replace),
+                keymap.describe(Command::Save),
+                keymap.describe(Command::Open),
+                keymap.describe(Command::ToggleSelection),
+                keymap.describe(Command::Quit),
+            );
+        }
+
+        let terminal = Terminal::defalut()?;
+        Ok(Self {
+            should_quit: false,
+            terminal,
+            cursor_position: buffers[0].cursor_position.clone(),
+            document,
+            offset: Position::default(),
+            status_message: StatusMessage::from(initial_status),
+            quit_times: config.quit_times,
+            highlighted_match: None,
+            search_status: None,
+            search_options: SearchOptions::default(),
+            last_search: None,
+            selection: None,
+            matching_bracket: None,
+            register: String::new(),
+            recording: None,
+            last_macro: Vec::new(),
+            replaying: VecDeque::new(),
+            count: None,
+            last_input: Instant::now(),
+            last_swap: Instant::now(),
+            finder: None,
+            quickfix: None,
+            quickfix_open: false,
+            jump_list: JumpList::default(),
+            comparison,
+            pipe,
+            script,
+            running_hook: false,
+            browser: None,
+            start_dir,
+            hex: None,
+            start_hex,
+            pager: None,
+            messages: Vec::new(),
+            logged: None,
+            lsp: HashMap::new(),
+            word_popup: None,
+            hover: None,
+            spell,
+            line_numbers: config.line_numbers,
+            soft_wrap: config.soft_wrap,
+            show_whitespace: config.show_whitespace,
+            indent_guides: config.indent_guides,
+            wrap_offset: 0,
+            buffers,
+            current_buffer: 0,
+            windows,
+            layout,
+            active_window: 0,
+            config,
+            keymap,
+            pending_keys: Vec::new(),
+            file_types,
+            theme,
+        })
+    }
+
+    fn refresh_screen(&mut self) -> Result<(), std::io::Error> {
+        Terminal::cursor_hide();
+        Terminal::cursor_position(&Position::default());
+
+        if self.should_quit {
+            Terminal::restore();
+        } else {
+            Terminal::begin_frame();
+            self.update_comparison();
+            let (windows, separators) = self.window_rects();
+            for (window, rect) in &windows {
+                if let Some(state) = self.windows.get(*window) {
+                    let until = Some(state.offset.y.saturating_add(rect.height));
+                    if *window != self.active_window && state.buffer != self.current_buffer {
+                        if let Some(buffer) = self.buffers.get_mut(state.buffer) {
+                            buffer.document.highlight(None, until);
+                            buffer.document.update_diff();
+                        }
+                    }
+                }
+            }
+            self.document.highlight(
+                self.highlighted_match.as_ref(),
+                Some(self.offset.y.saturating_add(self.text_height())),
+            );
+            self.document.update_diff();
+            self.matching_bracket = self.document.matching_bracket(&self.cursor_position);
+            for (window, rect) in windows {
+                if let Some(view) = self.window_view(window, rect) {
+                    self.draw_window(&view);
+                }
+            }
+            for separator in &separators {
+                Self::draw_separator(&self.theme, separator);
+            }
+            self.draw_browser();
+            self.draw_hex();
+            self.draw_pager();
+            self.draw_finder();
+            self.draw_quickfix();
+            self.draw_message_bar();
+            self.draw_word_popup();
+            self.draw_hover();
+            Terminal::end_frame();
+            let cursor = if self.pager.is_some() {
+                Position::default()
+            } else {
+                self.hex
+                    .as_ref()
+                    .map_or_else(|| self.screen_cursor(), HexView::screen_cursor)
+            };
+            Terminal::cursor_position(&cursor);
+            Terminal::set_cursor_shape(self.cursor_shape());
+        }
+        Terminal::cursor_show();
+        Terminal::flush()
+    }
+
+    fn log_status_message(&mut self) {
+        let StatusMessage { text, time } = &self.status_message;
+        if text.is_empty() || self.logged == Some(*time) {
+            return;
+        }
+        self.logged = Some(*time);
+        if self.messages.len() == MESSAGE_LOG_LENGTH {
+            self.messages.remove(0);
+        }
+        self.messages.push(text.clone());
+    }
+
+    fn cursor_shape(&self) -> CursorShape {
+        if self.selection.is_some() || self.hex.is_some() {
+            CursorShape::Block
+        } else {
+            CursorShape::Line
+        }
+    }
+
+    fn screen_cursor(&self) -> Position {
+        let rect = self.window_rect(self.active_window);
+        let mut x = self.cursor_column();
+        let mut y = self.cursor_position.y;
+        if self.soft_wrap {
+            let (_, columns) = self.wrapped_cursor();
+            let visual_lines = self.active_view().visual_lines();
+            x = cmp::min(x, columns.end).saturating_sub(columns.start);
+            y = visual_lines
+                .iter()
+                .position(|line| line.row == y && line.columns == columns)
+                .unwrap_or(visual_lines.len());
+        } else {
+            x = x.saturating_sub(self.offset.x);
+            y = y.saturating_sub(self.offset.y);
+        }
+        x = x.saturating_add(self.gutter_width()).saturating_add(rect.x);
+        y = y.saturating_add(rect.y);
+        Position { x, y }
+    }
+
+    fn is_any_loading(&self) -> bool {
+        self.document.is_loading()
+            || self
+                .buffers
+                .iter()
+                .any(|buffer| buffer.document.is_loading())
+    }
+
+    fn receive_rows(&mut self) {
+        let lossy = iter::once(&mut self.document)
+            .chain(self.buffers.iter_mut().map(|buffer| &mut buffer.document))
+            .filter_map(|document| {
+                document.receive_rows().then(|| {
+                    format!(
+                        "{} is not valid {}; opened read-only.",
+                        document.file_name.as_deref().unwrap_or("[No Name]"),
+                        document.encoding().name()
+                    )
+                })
+            })
+            .last();
+        if let Some(message) = lossy {
+            self.status_message = StatusMessage::from(message);
+        }
+    }
+
+    fn poll_timeout(&self) -> Option<Duration> {
+        let mut timeout = None;
+        if self.is_any_loading()
+            || self.documents().any(Document::is_highlighting)
+            || self.lsp.values().any(Option::is_some)
+        {
+            timeout = Some(LOAD_POLL_INTERVAL);
+        }
+        if let Some(delay) = self.config.auto_save {
+            if self.is_any_dirty() {
+                let remaining = (self.last_input + Duration::from_secs(delay))
+                    .saturating_duration_since(Instant::now());
+                timeout = Some(timeout.map_or(remaining, |timeout| cmp::min(timeout, remaining)));
+            }
+        }
+        if let Some(interval) = self.config.swap_interval {
+            if self.is_any_dirty() && self.last_input > self.last_swap {
+                let remaining = (self.last_swap + Duration::from_secs(interval))
+                    .saturating_duration_since(Instant::now());
+                timeout = Some(timeout.map_or(remaining, |timeout| cmp::min(timeout, remaining)));
+            }
+        }
+        let message = &self.status_message;
+        if !message.text.is_empty() && message.time.elapsed() < STATUS_MESSAGE_TIMEOUT {
+            let remaining =
+                (message.time + STATUS_MESSAGE_TIMEOUT).saturating_duration_since(Instant::now());
+            timeout = Some(timeout.map_or(remaining, |timeout| cmp::min(timeout, remaining)));
+        }
+        timeout
+    }
+
+    fn process_event(&mut self) -> Result<(), crossterm::ErrorKind> {
+        if let Some(interval) = self.config.swap_interval {
+            if self.last_input > self.last_swap
+                && self.last_swap.elapsed() >= Duration::from_secs(interval)
+            {
+                self.write_swaps();
+            }
+        }
+        if self.replaying.is_empty() {
+            self.receive_rows();
+            self.sync_lsp();
+            self.process_lsp()?;
+            if let Some(timeout) = self.poll_timeout() {
+                if !event::poll(timeout)? {
+                    self.process_idle()?;
+                    return Ok(());
+                }
+            }
+        }
+        let event = self.next_event()?;
+        if let Event::Key(_) | Event::Mouse(_) = event {
+            self.last_input = Instant::now();
+        }
+
+        match event {
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Release,
+                ..
+            }) => (),
+            Event::Key(pressed_key) => self.process_keypress(pressed_key)?,
+            Event::Mouse(mouse_event) => self.process_mouse(mouse_event),
+            Event::Resize(_, _) => {
+                self.terminal.update_size()?;
+                Terminal::clear_screen();
+                self.scroll();
+            }
+            Event::FocusGained => {
+                self.document.reload_diff_base();
+                self.check_disk_changes()?;
+            }
+            Event::FocusLost if self.config.auto_save.is_some() => self.auto_save()?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> Result<Event, crossterm::ErrorKind> {
+        loop {
+            match self.next_event()? {
+                Event::Key(KeyEvent {
+                    kind: KeyEventKind::Release,
+                    ..
+                }) => (),
+                event => return Ok(event),
+            }
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Event, crossterm::ErrorKind> {
+        if let Some(pressed_key) = self.replaying.pop_front() {
+            return Ok(Event::Key(pressed_key));
+        }
+        let event = event::read()?;
+        if let (Event::Key(pressed_key), Some(recording)) = (&event, self.recording.as_mut()) {
+            if pressed_key.kind != KeyEventKind::Release {
+                recording.push(*pressed_key);
+            }
+        }
+        Ok(event)
+    }
+
+    fn process_mouse(&mut self, mouse_event: MouseEvent) {
+        let x = usize::from(mouse_event.column);
+        let y = usize::from(mouse_event.row);
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(position) = self.mouse_position(x, y, true) {
+                    self.cursor_position = position;
+                    self.selection = None;
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(position) = self.mouse_position(x, y, false) {
+                    if self.selection.is_none() {
+                        self.selection = Some(self.cursor_position.clone());
+                    }
+                    self.cursor_position = position;
+                }
+            }
+            MouseEventKind::ScrollUp => self.scroll_lines(x, y, SearchDirection::Backword),
+            MouseEventKind::ScrollDown => self.scroll_lines(x, y, SearchDirection::Forward),
+            _ => return,
+        }
+        self.scroll();
+    }
+
+    fn window_at(&self, x: usize, y: usize) -> Option<(usize, Rect)> {
+        self.window_rects().0.into_iter().find(|(_, rect)| {
+            (rect.x..rect.x.saturating_add(rect.width)).contains(&x)
+                && (rect.y..rect.y.saturating_add(rect.height)).contains(&y)
+        })
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn mouse_position(&mut self, x: usize, y: usize, focus: bool) -> Option<Position> {
+        if focus {
+            let (window, rect) = self.window_at(x, y)?;
+            if y >= rect.y + rect.height.saturating_sub(1) {
+                return None;
+            }
+            if window != self.active_window {
+                self.focus_window(window);
+            }
+        }
+        let view = self.active_view();
+        let lines = view.visual_lines();
+        let line_index = y
+            .saturating_sub(view.rect.y)
+            .min(view.text_height().saturating_sub(1));
+        let (line, below_text) = match lines.get(line_index) {
+            Some(line) => (line, false),
+            None => (lines.last()?, true),
+        };
+        let row = self.document.row(line.row)?;
+        let tab_width = self.tab_width();
+        let mut column = line.columns.start + x.saturating_sub(view.rect.x + view.gutter_width());
+        if below_text {
+            column = usize::MAX;
+        } else if column >= line.columns.end && line.columns.end < row.width(tab_width) {
+            column = line.columns.end.saturating_sub(1);
+        }
+        Some(Position {
+            x: row.index_at(column, tab_width),
+            y: line.row,
+        })
+    }
+
+    fn scroll_lines(&mut self, x: usize, y: usize, direction: SearchDirection) {
+        const LINES: usize = 3;
+        if let Some((window, _)) = self.window_at(x, y) {
+            if window != self.active_window {
+                self.focus_window(window);
+            }
+        }
+        let last = self.document.len().saturating_sub(1);
+        self.offset.y = if direction == SearchDirection::Forward {
+            cmp::min(self.offset.y.saturating_add(LINES), last)
+        } else {
+            self.offset.y.saturating_sub(LINES)
+        };
+        self.wrap_offset = 0;
+        let visible = self.active_view().visual_lines();
+        let first = visible.first().map_or(self.offset.y, |line| line.row);
+        let last = visible.last().map_or(self.offset.y, |line| line.row);
+        let cursor = &mut self.cursor_position;
+        cursor.y = cursor.y.clamp(first, last);
+        cursor.x = cmp::min(cursor.x, self.document.row(cursor.y).map_or(0, Row::len));
+    }
+
+    fn scroll_page(&mut self, lines: usize, direction: SearchDirection) {
+        let lines = cmp::max(lines, 1);
+        let column = self.cursor_column();
+        let len = self.document.len();
+        let y = self.cursor_position.y;
+        if direction == SearchDirection::Forward {
+            let last = len.saturating_add(1).saturating_sub(self.text_height());
+            self.offset.y = cmp::min(
+                self.offset.y.saturating_add(lines),
+                cmp::max(last, self.offset.y),
+            );
+            self.cursor_position.y = cmp::min(y.saturating_add(lines), len);
+        } else {
+            self.offset.y = self.offset.y.saturating_sub(lines);
+            self.cursor_position.y = y.saturating_sub(lines);
+        }
+        self.wrap_offset = 0;
+        self.cursor_position.x = self
+            .document
+            .row(self.cursor_position.y)
+            .map_or(0, |row| row.index_at(column, self.tab_width()));
+    }
+
+    fn save(&mut self) -> Result<(), crossterm::ErrorKind> {
+        if self.document.file_name.is_none() {
+            let new_name = self.prompt("Save as: ", |_, _, _| {}).unwrap_or(None);
+            if new_name.is_none() {
+                self.status_message = StatusMessage::from("Save aborted.".to_string());
+                return Ok(());
+            }
+            self.document.file_name = new_name;
+        }
+
+        if self.document.changed_on_disk()
+            && !self
+                .confirm("File changed on disk. Overwrite? (y/n)")
+                .unwrap_or(false)
+        {
+            self.status_message = StatusMessage::from("Save aborted.".to_string());
+            return Ok(());
+        }
+
+        self.write_document()?;
+        Ok(())
+    }
+
+    fn write_document(&mut self) -> Result<bool, crossterm::ErrorKind> {
+        if self.config.format_on_save && !self.document.is_loading() && !self.format() {
+            return Ok(false);
+        }
+
+        if self.document.is_loading() {
+            self.status_message = StatusMessage::from("File is still loading.".to_string());
+            return Ok(false);
+        }
+        if let Err(error) = self.document.save(&self.file_types) {
+            self.status_message = StatusMessage::from(format!("Error writing file: {}", error));
+            return Ok(false);
+        }
+        self.status_message = StatusMessage::from("File saved successfully.".to_string());
+        self.run_hook(Hook::Save)?;
+        self.sync_lsp();
+        if let (Some(language_id), Some(file_name)) =
+            (self.start_lsp(), self.document.file_name.as_deref())
+        {
+            let path = history::absolute(file_name);
+            if let Some(Some(client)) = self.lsp.get_mut(&language_id) {
+                if let Err(error) = client.did_save(&path) {
+                    self.stop_lsp(&language_id, &error);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn run_hook(&mut self, hook: Hook) -> Result<(), crossterm::ErrorKind> {
+        if self.running_hook {
+            return Ok(());
+        }
+        self.running_hook = true;
+        let result = self
+            .script
+            .commands(hook)
+            .into_iter()
+            .try_for_each(|command| self.execute(command, false));
+        self.running_hook = false;
+        result
+    }
+
+    fn run_script(&mut self, index: usize) -> Result<(), crossterm::ErrorKind> {
+        for action in self.script.actions(index) {
+            match action {
+                Action::Run(command) => self.execute(command, false)?,
+                Action::Goto(line, column) => {
+                    self.cursor_position = clamp_position(
+                        &self.document,
+                        Position {
+                            x: column.saturating_sub(1),
+                            y: line.saturating_sub(1),
+                        },
+                    );
+                }
+                Action::Insert(text) => {
+                    if self.document.is_read_only() {
+                        self.status_message =
+                            StatusMessage::from("Buffer is read-only.".to_string());
+                        break;
+                    }
+                    self.cursor_position = self.document.insert_str(&self.cursor_position, &text);
+                }
+                Action::Buffer(index) => self.switch_buffer(index.saturating_sub(1)),
+                Action::Message(text) => self.status_message = StatusMessage::from(text),
+            }
+        }
+        self.document.seal_undo();
+        Ok(())
+    }
+
+    fn start_lsp(&mut self) -> Option<String> {
+        let language_id = lsp::language_id(&self.document.file_type());
+        let command = self.config.lsp.get(&language_id)?;
+        if !self.lsp.contains_key(&language_id) {
+            let root = env::current_dir().unwrap_or_default();
+            let client = match Client::start(command, &root) {
+                Ok(client) => Some(client),
+                Err(error) => {
+                    self.status_message =
+                        StatusMessage::from(format!("Could not start {}: {}", command, error));
+                    None
+                }
+            };
+            self.lsp.insert(language_id.clone(), client);
+        }
+        self.lsp.get(&language_id)?.is_some().then_some(language_id)
+    }
+
+    fn stop_lsp(&mut self, language_id: &str, error: &std::io::Error) {
+        self.status_message = StatusMessage::from(format!("Language server error: {}", error));
+        self.lsp.insert(language_id.to_string(), None);
+    }
+
+    fn sync_lsp(&mut self) {
+        if self.document.is_loading() {
+            return;
+        }
+        let Some(language_id) = self.start_lsp() else {
+            return;
+        };
+        let Some(file_name) = self.document.file_name.as_deref() else {
+            return;
+        };
+        let path = history::absolute(file_name);
+        if let Some(Some(client)) = self.lsp.get_mut(&language_id) {
+            let document = &self.document;
+            if let Err(error) =
+                client.sync(&path, &language_id, document.revision(), || document.text())
+            {
+                self.stop_lsp(&language_id, &error);
+            }
+        }
+    }
+
+    fn request_lsp(&mut self, command: Command) {
+        let Some(language_id) = self.start_lsp() else {
+            self.status_message =
+                StatusMessage::from("No language server for this file type.".to_string());
+            return;
+        };
+        let Some(file_name) = self.document.file_name.as_deref() else {
+            return;
+        };
+        let path = history::absolute(file_name);
+        let Position { x, y } = self.cursor_position;
+        let character = self
+            .document
+            .row(y)
+            .map_or(0, |row| lsp::utf16_column(row.as_str(), x));
+        self.sync_lsp();
+        if let Some(Some(client)) = self.lsp.get_mut(&language_id) {
+            let result = match command {
+                Command::Complete => client.completion(&path, y, character),
+                Command::FindReferences => client.references(&path, y, character),
+                Command::Hover => client.hover(&path, y, character),
+                _ => client.definition(&path, y, character),
+            };
+            if let Err(error) = result {
+                self.stop_lsp(&language_id, &error);
+            }
+        }
+    }
+
+    fn goto_tag(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some((_, name)) = self.word_under_cursor() else {
+            self.status_message = StatusMessage::from("No symbol under cursor.".to_string());
+            return Ok(());
+        };
+        let start = self
+            .document
+            .file_name
+            .as_deref()
+            .map(history::absolute)
+            .and_then(|path| Path::new(&path).parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| env::current_dir().unwrap_or_default());
+        let tags = tags::lookup(&name, &start);
+        let tag = match tags.as_slice() {
+            [] => {
+                self.status_message = StatusMessage::from(format!("No tag for {}.", name));
+                return Ok(());
+            }
+            [tag] => tag.clone(),
+            _ => {
+                let descriptions: Vec<String> = tags.iter().map(Tag::describe).collect();
+                let finder = Finder::from_files(descriptions.clone());
+                let Some(Some(selection)) = self.pick(&format!("Tag {}: ", name), finder) else {
+                    self.status_message = StatusMessage::from("Tag jump aborted.".to_string());
+                    return Ok(());
+                };
+                let Some(tag) = descriptions
+                    .iter()
+                    .position(|description| *description == selection)
+                    .and_then(|index| tags.get(index))
+                else {
+                    return Ok(());
+                };
+                tag.clone()
+            }
+        };
+        self.jump_to_match(&grep::Match {
+            path: tag.path,
+            line: tag.line,
+            column: 0,
+            text: tag.text,
+        })?;
+        let matcher = Matcher::new(
+            &name,
+            SearchOptions {
+                case: Case::Sensitive,
+                whole_word: true,
+            },
+        );
+        if let Some(found) = matcher.ok().and_then(|matcher| {
+            self.document
+                .row(self.cursor_position.y)?
+                .find(&matcher, 0, SearchDirection::Forward)
+        }) {
+            self.cursor_position.x = found.start;
+            self.scroll();
+        }
+        Ok(())
+    }
+
+    fn process_lsp(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let responses: Vec<Response> = self
+            .lsp
+            .values_mut()
+            .flatten()
+            .flat_map(Client::poll)
+            .collect();
+        for response in responses {
+            match response {
+                Response::Diagnostics(path, diagnostics) => {
+                    for document in iter::once(&mut self.document)
+                        .chain(self.buffers.iter_mut().map(|buffer| &mut buffer.document))
+                    {
+                        if document
+                            .file_name
+                            .as_deref()
+                            .map(history::absolute)
+                            .as_ref()
+                            == Some(&path)
+                        {
+                            document.set_diagnostics(diagnostics.clone());
+                        }
+                    }
+                }
+                Response::SemanticTokens(path, tokens) => {
+                    for document in iter::once(&mut self.document)
+                        .chain(self.buffers.iter_mut().map(|buffer| &mut buffer.document))
+                    {
+                        if document
+                            .file_name
+                            .as_deref()
+                            .map(history::absolute)
+                            .as_ref()
+                            == Some(&path)
+                        {
+                            document.set_semantic_tokens(&tokens);
+                        }
+                    }
+                }
+                Response::Completion(words) => self.complete(words),
+                Response::Definition(path, line, character) => {
+                    let origin = self.location();
+                    self.open_file(&path)?;
+                    let y = cmp::min(line, self.document.len());
+                    let x = self
+                        .document
+                        .row(y)
+                        .map_or(0, |row| lsp::grapheme_column(row.as_str(), character));
+                    self.cursor_position = Position { x, y };
+                    self.scroll();
+                    self.record_jump(origin);
+                }
+                Response::References(locations) => self.show_references(&locations)?,
+                Response::Hover(lines) => {
+                    if lines.is_empty() {
+                        self.status_message =
+                            StatusMessage::from("No hover information.".to_string());
+                    } else {
+                        self.hover = Some(lines);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn show_references(&mut self, locations: &[Location]) -> Result<(), crossterm::ErrorKind> {
+        if locations.is_empty() {
+            self.status_message = StatusMessage::from("No references found.".to_string());
+            return Ok(());
+        }
+        let root = env::current_dir().unwrap_or_default();
+        let mut files: HashMap<&str, Vec<String>> = HashMap::new();
+        let mut matches = Vec::new();
+        for location in locations {
+            let lines = files.entry(&location.path).or_insert_with(|| {
+                iter::once(&self.document)
+                    .chain(self.buffers.iter().map(|buffer| &buffer.document))
+                    .find(|document| {
+                        document
+                            .file_name
+                            .as_deref()
+                            .map(history::absolute)
+                            .as_ref()
+                            == Some(&location.path)
+                    })
+                    .map_or_else(
+                        || {
+                            fs::read_to_string(&location.path)
+                                .map(|text| text.lines().map(str::to_string).collect())
+                                .unwrap_or_default()
+                        },
+                        |document| {
+                            (0..document.len())
+                                .filter_map(|y| document.row(y))
+                                .map(|row| row.as_str().to_string())
+                                .collect()
+                        },
+                    )
+            });
+            let text = lines.get(location.line).cloned().unwrap_or_default();
+            let path = Path::new(&location.path);
+            matches.push(grep::Match {
+                path: path
+                    .strip_prefix(&root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .to_string(),
+                line: location.line,
+                column: lsp::grapheme_column(&text, location.character),
+                text: text.trim().to_string(),
+            });
+        }
+        let query = self
+            .word_under_cursor()
+            .map_or_else(|| "references".to_string(), |(_, word)| word);
+        self.quickfix = Some(Quickfix::new(query, matches));
+        self.show_quickfix()
+    }
+
+    fn toggle_spell_check(&mut self) {
+        if self.spell.take().is_some() {
+            self.status_message = StatusMessage::from("Spell checking off.".to_string());
+            return;
+        }
+        match SpellChecker::load(self.config.spell_dictionary.as_deref()) {
+            Ok(spell) => {
+                self.spell = Some(spell);
+                self.status_message = StatusMessage::from("Spell checking on.".to_string());
+            }
+            Err(error) => self.status_message = StatusMessage::from(error),
+        }
+    }
+
+    fn word_under_cursor(&self) -> Option<(Range<usize>, String)> {
+        let row = self.document.row(self.cursor_position.y)?;
+        let word = row.word_at(self.cursor_position.x)?;
+        let text = row.slice(word.start, word.end);
+        Some((word, text))
+    }
+
+    fn change_case(&mut self, change: CaseChange) {
+        let (start, end) = if let Some(range) = self.selection_range() {
+            range
+        } else if let Some((word, _)) = self.word_under_cursor() {
+            let y = self.cursor_position.y;
+            (Position { x: word.start, y }, Position { x: word.end, y })
+        } else {
+            self.status_message = StatusMessage::from("No word under cursor.".to_string());
+            return;
+        };
+        let text = self.document.extract_range(&start, &end);
+        let changed = change.apply(&text);
+        if changed == text {
+            return;
+        }
+        let end = self.document.replace(&start, &end, &changed);
+        if self.selection.is_some() {
+            self.selection = Some(start);
+        }
+        self.cursor_position = end;
+    }
+
+    fn format(&mut self) -> bool {
+        let Some(command) = self
+            .config
+            .format_command(&self.document.file_type())
+            .cloned()
+        else {
+            self.status_message = StatusMessage::from(format!(
+                "No formatter configured for {}.",
+                self.document.file_type()
+            ));
+            return true;
+        };
+        let output = match shell::filter(&command, self.document.text()) {
+            Ok(output) => output,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERR: {}: {}", command, error));
+                return false;
+            }
+        };
+        let lines: Vec<&str> = output.lines().collect();
+        let Some(alignment) = self.document.replace_lines(&lines) else {
+            self.status_message = StatusMessage::from("Already formatted.".to_string());
+            return true;
+        };
+        let y = self.cursor_position.y;
+        self.cursor_position.y = alignment
+            .iter()
+            .position(|line| *line == Some(y))
+            .or_else(|| {
+                alignment
+                    .iter()
+                    .rposition(|line| line.is_some_and(|line| line < y))
+                    .map(|line| line.saturating_add(1))
+            })
+            .unwrap_or(0);
+        self.cursor_position = clamp_position(&self.document, self.cursor_position.clone());
+        self.selection = None;
+        self.status_message = StatusMessage::from(format!("Formatted with {}.", command));
+        true
+    }
+
+    fn filter_shell(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let target = if self.selection.is_some() {
+            "selection"
+        } else {
+            "buffer"
+        };
+        let prompt = format!("Filter {} through: ", target);
+        let Some(command) = self.prompt(&prompt, |_, _, _| {})? else {
+            self.status_message = StatusMessage::from("Filter aborted.".to_string());
+            return Ok(());
+        };
+        let (start, end) = self.selection_range().unwrap_or_else(|| {
+            let y = self.document.len().saturating_sub(1);
+            let x = self.document.row(y).map_or(0, Row::len);
+            (Position::default(), Position { x, y })
+        });
+        let mut input = self.document.extract_range(&start, &end);
+        let line_based = !input.ends_with('\n');
+        if line_based {
+            input.push('\n');
+        }
+        let mut output = match shell::filter(&command, input) {
+            Ok(output) => output,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERR: {}: {}", command, error));
+                return Ok(());
+            }
+        };
+        if line_based && output.ends_with('\n') {
+            output.pop();
+        }
+        let end = self.document.replace(&start, &end, &output);
+        if self.selection.is_some() {
+            self.selection = Some(start);
+            self.cursor_position = end;
+        } else {
+            self.cursor_position = clamp_position(&self.document, self.cursor_position.clone());
+        }
+        self.status_message =
+            StatusMessage::from(format!("Filtered {} through {}.", target, command));
+        Ok(())
+    }
+
+    fn spell_suggest(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(spell) = &self.spell else {
+            self.status_message = StatusMessage::from("Spell checking is off.".to_string());
+            return Ok(());
+        };
+        let Some((word, text)) = self.word_under_cursor() else {
+            self.status_message = StatusMessage::from("No word under cursor.".to_string());
+            return Ok(());
+        };
+        if spell.check(&text) {
+            self.status_message = StatusMessage::from(format!("\"{}\" is correct.", text));
+            return Ok(());
+        }
+        let suggestions = spell.suggest(&text);
+        if suggestions.is_empty() {
+            self.status_message = StatusMessage::from(format!("No suggestions for \"{}\".", text));
+            return Ok(());
+        }
+        let y = self.cursor_position.y;
+        self.cursor_position.x = word.end;
+        if let Some(choice) = self.choose(suggestions)? {
+            self.cursor_position = self.document.replace(
+                &Position { x: word.start, y },
+                &Position { x: word.end, y },
+                &choice,
+            );
+        }
+        Ok(())
+    }
+
+    fn add_to_dictionary(&mut self) {
+        let Some((_, text)) = self.word_under_cursor() else {
+            self.status_message = StatusMessage::from("No word under cursor.".to_string());
+            return;
+        };
+        let Some(spell) = self.spell.as_mut() else {
+            self.status_message = StatusMessage::from("Spell checking is off.".to_string());
+            return;
+        };
+        self.status_message = match spell.add(&text) {
+            Ok(()) => StatusMessage::from(format!("Added \"{}\" to the dictionary.", text)),
+            Err(error) => StatusMessage::from(format!("Could not add word: {}", error)),
+        };
+    }
+
+    fn choose(&mut self, items: Vec<String>) -> Result<Option<String>, crossterm::ErrorKind> {
+        self.word_popup = Some(Finder::from_files(items));
+        let mut choice = None;
+        while let Some(popup) = self.word_popup.as_ref() {
+            let selection = popup.selection().cloned();
+            self.refresh_screen()?;
+            let Event::Key(pressed_key) = self.read_event()? else {
+                continue;
+            };
+            let Some(popup) = self.word_popup.as_mut() else {
+                break;
+            };
+            match pressed_key.code {
+                KeyCode::Up => popup.select_previous(),
+                KeyCode::Down | KeyCode::Tab => popup.select_next(),
+                KeyCode::Enter => {
+                    choice = selection;
+                    self.word_popup = None;
+                }
+                KeyCode::Esc => self.word_popup = None,
+                _ => (),
+            }
+        }
+        Ok(choice)
+    }
+
+    fn word_prefix(&self) -> Option<(Position, String)> {
+        let Position { x, y } = self.cursor_position;
+        let row = self.document.row(y)?;
+        let start = row.word_start(x);
+        (start < x).then(|| (Position { x: start, y }, row.slice(start, x)))
+    }
+
+    fn word_completions(&self) -> Option<Vec<String>> {
+        let (_, prefix) = self.word_prefix()?;
+        let mut words = BTreeSet::new();
+        for document in self.documents() {
+            document.collect_words(&prefix, &mut words);
+        }
+        (!words.is_empty()).then(|| words.into_iter().collect())
+    }
+
+    fn complete_word(&mut self) -> Result<(), crossterm::ErrorKind> {
+        self.word_popup = self.word_completions().map(Finder::from_files);
+        if self.word_popup.is_none() {
+            self.status_message = StatusMessage::from("No completions.".to_string());
+        }
+        while let Some(popup) = self.word_popup.as_mut() {
+            let selection = popup.selection().cloned();
+            self.refresh_screen()?;
+            let Event::Key(pressed_key) = self.read_event()? else {
+                continue;
+            };
+            let Some(popup) = self.word_popup.as_mut() else {
+                break;
+            };
+            match (pressed_key.modifiers, pressed_key.code) {
+                (_, KeyCode::Up) | (KeyModifiers::CONTROL, KeyCode::Char('p')) => {
+                    popup.select_previous();
+                }
+                (_, KeyCode::Down) | (KeyModifiers::CONTROL, KeyCode::Char('n')) => {
+                    popup.select_next();
+                }
+                (_, KeyCode::Enter | KeyCode::Tab) => {
+                    self.word_popup = None;
+                    if let (Some(word), Some((start, _))) = (selection, self.word_prefix()) {
+                        let end = self.cursor_position.clone();
+                        self.cursor_position = self.document.replace(&start, &end, &word);
+                    }
+                }
+                (_, KeyCode::Esc) => self.word_popup = None,
+                (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c))
+                    if c.is_alphanumeric() || c == '_' =>
+                {
+                    self.insert_char(c);
+                    self.word_popup = self.word_completions().map(Finder::from_files);
+                }
+                (_, KeyCode::Backspace) => {
+                    self.execute(Command::DeleteBackward, false)?;
+                    self.word_popup = self.word_completions().map(Finder::from_files);
+                }
+                _ => {
+                    self.word_popup = None;
+                    self.replaying.push_front(pressed_key);
+                }
+            }
+            self.scroll();
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self, words: Vec<String>) {
+        let Position { x, y } = self.cursor_position;
+        let Some(row) = self.document.row(y) else {
+            return;
+        };
+        let start = Position {
+            x: row.word_start(x),
+            y,
+        };
+        let prefix = row.slice(start.x, x);
+        let words: Vec<String> = words
+            .into_iter()
+            .filter(|word| word.starts_with(&prefix))
+            .collect();
+        match self.pick("Complete: ", Finder::from_files(words)) {
+            Some(Some(word)) => {
+                let end = self.cursor_position.clone();
+                self.cursor_position = self.document.replace(&start, &end, &word);
+                self.scroll();
+            }
+            Some(None) => {
+                self.status_message = StatusMessage::from("No completions.".to_string());
+            }
+            None => {
+                self.status_message = StatusMessage::from("Completion aborted.".to_string());
+            }
+        }
+    }
+
+    fn is_any_dirty(&self) -> bool {
+        (0..self.buffers.len())
+            .filter_map(|index| self.buffer_document(index))
+            .any(|document| {
+                document.is_dirty() && (self.pipe.is_none() || document.file_name.is_some())
+            })
+    }
+
+    fn buffer_document(&self, index: usize) -> Option<&Document> {
+        if index == self.current_buffer {
+            Some(&self.document)
+        } else {
+            self.buffers.get(index).map(|buffer| &buffer.document)
+        }
+    }
+
+    fn buffer_document_mut(&mut self, index: usize) -> Option<&mut Document> {
+        if index == self.current_buffer {
+            Some(&mut self.document)
+        } else {
+            self.buffers
+                .get_mut(index)
+                .map(|buffer| &mut buffer.document)
+        }
+    }
+
+    fn update_comparison(&mut self) {
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        let (left, right) = comparison.buffers();
+        let (Some(left_document), Some(right_document)) =
+            (self.buffer_document(left), self.buffer_document(right))
+        else {
+            return;
+        };
+        if left_document.is_loading() || right_document.is_loading() {
+            return;
+        }
+        let revisions = (left_document.revision(), right_document.revision());
+        if !comparison.is_current(revisions) {
+            let left_lines = left_document.lines();
+            let right_lines = right_document.lines();
+            if let Some(comparison) = &mut self.comparison {
+                comparison.update(&left_lines, &right_lines, revisions);
+            }
+            if let Some(document) = self.buffer_document_mut(left) {
+                document.set_diff_base(right_lines);
+            }
+            if let Some(document) = self.buffer_document_mut(right) {
+                document.set_diff_base(left_lines);
+            }
+        }
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        let Some(other) = comparison.other(self.current_buffer) else {
+            return;
+        };
+        let offset = Position {
+            x: self.offset.x,
+            y: comparison.counterpart(self.current_buffer, self.offset.y),
+        };
+        for (index, window) in self.windows.iter_mut().enumerate() {
+            if index != self.active_window && window.buffer == other {
+                window.offset = offset.clone();
+            }
+        }
+    }
+
+    fn write_pipe(&mut self) {
+        let Some(mut pipe) = self.pipe.take() else {
+            return;
+        };
+        if let Some(document) = self.buffer_document(0) {
+            let _ = pipe.write_all(document.text().as_bytes());
+        }
+    }
+
+    fn buffer_name(&self, index: usize) -> String {
+        let Some(document) = self.buffer_document(index) else {
+            return String::new();
+        };
+        let mut name = document
+            .file_name
+            .clone()
+            .unwrap_or_else(|| "[No Name]".to_string());
+        if document.is_dirty() {
+            name.push('*');
+        }
+        name
+    }
+
+    fn switch_buffer(&mut self, index: usize) {
+        if index == self.current_buffer || index >= self.buffers.len() {
+            return;
+        }
+        #[allow(clippy::indexing_slicing)]
+        let current = &mut self.buffers[self.current_buffer];
+        std::mem::swap(&mut current.document, &mut self.document);
+        current.cursor_position = self.cursor_position.clone();
+        current.offset = self.offset.clone();
+        current.wrap_offset = self.wrap_offset;
+        #[allow(clippy::indexing_slicing)]
+        let next = &mut self.buffers[index];
+        std::mem::swap(&mut next.document, &mut self.document);
+        self.cursor_position = next.cursor_position.clone();
+        self.offset = next.offset.clone();
+        self.wrap_offset = next.wrap_offset;
+        self.current_buffer = index;
+        if let Some(window) = self.windows.get_mut(self.active_window) {
+            window.buffer = index;
+        }
+        self.selection = None;
+        self.document.unhighlight_rows(0);
+    }
+
+    fn store_window(&mut self) {
+        if let Some(window) = self.windows.get_mut(self.active_window) {
+            window.buffer = self.current_buffer;
+            window.cursor_position = self.cursor_position.clone();
+            window.offset = self.offset.clone();
+            window.wrap_offset = self.wrap_offset;
+        }
+    }
+
+    fn focus_window(&mut self, index: usize) {
+        let window = if let Some(window) = self.windows.get(index) {
+            window.clone()
+        } else {
+            return;
+        };
+        self.store_window();
+        self.active_window = index;
+        self.switch_buffer(window.buffer);
+        self.cursor_position = window.cursor_position;
+        self.offset = window.offset;
+        self.wrap_offset = window.wrap_offset;
+        self.selection = None;
+    }
+
+    fn split_window(&mut self, direction: SplitDirection) {
+        let rect = self.window_rect(self.active_window);
+        let too_small = match direction {
+            SplitDirection::Horizontal => rect.height < 4,
+            SplitDirection::Vertical => rect.width < 3,
+        };
+        if too_small {
+            self.status_message = StatusMessage::from("Window too small to split.".to_string());
+            return;
+        }
+        self.store_window();
+        let new_window = self.windows.len();
+        self.windows.push(
+            self.windows
+                .get(self.active_window)
+                .cloned()
+                .unwrap_or_default(),
+        );
+        self.layout.split(self.active_window, new_window, direction);
+        self.active_window = new_window;
+    }
+
+    fn close_window(&mut self) {
+        if self.windows.len() <= 1 {
+            self.status_message = StatusMessage::from("Cannot close the last window.".to_string());
+            return;
+        }
+        let closed = self.active_window;
+        self.layout.remove(closed);
+        self.windows.remove(closed);
+        let next = self.layout.windows().first().copied().unwrap_or_default();
+        self.active_window = usize::MAX;
+        self.focus_window(next);
+    }
+
+    fn cycle_window(&mut self) {
+        let windows = self.layout.windows();
+        let next = windows
+            .iter()
+            .position(|window| *window == self.active_window)
+            .and_then(|index| windows.get(index.saturating_add(1)))
+            .or_else(|| windows.first())
+            .copied()
+            .unwrap_or_default();
+        if next != self.active_window {
+            self.focus_window(next);
+        }
+    }
+
+    fn process_idle(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let idle = self.last_input.elapsed();
+        if let Some(delay) = self.config.auto_save {
+            if idle >= Duration::from_secs(delay) {
+                self.auto_save()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn documents(&self) -> impl Iterator<Item = &Document> {
+        let current_buffer = self.current_buffer;
+        iter::once(&self.document).chain(
+            self.buffers
+                .iter()
+                .enumerate()
+                .filter(move |(index, _)| *index != current_buffer)
+                .map(|(_, buffer)| &buffer.document),
+        )
+    }
+
+    fn write_swaps(&mut self) {
+        self.last_swap = Instant::now();
+        for document in self.documents() {
+            if document.is_dirty() && !document.is_loading() {
+                let _ = document.write_swap();
+            }
+        }
+    }
+
+    fn record_history(&self) {
+        let current_buffer = self.current_buffer;
+        let files: Vec<(String, Position)> = iter::once((&self.document, &self.cursor_position))
+            .chain(
+                self.buffers
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| *index != current_buffer)
+                    .map(|(_, buffer)| (&buffer.document, &buffer.cursor_position)),
+            )
+            .filter_map(|(document, position)| {
+                Some((document.file_name.clone()?, position.clone()))
+            })
+            .collect();
+        history::record(&files);
+    }
+
+    fn remove_swaps(&self) {
+        for document in self.documents() {
+            document.remove_swap();
+        }
+    }
+
+    fn recover_swaps(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let current_buffer = self.current_buffer;
+        for index in 0..self.buffers.len() {
+            self.switch_buffer(index);
+            self.recover_swap()?;
+        }
+        self.switch_buffer(current_buffer);
+        Ok(())
+    }
+
+    fn recover_swap(&mut self) -> Result<(), crossterm::ErrorKind> {
+        if self.document.is_loading() || !self.document.has_swap() {
+            return Ok(());
+        }
+        let file_name = self.document.file_name.clone().unwrap_or_default();
+        let stale = if self.document.is_swap_stale() {
+            " (file has changed since)"
+        } else {
+            ""
+        };
+        if self.confirm(&format!(
+            "Found unsaved changes to {} from a previous session{}. Restore? (y/n)",
+            file_name, stale
+        ))? {
+            self.document.restore_swap();
+            self.status_message = StatusMessage::from("Restored unsaved changes.".to_string());
+        } else {
+            self.document.remove_swap();
+            self.status_message = StatusMessage::from(String::new());
+        }
+        Ok(())
+    }
+
+    fn auto_save(&mut self) -> Result<(), crossterm::ErrorKind> {
+        self.last_input = Instant::now();
+        let current_buffer = self.current_buffer;
+        let pending: Vec<usize> = (0..self.buffers.len())
+            .filter(|index| {
+                let document = if *index == current_buffer {
+                    &self.document
+                } else if let Some(buffer) = self.buffers.get(*index) {
+                    &buffer.document
+                } else {
+                    return false;
+                };
+                document.is_dirty()
+                    && document.file_name.is_some()
+                    && !document.is_loading()
+                    && !document.changed_on_disk()
+            })
+            .collect();
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let selection = self.selection.take();
+        let mut saved: usize = 0;
+        for index in pending {
+            self.switch_buffer(index);
+            if self.write_document()? {
+                saved = saved.saturating_add(1);
+            }
+        }
+        self.switch_buffer(current_buffer);
+        self.selection = selection;
+        if saved > 0 {
+            self.status_message = StatusMessage::from(format!("Auto-saved {} file(s).", saved));
+        }
+        Ok(())
+    }
+
+    fn jump_to_hunk(&mut self, direction: SearchDirection) {
+        self.document.update_diff();
+        if let Some(y) = self.document.hunk(self.cursor_position.y, direction) {
+            self.cursor_position = Position { x: 0, y };
+        } else if self.document.has_diff() {
+            self.status_message = StatusMessage::from("No more hunks.".to_string());
+        } else {
+            self.status_message = StatusMessage::from("Not tracked by git.".to_string());
+        }
+    }
+
+    fn jump_to_diagnostic(&mut self, direction: SearchDirection) {
+        let Some(diagnostic) = self
+            .document
+            .next_diagnostic(self.cursor_position.y, direction)
+        else {
+            self.status_message = StatusMessage::from(if self.document.diagnostics().is_empty() {
+                "No diagnostics.".to_string()
+            } else {
+                "No more diagnostics.".to_string()
+            });
+            return;
+        };
+        let y = diagnostic.line;
+        let x = self.document.row(y).map_or(0, |row| {
+            lsp::grapheme_column(row.as_str(), diagnostic.character)
+        });
+        self.cursor_position = clamp_position(&self.document, Position { x, y });
+    }
+
+    fn check_disk_changes(&mut self) -> Result<(), crossterm::ErrorKind> {
+        if !self.document.changed_on_disk() {
+            return Ok(());
+        }
+        let message = if self.document.is_dirty() {
+            "File changed on disk. Reload and discard your changes? (y/n)"
+        } else {
+            "File changed on disk. Reload? (y/n)"
+        };
+        if !self.confirm(message)? {
+            self.document.ignore_disk_change();
+            self.status_message = StatusMessage::from(String::new());
+            return Ok(());
+        }
+        let Some(file_name) = self.document.file_name.clone() else {
+            return Ok(());
+        };
+        match Document::open(&file_name, &self.file_types) {
+            Ok(mut document) => {
+                document.set_read_only(self.document.is_read_only());
+                if let Some(levels) = self.document.undo_levels() {
+                    document.enable_persistent_undo(levels);
+                }
+                self.document = document;
+                let y = cmp::min(self.cursor_position.y, self.document.len());
+                let len = self.document.row(y).map_or(0, Row::len);
+                self.cursor_position = Position {
+                    x: cmp::min(self.cursor_position.x, len),
+                    y,
+                };
+                self.selection = None;
+                self.scroll();
+                self.status_message = StatusMessage::from("Reloaded from disk.".to_string());
+            }
+            Err(error) => {
+                self.status_message =
+                    StatusMessage::from(format!("ERR: Could not reload {}: {}", file_name, error));
+            }
+        }
+        Ok(())
+    }
+
+    fn confirm(&mut self, message: &str) -> Result<bool, crossterm::ErrorKind> {
+        self.status_message = StatusMessage::from(message.to_string());
+        self.refresh_screen()?;
+        loop {
+            if let Event::Key(pressed_key) = self.read_event()? {
+                match pressed_key.code {
+                    KeyCode::Char('y') => return Ok(true),
+                    KeyCode::Char('n') | KeyCode::Esc => return Ok(false),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn open(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let mut candidates = Vec::new();
+        let mut index: usize = 0;
+        let file_name = if let Some(file_name) = self
+            .prompt("Open: ", |_, key, input| {
+                if key.code != KeyCode::Tab {
+                    candidates.clear();
+                    return;
+                }
+                if candidates.is_empty() {
+                    candidates = completion::complete_path(input);
+                    index = 0;
+                    let common = completion::common_prefix(&candidates);
+                    if common.len() > input.len() {
+                        *input = common;
+                        candidates.clear();
+                        return;
+                    }
+                } else {
+                    index = index.saturating_add(1) % candidates.len();
+                }
+                if let Some(candidate) = candidates.get(index) {
+                    input.clone_from(candidate);
+                }
+            })
+            .unwrap_or(None)
+        {
+            file_name
+        } else {
+            self.status_message = StatusMessage::from("Open aborted.".to_string());
+            return Ok(());
+        };
+        self.open_file(&file_name)
+    }
+
+    fn browse(&mut self, root: PathBuf) -> Result<(), crossterm::ErrorKind> {
+        self.browser = Some(Browser::new(root));
+        let help = "Enter: open | n: new | r: rename | d: delete | Esc: close";
+        self.status_message = StatusMessage::from(help.to_string());
+        loop {
+            self.refresh_screen()?;
+            let Event::Key(pressed_key) = self.read_event()? else {
+                continue;
+            };
+            self.status_message = StatusMessage::from(help.to_string());
+            let Some(browser) = self.browser.as_mut() else {
+                break;
+            };
+            match pressed_key.code {
+                KeyCode::Up => browser.select_previous(),
+                KeyCode::Down => browser.select_next(),
+                KeyCode::Right => browser.expand(),
+                KeyCode::Left => browser.collapse(),
+                KeyCode::Enter => match browser.selection().cloned() {
+                    Some(entry) if entry.is_dir => browser.toggle(),
+                    Some(entry) => {
+                        self.browser = None;
+                        self.status_message = StatusMessage::from(String::new());
+                        self.open_file(&entry.path.to_string_lossy())?;
+                        return Ok(());
+                    }
+                    None => (),
+                },
+                KeyCode::Char('n') => self.browser_create()?,
+                KeyCode::Char('r') => self.browser_rename()?,
+                KeyCode::Char('d') => self.browser_delete()?,
+                KeyCode::Esc | KeyCode::Char('q') => break,
+                _ => (),
+            }
+        }
+        self.browser = None;
+        self.status_message = StatusMessage::from(String::new());
+        Ok(())
+    }
+
+    fn hex_edit(&mut self, file_name: &str) -> Result<(), crossterm::ErrorKind> {
+        match HexView::open(file_name) {
+            Ok(hex) => self.hex = Some(hex),
+            Err(error) => {
+                self.status_message =
+                    StatusMessage::from(format!("ERR: Could not open {}: {}", file_name, error));
+                return Ok(());
+            }
+        }
+        let help = "Tab: hex/ASCII | Ctrl-S: save | Esc: close";
+        self.status_message = StatusMessage::from(help.to_string());
+        loop {
+            let height = usize::from(self.terminal.size().height);
+            if let Some(hex) = self.hex.as_mut() {
+                hex.scroll(height);
+            }
+            self.refresh_screen()?;
+            let Event::Key(pressed_key) = self.read_event()? else {
+                continue;
+            };
+            if matches!(
+                (pressed_key.modifiers, pressed_key.code),
+                (KeyModifiers::CONTROL, KeyCode::Char('q')) | (_, KeyCode::Esc)
+            ) {
+                let dirty = self.hex.as_ref().is_some_and(HexView::is_dirty);
+                if !dirty || self.confirm("Discard unsaved changes? (y/n)")? {
+                    break;
+                }
+                self.status_message = StatusMessage::from(help.to_string());
+                continue;
+            }
+            let Some(hex) = self.hex.as_mut() else {
+                break;
+            };
+            #[allow(clippy::cast_possible_wrap)]
+            let line = hex::BYTES_PER_LINE as isize;
+            #[allow(clippy::cast_possible_wrap)]
+            let page = line.saturating_mul(height as isize);
+            match (pressed_key.modifiers, pressed_key.code) {
+                (_, KeyCode::Up) => hex.move_by(-line),
+                (_, KeyCode::Down) => hex.move_by(line),
+                (_, KeyCode::Left) => hex.move_by(-1),
+                (_, KeyCode::Right) => hex.move_by(1),
+                (_, KeyCode::PageUp) => hex.move_by(-page),
+                (_, KeyCode::PageDown) => hex.move_by(page),
+                (_, KeyCode::Home) => hex.move_to(hex.line_start()),
+                (_, KeyCode::End) => {
+                    hex.move_to(hex.line_start().saturating_add(hex::BYTES_PER_LINE - 1));
+                }
+                (_, KeyCode::Tab) => hex.toggle_column(),
+                (KeyModifiers::CONTROL, KeyCode::Char('s')) => {
+                    self.status_message = StatusMessage::from(match hex.save() {
+                        Ok(()) => format!("Wrote {} bytes.", hex.len()),
+                        Err(error) => format!("Error writing file: {}", error),
+                    });
+                    continue;
+                }
+                (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) if !hex.input(c) => {
+                    self.status_message = StatusMessage::from(format!("Invalid input: {}", c));
+                    continue;
+                }
+                _ => (),
+            }
+            self.status_message = StatusMessage::from(help.to_string());
+        }
+        self.hex = None;
+        self.status_message = StatusMessage::from(String::new());
+        Ok(())
+    }
+
+    fn show_pager(&mut self, pager: Pager) -> Result<(), crossterm::ErrorKind> {
+        self.pager = Some(pager);
+        loop {
+            self.refresh_screen()?;
+            let Event::Key(pressed_key) = self.read_event()? else {
+                continue;
+            };
+            let height = usize::from(self.terminal.size().height);
+            let Some(pager) = self.pager.as_mut() else {
+                break;
+            };
+            #[allow(clippy::cast_possible_wrap)]
+            let page = height as isize;
+            match pressed_key.code {
+                KeyCode::Up => pager.scroll_by(-1, height),
+                KeyCode::Down | KeyCode::Enter => pager.scroll_by(1, height),
+                KeyCode::PageUp => pager.scroll_by(-page, height),
+                KeyCode::PageDown | KeyCode::Char(' ') => pager.scroll_by(page, height),
+                KeyCode::Home => pager.scroll_by(isize::MIN, height),
+                KeyCode::End => pager.scroll_by(isize::MAX, height),
+                KeyCode::Esc | KeyCode::Char('q') | KeyCode::F(1) => break,
+                _ => (),
+            }
+        }
+        self.pager = None;
+        Ok(())
+    }
+
+    fn browser_create(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(dir) = self.browser.as_ref().map(Browser::target_dir) else {
+            return Ok(());
+        };
+        let Some(name) = self.prompt("New file (end with / for a directory): ", |_, _, _| {})?
+        else {
+            return Ok(());
+        };
+        let path = dir.join(&name);
+        let result = if name.ends_with('/') {
+            fs::create_dir_all(&path)
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map(|_| ())
+        };
+        self.browser_finish(result, &path);
+        Ok(())
+    }
+
+    fn browser_rename(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(entry) = self.browser.as_ref().and_then(Browser::selection).cloned() else {
+            return Ok(());
+        };
+        let Some(name) = self.prompt(&format!("Rename {} to: ", entry.name), |_, _, _| {})? else {
+            return Ok(());
+        };
+        let path = entry
+            .path
+            .parent()
+            .map_or_else(|| PathBuf::from(&name), |parent| parent.join(&name));
+        let result = fs::rename(&entry.path, &path);
+        self.browser_finish(result, &path);
+        Ok(())
+    }
+
+    fn browser_delete(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(entry) = self.browser.as_ref().and_then(Browser::selection).cloned() else {
+            return Ok(());
+        };
+        if !self.confirm(&format!("Delete {}? (y/n)", entry.name))? {
+            return Ok(());
+        }
+        let result = if entry.is_dir {
+            fs::remove_dir(&entry.path)
+        } else {
+            fs::remove_file(&entry.path)
+        };
+        self.browser_finish(result, &entry.path);
+        Ok(())
+    }
+
+    fn browser_finish(&mut self, result: Result<(), std::io::Error>, path: &Path) {
+        if let Some(browser) = self.browser.as_mut() {
+            browser.refresh();
+            browser.select(path);
+        }
+        if let Err(error) = result {
+            self.status_message = StatusMessage::from(format!("ERR: {}", error));
+        }
+    }
+
+    fn find_file(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let root = env::current_dir().unwrap_or_default();
+        self.pick_file("Find file: ", Finder::new(&root))
+    }
+
+    fn recent_files(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let files = history::load()
+            .into_iter()
+            .map(|entry| entry.path)
+            .filter(|path| Path::new(path).is_file())
+            .collect();
+        self.pick_file("Recent files: ", Finder::from_files(files))
+    }
+
+    fn reopen_last(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let current = self.document.file_name.as_deref().map(history::absolute);
+        let last = history::load()
+            .into_iter()
+            .map(|entry| entry.path)
+            .find(|path| Some(path) != current.as_ref() && Path::new(path).is_file());
+        if let Some(path) = last {
+            self.open_file(&path)?;
+        } else {
+            self.status_message = StatusMessage::from("No recent files.".to_string());
+        }
+        Ok(())
+    }
+
+    fn find_in_files(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(query) = self.prompt("Find in files (re: for regex): ", |_, _, _| {})? else {
+            self.status_message = StatusMessage::from("Find aborted.".to_string());
+            return Ok(());
+        };
+        let matcher = match Matcher::new(&query, self.search_options) {
+            Ok(matcher) => matcher,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("Invalid regex: {}", error));
+                return Ok(());
+            }
+        };
+        self.status_message = StatusMessage::from(format!("Searching for {}...", query));
+        self.refresh_screen()?;
+        let root = env::current_dir().unwrap_or_default();
+        let matches = grep::search(&root, &matcher);
+        if matches.is_empty() {
+            self.status_message = StatusMessage::from(format!("No matches for {}.", query));
+            return Ok(());
+        }
+        self.quickfix = Some(Quickfix::new(query, matches));
+        self.show_quickfix()
+    }
+
+    fn set_file_type(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(name) = self.prompt("Set filetype: ", |_, _, _| {})? else {
+            self.status_message = StatusMessage::from("Set filetype aborted.".to_string());
+            return Ok(());
+        };
+        let name = name.trim();
+        let name = name.strip_prefix("filetype=").unwrap_or(name);
+        if let Some(file_type) = FileType::find(name, &self.file_types) {
+            self.document.set_file_type(file_type);
+            self.status_message =
+                StatusMessage::from(format!("Filetype set to {}.", self.document.file_type()));
+        } else {
+            self.status_message = StatusMessage::from(format!("Unknown filetype: {}", name));
+        }
+        Ok(())
+    }
+
+    fn todo_list(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(path) = self.document.file_name.clone() else {
+            self.status_message =
+                StatusMessage::from("Save the buffer to list its TODOs.".to_string());
+            return Ok(());
+        };
+        let matches: Vec<grep::Match> = self
+            .document
+            .todos()
+            .into_iter()
+            .map(|position| grep::Match {
+                path: path.clone(),
+                line: position.y,
+                column: position.x,
+                text: self
+                    .document
+                    .row(position.y)
+                    .map(|row| row.as_str().trim().to_string())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        if matches.is_empty() {
+            self.status_message = StatusMessage::from("No TODOs in this buffer.".to_string());
+            return Ok(());
+        }
+        self.quickfix = Some(Quickfix::new("TODO".to_string(), matches));
+        self.show_quickfix()
+    }
+
+    fn show_quickfix(&mut self) -> Result<(), crossterm::ErrorKind> {
+        if self.quickfix.is_none() {
+            self.status_message = StatusMessage::from("No search results.".to_string());
+            return Ok(());
+        }
+        self.quickfix_open = true;
+        while let Some(quickfix) = &self.quickfix {
+            self.status_message = StatusMessage::from(format!(
+                "{} matches for {} | Enter: open | Esc: close",
+                quickfix.matches().len(),
+                quickfix.query()
+            ));
+            self.refresh_screen()?;
+            let Event::Key(pressed_key) = self.read_event()? else {
+                continue;
+            };
+            let Some(quickfix) = self.quickfix.as_mut() else {
+                break;
+            };
+            match pressed_key.code {
+                KeyCode::Up => quickfix.select_previous(),
+                KeyCode::Down | KeyCode::Tab => quickfix.select_next(),
+                KeyCode::PageUp => {
+                    for _ in 0..FINDER_HEIGHT {
+                        quickfix.select_previous();
+                    }
+                }
+                KeyCode::PageDown => {
+                    for _ in 0..FINDER_HEIGHT {
+                        quickfix.select_next();
+                    }
+                }
+                KeyCode::Enter => {
+                    let found = quickfix.selection().cloned();
+                    self.quickfix_open = false;
+                    self.status_message = StatusMessage::from(String::new());
+                    if let Some(found) = found {
+                        self.jump_to_match(&found)?;
+                    }
+                    return Ok(());
+                }
+                KeyCode::Esc | KeyCode::Char('q') => break,
+                _ => (),
+            }
+        }
+        self.quickfix_open = false;
+        self.status_message = StatusMessage::from(String::new());
+        Ok(())
+    }
+
+    fn jump_to_match(&mut self, found: &grep::Match) -> Result<(), crossterm::ErrorKind> {
+        let origin = self.location();
+        self.open_file(&found.path)?;
+        let current = self.document.file_name.as_deref().map(history::absolute);
+        if current == Some(history::absolute(&found.path)) {
+            self.cursor_position = clamp_position(
+                &self.document,
+                Position {
+                    x: found.column,
+                    y: found.line,
+                },
+            );
+            self.scroll();
+            self.record_jump(origin);
+        }
+        Ok(())
+    }
+
+    fn pick_file(&mut self, prompt: &str, finder: Finder) -> Result<(), crossterm::ErrorKind> {
+        match self.pick(prompt, finder) {
+            Some(Some(file_name)) => self.open_file(&file_name)?,
+            Some(None) => {
+                self.status_message = StatusMessage::from("No matching files.".to_string());
+            }
+            None => {
+                self.status_message = StatusMessage::from("Find aborted.".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn pick(&mut self, prompt: &str, finder: Finder) -> Option<Option<String>> {
+        self.finder = Some(finder);
+        let query = self
+            .prompt_raw(prompt, |editor, key, query| {
+                if let Some(finder) = editor.finder.as_mut() {
+                    match key.code {
+                        KeyCode::Up => finder.select_previous(),
+                        KeyCode::Down | KeyCode::Tab => finder.select_next(),
+                        _ => finder.update(query),
+                    }
+                }
+            })
+            .unwrap_or(None);
+        let selection = self
+            .finder
+            .take()
+            .and_then(|finder| finder.selection().cloned());
+        query.map(|_| selection)
+    }
+
+    fn open_file(&mut self, file_name: &str) -> Result<(), crossterm::ErrorKind> {
+        let path = history::absolute(file_name);
+        let existing = (0..self.buffers.len()).find(|index| {
+            let document = if *index == self.current_buffer {
+                &self.document
+            } else {
+                #[allow(clippy::indexing_slicing)]
+                &self.buffers[*index].document
+            };
+            document.file_name.as_deref().map(history::absolute) == Some(path.clone())
+        });
+        if let Some(index) = existing {
+            self.switch_buffer(index);
+            return Ok(());
+        }
+        if hex::is_binary(file_name) {
+            self.hex_edit(file_name)?;
+            return Ok(());
+        }
+        match Document::open(file_name, &self.file_types) {
+            Ok(mut document) => {
+                if self.config.persistent_undo {
+                    document.enable_persistent_undo(self.config.undo_levels);
+                }
+                self.buffers.push(Buffer {
+                    document,
+                    ..Buffer::default()
+                });
+                self.switch_buffer(self.buffers.len().saturating_sub(1));
+                if let Some(position) = history::find(file_name) {
+                    self.cursor_position = clamp_position(&self.document, position);
+                    self.scroll();
+                }
+                self.recover_swap()?;
+                self.record_history();
+                self.run_hook(Hook::Open)?;
+            }
+            Err(_) => {
+                self.status_message =
+                    StatusMessage::from(format!("ERR: Could not open file: {}", file_name));
+            }
+        }
+        Ok(())
+    }
+
+    fn switch_buffer_prompt(&mut self) {
+        let list = (0..self.buffers.len())
+            .map(|index| format!("{}:{}", index.saturating_add(1), self.buffer_name(index)))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let answer = if let Some(answer) = self
+            .prompt(&format!("{} | Switch to: ", list), |_, _, _| {})
+            .unwrap_or(None)
+        {
+            answer
+        } else {
+            return;
+        };
+        let index = if let Ok(number) = answer.parse::<usize>() {
+            number.checked_sub(1)
+        } else {
+            (0..self.buffers.len()).find(|index| self.buffer_name(*index).contains(&answer))
+        };
+        if let Some(index) = index.filter(|index| *index < self.buffers.len()) {
+            self.switch_buffer(index);
+        } else {
+            self.status_message = StatusMessage::from(format!("No buffer matches {}", answer));
+        }
+    }
+
+    fn search(&mut self) {
+        let old_position = self.cursor_position.clone();
+        let mut direction = SearchDirection::Forward;
+        self.search_status = Some(self.search_options.describe());
+        let query = self
+            .prompt(
+                "Search (ESC to cancel, Arrows to navigate, re: for regex, Alt-c/Alt-w for case/word): ",
+                |editor, key, query| {
+                    let mut moved = false;
+                    match (key.modifiers, key.code) {
+                        (_, KeyCode::Right | KeyCode::Down) => {
+                            direction = SearchDirection::Forward;
+                            editor.move_cursor(KeyCode::Right);
+                            moved = true;
+                        }
+                        (_, KeyCode::Left | KeyCode::Up) => direction = SearchDirection::Backword,
+                        (KeyModifiers::ALT, KeyCode::Char('c')) => {
+                            editor.search_options.case = editor.search_options.case.next();
+                        }
+                        (KeyModifiers::ALT, KeyCode::Char('w')) => {
+                            editor.search_options.whole_word = !editor.search_options.whole_word;
+                        }
+                        _ => direction = SearchDirection::Forward,
+                    }
+                    let matcher = Matcher::new(query, editor.search_options).ok();
+                    if let Some(found) = matcher.as_ref().and_then(|matcher| {
+                        editor
+                            .document
+                            .find(matcher, &editor.cursor_position, direction)
+                    }) {
+                        editor.cursor_position = found.start;
+                        editor.scroll();
+                    } else if moved {
+                        editor.move_cursor(KeyCode::Left);
+                    }
+                    let options = editor.search_options.describe();
+                    editor.search_status = Some(matcher.as_ref().map_or(options.clone(), |matcher| {
+                        format!("{} {}", options, editor.match_counter(matcher))
+                    }));
+                    editor.highlighted_match = matcher;
+                },
+            )
+            .unwrap_or(None);
+        if query.is_none() {
+            self.cursor_position = old_position;
+            self.scroll();
+        } else {
+            self.last_search = query;
+        }
+        self.highlighted_match = None;
+        self.search_status = None;
+        self.document.unhighlight_rows(0);
+    }
+
+    fn search_again(&mut self, direction: SearchDirection) {
+        let Some(query) = self.last_search.clone() else {
+            self.status_message = StatusMessage::from("No previous search.".to_string());
+            return;
+        };
+        let matcher = match Matcher::new(&query, self.search_options) {
+            Ok(matcher) => matcher,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("Invalid regex: {}", error));
+                return;
+            }
+        };
+        let at = match direction {
+            SearchDirection::Forward => Position {
+                x: self.cursor_position.x.saturating_add(1),
+                y: self.cursor_position.y,
+            },
+            SearchDirection::Backword => self.cursor_position.clone(),
+        };
+        let mut found = self.document.find(&matcher, &at, direction);
+        if found.is_none() {
+            let wrapped = match direction {
+                SearchDirection::Forward => Position::default(),
+                SearchDirection::Backword => {
+                    let y = self.document.len().saturating_sub(1);
+                    Position {
+                        x: self.document.row(y).map_or(0, Row::len),
+                        y,
+                    }
+                }
+            };
+            found = self.document.find(&matcher, &wrapped, direction);
+            if found.is_some() {
+                self.status_message = StatusMessage::from("Search wrapped.".to_string());
+            }
+        }
+        if let Some(found) = found {
+            self.cursor_position = found.start;
+        } else {
+            self.status_message = StatusMessage::from(format!("Pattern not found: {}", query));
+        }
+    }
+
+    fn match_counter(&self, matcher: &Matcher) -> String {
+        let matches = self.document.find_all(matcher);
+        if matches.is_empty() {
+            return "no matches".to_string();
+        }
+        if let Some(index) = matches
+            .iter()
+            .position(|found| found.start == self.cursor_position)
+        {
+            format!("match {} of {}", index.saturating_add(1), matches.len())
+        } else {
+            format!("{} matches", matches.len())
+        }
+    }
+
+    fn replace(&mut self) {
+        let query = if let Some(query) = self.prompt("Replace: ", |_, _, _| {}).unwrap_or(None) {
+            query
+        } else {
+            self.status_message = StatusMessage::from("Replace aborted.".to_string());
+            return;
+        };
+        let replacement = if let Some(replacement) = self
+            .prompt(&format!("Replace {} with: ", query), |_, _, _| {})
+            .unwrap_or(None)
+        {
+            replacement
+        } else {
+            self.status_message = StatusMessage::from("Replace aborted.".to_string());
+            return;
+        };
+        let matcher = match Matcher::new(&query, self.search_options) {
+            Ok(matcher) => matcher,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("Invalid regex: {}", error));
+                return;
+            }
+        };
+        let position = self.cursor_position.clone();
+        let (replaced, _) = self.replace_matches(&matcher, &replacement, position, false);
+        self.status_message = StatusMessage::from(format!("Replaced {} occurrences.", replaced));
+    }
+
+    fn replace_matches(
+        &mut self,
+        matcher: &Matcher,
+        replacement: &str,
+        mut position: Position,
+        mut replace_all: bool,
+    ) -> (usize, bool) {
+        let mut replaced = 0;
+        let mut quit = false;
+        self.highlighted_match = Some(matcher.clone());
+        while let Some(found) = self
+            .document
+            .find(matcher, &position, SearchDirection::Forward)
+        {
+            self.cursor_position = found.start.clone();
+            self.scroll();
+            let choice = if replace_all {
+                ReplaceChoice::All
+            } else {
+                self.replace_choice("Replace this occurrence? (y/n/a/q)", false)
+                    .unwrap_or(ReplaceChoice::Quit)
+            };
+            match choice {
+                ReplaceChoice::Yes | ReplaceChoice::All => {
+                    replace_all = choice == ReplaceChoice::All;
+                    let replacement = self.document.expand(matcher, &found, replacement);
+                    position = self
+                        .document
+                        .replace(&found.start, &found.end, &replacement);
+                    replaced += 1;
+                }
+                ReplaceChoice::No | ReplaceChoice::Each => {
+                    position = Position {
+                        x: found.start.x.saturating_add(1),
+                        y: found.start.y,
+                    };
+                }
+                ReplaceChoice::Quit => {
+                    quit = true;
+                    break;
+                }
+            }
+        }
+        self.highlighted_match = None;
+        self.document.unhighlight_rows(0);
+        (replaced, quit)
+    }
+
+    fn replace_in_files(&mut self) -> Result<(), crossterm::ErrorKind> {
+        let Some(query) = self.prompt("Replace in files (re: for regex): ", |_, _, _| {})? else {
+            self.status_message = StatusMessage::from("Replace aborted.".to_string());
+            return Ok(());
+        };
+        let Some(replacement) =
+            self.prompt_raw(&format!("Replace {} in files with: ", query), |_, _, _| {})?
+        else {
+            self.status_message = StatusMessage::from("Replace aborted.".to_string());
+            return Ok(());
+        };
+        let matcher = match Matcher::new(&query, self.search_options) {
+            Ok(matcher) => matcher,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("Invalid regex: {}", error));
+                return Ok(());
+            }
+        };
+        self.status_message = StatusMessage::from(format!("Searching for {}...", query));
+        self.refresh_screen()?;
+        let root = env::current_dir().unwrap_or_default();
+        let matches = grep::search(&root, &matcher);
+        let mut files: Vec<String> = matches.iter().map(|found| found.path.clone()).collect();
+        files.dedup();
+        let mut replace_all = false;
+        let mut replaced = 0;
+        let mut changed = 0;
+        let mut written = 0;
+        for file in &files {
+            self.open_file(file)?;
+            let current = self.document.file_name.as_deref().map(history::absolute);
+            if current != Some(history::absolute(file))
+                || self.document.is_loading()
+                || self.document.is_read_only()
+            {
+                continue;
+            }
+            let count = self.document.find_all(&matcher).len();
+            let was_dirty = self.document.is_dirty();
+            let choice = if replace_all {
+                ReplaceChoice::All
+            } else {
+                self.cursor_position = Position::default();
+                self.scroll();
+                self.highlighted_match = Some(matcher.clone());
+                let choice = self.replace_choice(
+                    &format!(
+                        "Replace {} occurrences in {}? (y/n/a/q, m: confirm each)",
+                        count, file
+                    ),
+                    true,
+                )?;
+                self.highlighted_match = None;
+                self.document.unhighlight_rows(0);
+                choice
+            };
+            let (count, quit) = match choice {
+                ReplaceChoice::Yes | ReplaceChoice::All => {
+                    replace_all = choice == ReplaceChoice::All;
+                    self.replace_matches(&matcher, &replacement, Position::default(), true)
+                }
+                ReplaceChoice::Each => {
+                    self.replace_matches(&matcher, &replacement, Position::default(), false)
+                }
+                ReplaceChoice::No => (0, false),
+                ReplaceChoice::Quit => (0, true),
+            };
+            if count > 0 {
+                replaced += count;
+                changed += 1;
+                if !was_dirty && self.document.save(&self.file_types).is_ok() {
+                    written += 1;
+                }
+            }
+            if quit {
+                break;
+            }
+        }
+        self.status_message = StatusMessage::from(format!(
+            "Replaced {} occurrences in {} of {} files, wrote {} files.",
+            replaced,
+            changed,
+            files.len(),
+            written
+        ));
+        Ok(())
+    }
+
+    fn replace_choice(
+        &mut self,
+        message: &str,
+        each: bool,
+    ) -> Result<ReplaceChoice, crossterm::ErrorKind> {
+        self.status_message = StatusMessage::from(message.to_string());
+        self.refresh_screen()?;
+        loop {
+            if let Event::Key(pressed_key) = self.read_event()? {
+                match pressed_key.code {
+                    KeyCode::Char('y') => return Ok(ReplaceChoice::Yes),
+                    KeyCode::Char('n') => return Ok(ReplaceChoice::No),
+                    KeyCode::Char('a') => return Ok(ReplaceChoice::All),
+                    KeyCode::Char('m') if each => return Ok(ReplaceChoice::Each),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(ReplaceChoice::Quit),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn process_keypress(&mut self, pressed_key: KeyEvent) -> Result<(), crossterm::ErrorKind> {
+        self.hover = None;
+        self.pending_keys.push(Key::from(pressed_key));
+        match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Prefix => {
+                self.status_message =
+                    StatusMessage::from(format!("{}-", describe_keys(&self.pending_keys)));
+                return Ok(());
+            }
+            Lookup::Command(Command::Quit) => {
+                self.pending_keys.clear();
+                if self.quit_times > 0 && self.is_any_dirty() {
+                    self.status_message = StatusMessage::from(format!(
+                        "WARNING! FILE has unsaved changes. Press {} {} more times to quit.",
+                        self.keymap.describe(Command::Quit),
+                        self.quit_times
+                    ));
+                    self.quit_times -= 1;
+                    return Ok(());
+                }
+                self.should_quit = true;
+            }
+            Lookup::Command(command @ (Command::ToggleMacroRecording | Command::ReplayMacro)) => {
+                let keys = std::mem::take(&mut self.pending_keys);
+                if let Some(recording) = self.recording.as_mut() {
+                    recording.truncate(recording.len().saturating_sub(keys.len()));
+                }
+                self.execute(command, false)?;
+            }
+            Lookup::Command(command) => {
+                self.pending_keys.clear();
+                self.execute(command, false)?;
+            }
+            Lookup::Unbound => {
+                let keys = std::mem::take(&mut self.pending_keys);
+                self.process_unbound(pressed_key, &keys)?;
+            }
+        }
+        self.scroll();
+        if self.quit_times < self.config.quit_times {
+            self.quit_times = self.config.quit_times;
+            self.status_message = StatusMessage::from(String::new());
+        }
+        Ok(())
+    }
+
+    fn process_unbound(
+        &mut self,
+        pressed_key: KeyEvent,
+        keys: &[Key],
+    ) -> Result<(), crossterm::ErrorKind> {
+        if let ([_], KeyCode::Char(digit @ '0'..='9')) = (keys, pressed_key.code) {
+            if pressed_key.modifiers == KeyModifiers::ALT {
+                let digit = digit.to_digit(10).unwrap_or_default() as usize;
+                let count = self
+                    .count
+                    .unwrap_or_default()
+                    .saturating_mul(10)
+                    .saturating_add(digit);
+                self.count = Some(count);
+                self.status_message = StatusMessage::from(format!("Count: {}", count));
+                return Ok(());
+            }
+        }
+        if keys.len() == 1 && pressed_key.modifiers.contains(KeyModifiers::SHIFT) {
+            let unshifted = Key {
+                modifiers: pressed_key.modifiers - KeyModifiers::SHIFT,
+                code: pressed_key.code,
+            };
+            if let Lookup::Command(command) = self.keymap.lookup(&[unshifted]) {
+                if command.is_movement() {
+                    return self.execute(command, true);
+                }
+            }
+        }
+        match (keys, pressed_key.code) {
+            ([_], KeyCode::Char(c)) if (pressed_key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                self.insert_char(c);
+            }
+            _ => {
+                self.status_message =
+                    StatusMessage::from(format!("{} is undefined", describe_keys(keys)));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_char(&mut self, c: char) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from("Buffer is read-only.".to_string());
+            return;
+        }
+        self.delete_selection();
+        if self.config.auto_close {
+            let Position { x, y } = self.cursor_position;
+            let row = self.document.row(y);
+            let next = row.and_then(|row| row.slice(x, x.saturating_add(1)).chars().next());
+            let previous = row.and_then(|row| row.slice(x.saturating_sub(1), x).chars().last());
+            if next == Some(c) && self.document.is_closing_pair(c) {
+                self.move_cursor(KeyCode::Right);
+                return;
+            }
+            if let Some(close) = self.document.closing_pair(c) {
+                let before_boundary = next
+                    .is_none_or(|next| next.is_whitespace() || self.document.is_closing_pair(next));
+                let after_word = previous.is_some_and(char::is_alphanumeric);
+                if before_boundary && !(close == c && after_word) {
+                    self.document.insert(&self.cursor_position, c);
+                    self.move_cursor(KeyCode::Right);
+                    self.document.insert(&self.cursor_position, close);
+                    return;
+                }
+            }
+        }
+        let Position { y, .. } = self.cursor_position;
+        let len = self.document.row(y).map_or(0, Row::len);
+        self.document.insert(&self.cursor_position, c);
+        if self.document.row(y).map_or(0, Row::len) > len {
+            self.move_cursor(KeyCode::Right);
+        }
+    }
+
+    fn execute(
+        &mut self,
+        command: Command,
+        extend_selection: bool,
+    ) -> Result<(), crossterm::ErrorKind> {
+        let count = self.count.take().unwrap_or(1);
+        if command.is_edit() && self.document.is_read_only() {
+            self.status_message = StatusMessage::from("Buffer is read-only.".to_string());
+            return Ok(());
+        }
+        if command.is_movement() && extend_selection && self.selection.is_none() {
+            self.selection = Some(self.cursor_position.clone());
+        }
+        if !matches!(command, Command::DeleteForward | Command::DeleteBackward) {
+            self.document.seal_undo();
+        }
+        let origin = command.is_jump().then(|| self.location());
+        match command {
+            Command::Quit => (),
+            Command::Help => self.show_pager(Pager::help(&self.keymap))?,
+            Command::Messages => {
+                let mut pager = Pager::new("Messages", self.messages.clone());
+                pager.scroll_by(isize::MAX, usize::from(self.terminal.size().height));
+                self.show_pager(pager)?;
+            }
+            #[cfg(unix)]
+            Command::Suspend => {
+                self.terminal.suspend()?;
+                Terminal::clear_screen();
+                self.scroll();
+            }
+            #[cfg(not(unix))]
+            Command::Suspend => {
+                self.status_message = StatusMessage::from("Suspend not supported.".to_string());
+            }
+            Command::Save => self.save()?,
+            Command::Script(index) => self.run_script(index)?,
+            Command::Undo => self.undo(count, SearchDirection::Backword),
+            Command::Redo => self.undo(count, SearchDirection::Forward),
+            Command::UndoEarlier => self.undo_travel(SearchDirection::Backword)?,
+            Command::UndoLater => self.undo_travel(SearchDirection::Forward)?,
+            Command::UndoTree => {
+                let (lines, current) = self.document.undo_tree();
+                let height = usize::from(self.terminal.size().height);
+                let mut pager = Pager::new("Undo tree", lines);
+                #[allow(clippy::cast_possible_wrap)]
+                pager.scroll_by(current.saturating_sub(height / 2) as isize, height);
+                self.show_pager(pager)?;
+            }
+            Command::Find => self.search(),
+            Command::FindNext => self.search_again(SearchDirection::Forward),
+            Command::FindPrevious => self.search_again(SearchDirection::Backword),
+            Command::Replace => self.replace(),
+            Command::Open => self.open()?,
+            Command::FindFile => self.find_file()?,
+            Command::RecentFiles => self.recent_files()?,
+            Command::ReopenLast => self.reopen_last()?,
+            Command::BrowseFiles => {
+                let dir = self
+                    .document
+                    .file_name
+                    .as_deref()
+                    .and_then(|file_name| Path::new(file_name).parent())
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+                self.browse(dir)?;
+            }
+            Command::FindInFiles => {
+                self.find_in_files()?;
+            }
+            Command::ReplaceInFiles => {
+                self.replace_in_files()?;
+            }
+            Command::QuickfixList => {
+                self.show_quickfix()?;
+            }
+            Command::TodoList => {
+                self.todo_list()?;
+            }
+            Command::SwitchBuffer => self.switch_buffer_prompt(),
+            Command::SplitHorizontal => self.split_window(SplitDirection::Horizontal),
+            Command::SplitVertical => self.split_window(SplitDirection::Vertical),
+            Command::CloseWindow => self.close_window(),
+            Command::NextWindow => self.cycle_window(),
+            Command::ToggleSelection => {
+                self.selection = if self.selection.is_some() {
+                    None
+                } else {
+                    Some(self.cursor_position.clone())
+                };
+            }
+            Command::ClearSelection => self.selection = None,
+            Command::Copy => self.copy(),
+            Command::Cut => self.cut(),
+            Command::Paste => self.paste(),
+            Command::ToggleLineNumbers => {
+                self.line_numbers = match self.line_numbers {
+                    LineNumbers::Off => LineNumbers::Absolute,
+                    LineNumbers::Absolute => LineNumbers::Relative,
+                    LineNumbers::Relative => LineNumbers::Off,
+                };
+            }
+            Command::ToggleSoftWrap => {
+                self.soft_wrap = !self.soft_wrap;
+                self.offset.x = 0;
+                self.wrap_offset = 0;
+            }
+            Command::ToggleWhitespace => self.show_whitespace = !self.show_whitespace,
+            Command::ToggleIndentGuides => self.indent_guides = !self.indent_guides,
+            Command::ToggleLineEnding => {
+                let line_ending = match self.document.line_ending() {
+                    LineEnding::Lf => LineEnding::Crlf,
+                    LineEnding::Crlf => LineEnding::Lf,
+                };
+                self.document.set_line_ending(line_ending);
+                self.status_message =
+                    StatusMessage::from(format!("Line endings set to {}.", line_ending.name()));
+            }
+            Command::ToggleComment => self.toggle_comment(),
+            Command::ToggleReadOnly => {
+                let read_only = !self.document.is_read_only();
+                self.document.set_read_only(read_only);
+                self.status_message = StatusMessage::from(if read_only {
+                    "Buffer is now read-only.".to_string()
+                } else {
+                    "Buffer is now writable.".to_string()
+                });
+            }
+            Command::SetFileType => self.set_file_type()?,
+            Command::ToggleMacroRecording => self.toggle_macro_recording(),
+            Command::ReplayMacro => self.replay_macro(count),
+            Command::MoveUp => self.move_cursor(KeyCode::Up),
+            Command::MoveDown => self.move_cursor(KeyCode::Down),
+            Command::MoveLeft => self.move_cursor(KeyCode::Left),
+            Command::MoveRight => self.move_cursor(KeyCode::Right),
+            Command::PageUp => self.scroll_page(self.text_height(), SearchDirection::Backword),
+            Command::PageDown => self.scroll_page(self.text_height(), SearchDirection::Forward),
+            #[allow(clippy::integer_division)]
+            Command::HalfPageUp => {
+                self.scroll_page(self.text_height() / 2, SearchDirection::Backword);
+            }
+            #[allow(clippy::integer_division)]
+            Command::HalfPageDown => {
+                self.scroll_page(self.text_height() / 2, SearchDirection::Forward);
+            }
+            Command::CenterView => self.center_cursor(),
+            Command::LineStart => self.move_cursor(KeyCode::Home),
+            Command::LineEnd => self.move_cursor(KeyCode::End),
+            Command::WordLeft => self.move_word(SearchDirection::Backword),
+            Command::WordRight => self.move_word(SearchDirection::Forward),
+            Command::MatchingBracket => {
+                if let Some(position) = self.document.matching_bracket(&self.cursor_position) {
+                    self.cursor_position = position;
+                }
+            }
+            Command::GotoDefinition if self.start_lsp().is_none() => self.goto_tag()?,
+            Command::Complete
+            | Command::GotoDefinition
+            | Command::FindReferences
+            | Command::Hover => self.request_lsp(command),
+            Command::ToggleSpellCheck => self.toggle_spell_check(),
+            Command::Uppercase => self.change_case(CaseChange::Upper),
+            Command::Lowercase => self.change_case(CaseChange::Lower),
+            Command::Titlecase => self.change_case(CaseChange::Title),
+            Command::Format => {
+                self.format();
+            }
+            Command::FilterShell => {
+                self.filter_shell()?;
+            }
+            Command::SpellSuggest => {
+                self.spell_suggest()?;
+            }
+            Command::AddToDictionary => self.add_to_dictionary(),
+            Command::CompleteWord => {
+                self.complete_word()?;
+            }
+            Command::NextHunk => self.jump_to_hunk(SearchDirection::Forward),
+            Command::PreviousHunk => self.jump_to_hunk(SearchDirection::Backword),
+            Command::NextDiagnostic => self.jump_to_diagnostic(SearchDirection::Forward),
+            Command::PreviousDiagnostic => self.jump_to_diagnostic(SearchDirection::Backword),
+            Command::JumpBack => {
+                let current = self.location();
+                match self.jump_list.back(current) {
+                    Some(jump) => self.go_to(&jump)?,
+                    None => {
+                        self.status_message =
+                            StatusMessage::from("Already at oldest jump.".to_string());
+                    }
+                }
+            }
+            Command::JumpForward => match self.jump_list.forward() {
+                Some(jump) => self.go_to(&jump)?,
+                None => {
+                    self.status_message =
+                        StatusMessage::from("Already at newest jump.".to_string());
+                }
+            },
+            Command::KillLine => self.kill_line(count),
+            Command::DeleteLine => self.delete_lines(count),
+            Command::JoinLines => {
+                for _ in 0..count {
+                    match self.document.join_lines(self.cursor_position.y) {
+                        Some(position) => self.cursor_position = position,
+                        None => break,
+                    }
+                }
+            }
+            Command::MoveLineUp => self.move_lines(SearchDirection::Backword),
+            Command::MoveLineDown => self.move_lines(SearchDirection::Forward),
+            Command::DuplicateLine => self.duplicate_lines(),
+            Command::OpenLineBelow => self.open_line(SearchDirection::Forward),
+            Command::OpenLineAbove => self.open_line(SearchDirection::Backword),
+            Command::InsertNewline => {
+                self.delete_selection();
+                let indent = self.newline_indent();
+                self.cursor_position = self
+                    .document
+                    .insert_str(&self.cursor_position, &format!("\n{}", indent));
+            }
+            Command::InsertTab => {
+                self.delete_selection();
+                if self.expand_tab() {
+                    let tab_width = cmp::max(self.tab_width(), 1);
+                    #[allow(clippy::arithmetic_side_effects)]
+                    let spaces = tab_width - self.cursor_column() % tab_width;
+                    for _ in 0..spaces {
+                        self.document.insert(&self.cursor_position, ' ');
+                        self.move_cursor(KeyCode::Right);
+                    }
+                } else {
+                    self.document.insert(&self.cursor_position, '\t');
+                    self.move_cursor(KeyCode::Right);
+                }
+            }
+            Command::DeleteForward => {
+                if !self.delete_selection() {
+                    self.document.delete(&self.cursor_position);
+                }
+            }
+            Command::DeleteWordForward => self.delete_word(SearchDirection::Forward),
+            Command::DeleteWordBackward => self.delete_word(SearchDirection::Backword),
+            Command::DeleteBackward => {
+                if !self.delete_selection()
+                    && (self.cursor_position.x > 0 || self.cursor_position.y > 0)
+                {
+                    self.move_cursor(KeyCode::Left);
+                    self.document.delete(&self.cursor_position);
+                }
+            }
+        }
+        if !matches!(command, Command::DeleteForward | Command::DeleteBackward) {
+            self.document.seal_undo();
+        }
+        if let Some(origin) = origin {
+            self.record_jump(origin);
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, count: usize, direction: SearchDirection) {
+        for index in 0..count {
+            let position = if direction == SearchDirection::Forward {
+                self.document.redo()
+            } else {
+                self.document.undo()
+            };
+            let Some(position) = position else {
+                if index == 0 {
+                    self.status_message = StatusMessage::from(
+                        if direction == SearchDirection::Forward {
+                            "Already at newest change."
+                        } else {
+                            "Already at oldest change."
+                        }
+                        .to_string(),
+                    );
+                }
+                break;
+            };
+            self.selection = None;
+            self.cursor_position = clamp_position(&self.document, position);
+        }
+    }
+
+    fn undo_travel(&mut self, direction: SearchDirection) -> Result<(), crossterm::ErrorKind> {
+        let prompt = if direction == SearchDirection::Forward {
+            "Later by (steps, or time like 30s, 5m, 1h): "
+        } else {
+            "Earlier by (steps, or time like 30s, 5m, 1h): "
+        };
+        let Some(amount) = self.prompt_raw(prompt, |_, _, _| {})? else {
+            self.status_message = StatusMessage::from("Undo aborted.".to_string());
+            return Ok(());
+        };
+        let amount = amount.trim();
+        let travel = if amount.is_empty() {
+            Travel::Steps(direction, 1)
+        } else if let Ok(steps) = amount.parse() {
+            Travel::Steps(direction, steps)
+        } else if let Some(duration) = undo::parse_duration(amount) {
+            Travel::Time(direction, duration)
+        } else {
+            self.status_message = StatusMessage::from(format!("Invalid amount: {}", amount));
+            return Ok(());
+        };
+        match self.document.travel(travel) {
+            Some(position) => {
+                self.selection = None;
+                self.cursor_position = clamp_position(&self.document, position);
+            }
+            None => {
+                self.status_message = StatusMessage::from(
+                    if direction == SearchDirection::Forward {
+                        "Already at newest change."
+                    } else {
+                        "Already at oldest change."
+                    }
+                    .to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn location(&self) -> Jump {
+        Jump {
+            file: self.document.file_name.as_deref().map(history::absolute),
+            position: self.cursor_position.clone(),
+        }
+    }
+
+    fn record_jump(&mut self, origin: Jump) {
+        let current = self.location();
+        if current.file != origin.file || current.position.y != origin.position.y {
+            self.jump_list.record(origin);
+        }
+    }
+
+    fn go_to(&mut self, jump: &Jump) -> Result<(), crossterm::ErrorKind> {
+        if self.location().file != jump.file {
+            let index = (0..self.buffers.len()).find(|index| {
+                *index != self.current_buffer
+                    && self.buffers.get(*index).is_some_and(|buffer| {
+                        buffer.document.file_name.as_deref().map(history::absolute) == jump.file
+                    })
+            });
+            match (index, &jump.file) {
+                (Some(index), _) => self.switch_buffer(index),
+                (None, Some(file)) => self.open_file(file)?,
+                (None, None) => return Ok(()),
+            }
+        }
+        if self.location().file == jump.file {
+            self.cursor_position = clamp_position(&self.document, jump.position.clone());
+            self.scroll();
+        }
+        Ok(())
+    }
+
+    fn delete_word(&mut self, direction: SearchDirection) {
+        if self.delete_selection() {
+            return;
+        }
+        let start = self.cursor_position.clone();
+        self.move_word(direction);
+        let end = self.cursor_position.clone();
+        if direction == SearchDirection::Forward {
+            self.document.delete_range(&start, &end);
+            self.cursor_position = start;
+        } else {
+            self.document.delete_range(&end, &start);
+        }
+    }
+
+    fn move_word(&mut self, direction: SearchDirection) {
+        let Position { mut x, mut y } = self.cursor_position;
+        while let Some(row) = self.document.row(y) {
+            let target = if direction == SearchDirection::Forward {
+                row.next_word_end(x)
+            } else {
+                row.previous_word_start(x)
+            };
+            if let Some(target) = target {
+                x = target;
+                break;
+            }
+            if direction == SearchDirection::Forward {
+                if y.saturating_add(1) >= self.document.len() {
+                    x = row.len();
+                    break;
+                }
+                y = y.saturating_add(1);
+                x = 0;
+            } else {
+                if y == 0 {
+                    x = 0;
+                    break;
+                }
+                y = y.saturating_sub(1);
+                x = self.document.row(y).map_or(0, Row::len);
+            }
+        }
+        self.cursor_position = Position { x, y };
+    }
+
+    fn newline_indent(&self) -> String {
+        if !self.config.auto_indent {
+            return String::new();
+        }
+        let line_start = Position {
+            x: 0,
+            y: self.cursor_position.y,
+        };
+        let before = self
+            .document
+            .extract_range(&line_start, &self.cursor_position);
+        let mut indent: String = before
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        if before
+            .trim_end()
+            .chars()
+            .last()
+            .is_some_and(|c| self.document.indents_after(c))
+        {
+            if self.expand_tab() {
+                indent.push_str(&" ".repeat(self.tab_width()));
+            } else {
+                indent.push('\t');
+            }
+        }
+        indent
+    }
+
+    fn toggle_macro_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            self.status_message =
+                StatusMessage::from(format!("Recorded macro with {} keys.", recording.len()));
+            self.last_macro = recording;
+        } else {
+            self.recording = Some(Vec::new());
+            self.status_message = StatusMessage::from("Recording macro...".to_string());
+        }
+    }
+
+    fn replay_macro(&mut self, count: usize) {
+        if self.recording.is_some() {
+            self.status_message =
+                StatusMessage::from("Cannot replay a macro while recording.".to_string());
+        } else if self.last_macro.is_empty() {
+            self.status_message = StatusMessage::from("No macro recorded.".to_string());
+        } else if self.replaying.is_empty() {
+            for _ in 0..count {
+                self.replaying.extend(self.last_macro.iter().copied());
+            }
+        }
+    }
+
+    fn toggle_comment(&mut self) {
+        let (first, last) = match self.selection_range() {
+            Some((start, end)) if end.y > start.y && end.x == 0 => {
+                (start.y, end.y.saturating_sub(1))
+            }
+            Some((start, end)) => (start.y, end.y),
+            None => (self.cursor_position.y, self.cursor_position.y),
+        };
+        let lines: Vec<(usize, String)> = (first..=last)
+            .filter_map(|y| Some((y, self.document.row(y)?.as_str().to_string())))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+        if lines.is_empty() {
+            return;
+        }
+        let opts = self.document.highlighting_options();
+        if let Some(prefix) = opts.comment().map(str::to_string) {
+            self.toggle_line_comment(&lines, &prefix);
+        } else if let Some((start, end)) = opts
+            .multiline_comment()
+            .map(|(start, end)| (start.to_string(), end.to_string()))
+        {
+            self.toggle_block_comment(&lines, &start, &end);
+        } else {
+            self.status_message = StatusMessage::from(format!(
+                "No comment syntax for {}.",
+                self.document.file_type()
+            ));
+        }
+    }
+
+    fn toggle_line_comment(&mut self, lines: &[(usize, String)], prefix: &str) {
+        let commented = lines
+            .iter()
+            .all(|(_, line)| line.trim_start().starts_with(prefix));
+        if commented {
+            for (y, line) in lines {
+                let x = indentation(line);
+                let rest = line.trim_start().get(prefix.len()..).unwrap_or_default();
+                let len = prefix
+                    .chars()
+                    .count()
+                    .saturating_add(usize::from(rest.starts_with(' ')));
+                self.remove_text(*y, x, len);
+            }
+        } else {
+            let x = lines
+                .iter()
+                .map(|(_, line)| indentation(line))
+                .min()
+                .unwrap_or_default();
+            for (y, _) in lines {
+                self.insert_text(*y, x, &format!("{} ", prefix));
+            }
+        }
+    }
+
+    fn toggle_block_comment(&mut self, lines: &[(usize, String)], start: &str, end: &str) {
+        let (Some((first, first_line)), Some((last, last_line))) = (lines.first(), lines.last())
+        else {
+            return;
+        };
+        let last_line = last_line.trim_end();
+        if first_line.trim_start().starts_with(start) && last_line.ends_with(end) {
+            let before_end = last_line.get(..last_line.len().saturating_sub(end.len()));
+            let len = end.chars().count().saturating_add(usize::from(
+                before_end.is_some_and(|text| text.ends_with(' ')),
+            ));
+            self.remove_text(*last, last_line.chars().count().saturating_sub(len), len);
+            let rest = first_line
+                .trim_start()
+                .get(start.len()..)
+                .unwrap_or_default();
+            let len = start
+                .chars()
+                .count()
+                .saturating_add(usize::from(rest.starts_with(' ')));
+            self.remove_text(*first, indentation(first_line), len);
+        } else {
+            self.insert_text(*last, last_line.chars().count(), &format!(" {}", end));
+            self.insert_text(*first, indentation(first_line), &format!("{} ", start));
+        }
+    }
+
+    fn insert_text(&mut self, y: usize, x: usize, text: &str) {
+        self.document.insert_str(&Position { x, y }, text);
+        let len = text.chars().count();
+        for position in [Some(&mut self.cursor_position), self.selection.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            if position.y == y && position.x >= x {
+                position.x = position.x.saturating_add(len);
+            }
+        }
+    }
+
+    fn remove_text(&mut self, y: usize, x: usize, len: usize) {
+        let end = x.saturating_add(len);
+        self.document
+            .delete_range(&Position { x, y }, &Position { x: end, y });
+        for position in [Some(&mut self.cursor_position), self.selection.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            if position.y == y && position.x > x {
+                position.x = position.x.saturating_sub(len).max(x);
+            }
+        }
+    }
+
+    fn kill_line(&mut self, count: usize) {
+        self.register = self
+            .document
+            .delete_to_line_end(&self.cursor_position, count);
+        self.selection = None;
+    }
+
+    fn delete_lines(&mut self, count: usize) {
+        let lines = if self.selection.is_some() {
+            self.selected_lines()
+        } else {
+            let y = self.cursor_position.y;
+            y..y.saturating_add(count)
+        };
+        self.register = self.document.delete_lines(lines.clone());
+        self.selection = None;
+        let y = cmp::min(lines.start, self.document.len().saturating_sub(1));
+        let x = self
+            .document
+            .row(y)
+            .map_or(0, |row| indentation(row.as_str()));
+        self.cursor_position = Position { x, y };
+    }
+
+    fn selected_lines(&self) -> Range<usize> {
+        let (start, end) = self
+            .selection_range()
+            .unwrap_or_else(|| (self.cursor_position.clone(), self.cursor_position.clone()));
+        let end = if end.x == 0 && end.y > start.y {
+            end.y
+        } else {
+            end.y.saturating_add(1)
+        };
+        start.y..cmp::min(end, self.document.len())
+    }
+
+    fn move_lines(&mut self, direction: SearchDirection) {
+        if !self.document.move_lines(self.selected_lines(), direction) {
+            return;
+        }
+        let shift = |y: usize| match direction {
+            SearchDirection::Forward => y.saturating_add(1),
+            SearchDirection::Backword => y.saturating_sub(1),
+        };
+        self.cursor_position.y = shift(self.cursor_position.y);
+        if let Some(anchor) = self.selection.as_mut() {
+            anchor.y = shift(anchor.y);
+        }
+    }
+
+    fn duplicate_lines(&mut self) {
+        let lines = self.selected_lines();
+        let count = lines.len();
+        self.document.duplicate_lines(lines);
+        self.cursor_position.y = self.cursor_position.y.saturating_add(count);
+        if let Some(anchor) = self.selection.as_mut() {
+            anchor.y = anchor.y.saturating_add(count);
+        }
+    }
 
-        loop {
-            if let Err(error) = self.refresh_screen() {
-                die(&error);
-            }
-            if self.should_quit {
-                break;
+    fn open_line(&mut self, direction: SearchDirection) {
+        self.selection = None;
+        let y = self.cursor_position.y;
+        match direction {
+            SearchDirection::Forward => {
+                self.cursor_position.x = self.document.row(y).map_or(0, Row::len);
+                let indent = self.newline_indent();
+                self.cursor_position = self
+                    .document
+                    .insert_str(&self.cursor_position, &format!("\n{}", indent));
             }
-            if let Err(error) = self.process_event() {
-                die(&error);
+            SearchDirection::Backword => {
+                let indent: String = self.document.row(y).map_or_else(String::new, |row| {
+                    row.as_str()
+                        .chars()
+                        .take_while(|c| *c == ' ' || *c == '\t')
+                        .collect()
+                });
+                self.document
+                    .insert_str(&Position { x: 0, y }, &format!("{}\n", indent));
+                self.cursor_position = Position {
+                    x: indent.chars().count(),
+                    y,
+                };
             }
         }
     }
 
-    pub fn default() -> Self {
-        enable_raw_mode().unwrap();
-        let args: Vec<String> = env::args().collect();
-        let mut initial_status =
-            String::from("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit");
-        let document = if let Some(file_name) = args.get(1) {
-            let doc = Document::open(file_name);
-            if let Ok(doc) = doc {
-                doc
-            } else {
-                initial_status = format!("ERR: Could not open file: {}", file_name);
-                Document::default()
-            }
+    fn selection_range(&self) -> Option<(Position, Position)> {
+        let anchor = self.selection.as_ref()?;
+        let cursor = &self.cursor_position;
+        if (anchor.y, anchor.x) <= (cursor.y, cursor.x) {
+            Some((anchor.clone(), cursor.clone()))
         } else {
-            Document::default()
-        };
-
-        Self {
-            should_quit: false,
-            terminal: Terminal::defalut().expect("Failed to initialize terminal"),
-            cursor_position: Position::default(),
-            document,
-            offset: Position::default(),
-            status_message: StatusMessage::from(initial_status),
-            quit_times: QUIT_TIMES,
-            highlighted_word: None,
+            Some((cursor.clone(), anchor.clone()))
         }
     }
 
-    fn refresh_screen(&mut self) -> Result<(), std::io::Error> {
-        Terminal::cursor_hide();
-        Terminal::cursor_position(&Position::default());
+    fn copy(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.register = self.document.extract_range(&start, &end);
+            self.selection = None;
+            self.status_message = StatusMessage::from("Copied selection.".to_string());
+        }
+    }
 
-        if self.should_quit {
-            Terminal::clear_screen();
-            println!("Goodbye.\r");
-        } else {
-            self.document.highlight(
-                &self.highlighted_word,
-                Some(
-                    self.offset
-                        .y
-                        .saturating_add(self.terminal.size().height as usize),
-                ),
-            );
-            self.draw_rows();
-            self.draw_status_bar();
-            self.draw_message_bar();
-            let Position { mut x, mut y } = self.cursor_position;
-            x = x.saturating_sub(self.offset.x);
-            x = if let Some(row) = self.document.row(y) {
-                cmp::min(x, row.len().saturating_sub(self.offset.x))
-            } else {
-                0
-            };
-            y = y.saturating_sub(self.offset.y);
-            Terminal::cursor_position(&Position { x, y });
+    fn cut(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.register = self.document.extract_range(&start, &end);
+            self.delete_selection();
+            self.status_message = StatusMessage::from("Cut selection.".to_string());
         }
-        Terminal::cursor_show();
-        Terminal::flush()
     }
 
-    fn process_event(&mut self) -> Result<(), crossterm::ErrorKind> {
-        let event = event::read()?;
+    fn paste(&mut self) {
+        if self.register.is_empty() {
+            return;
+        }
+        self.delete_selection();
+        self.cursor_position = self
+            .document
+            .insert_str(&self.cursor_position, &self.register);
+    }
 
-        match event {
-            Event::Key(pressed_key) => self.process_keypress(pressed_key),
-            Event::Resize(_, _) => self.terminal.update_size()?,
-            _ => (),
+    fn delete_selection(&mut self) -> bool {
+        if let Some((start, end)) = self.selection_range() {
+            self.document.delete_range(&start, &end);
+            self.cursor_position = start;
+            self.selection = None;
+            return true;
         }
+        false
+    }
 
-        Ok(())
+    fn window_rects(&self) -> (Vec<(usize, Rect)>, Vec<Rect>) {
+        let size = self.terminal.size();
+        let area = Rect {
+            x: 0,
+            y: 0,
+            width: size.width as usize,
+            height: (size.height as usize).saturating_add(1),
+        };
+        let mut windows = Vec::new();
+        let mut separators = Vec::new();
+        self.layout.arrange(area, &mut windows, &mut separators);
+        (windows, separators)
     }
 
-    fn save(&mut self) {
-        if self.document.file_name.is_none() {
-            let new_name = self.prompt("Save as: ", |_, _, _| {}).unwrap_or(None);
-            if new_name.is_none() {
-                self.status_message = StatusMessage::from("Save aborted.".to_string());
-                return;
-            }
-            self.document.file_name = new_name;
-        }
+    fn window_rect(&self, window: usize) -> Rect {
+        self.window_rects()
+            .0
+            .into_iter()
+            .find(|(id, _)| *id == window)
+            .map(|(_, rect)| rect)
+            .unwrap_or_default()
+    }
 
-        if self.document.save().is_ok() {
-            self.status_message = StatusMessage::from("File saved successfully.".to_string());
-        } else {
-            self.status_message = StatusMessage::from("Error writing file!".to_string());
+    fn active_view(&self) -> View<'_> {
+        View {
+            document: &self.document,
+            cursor_position: &self.cursor_position,
+            offset: &self.offset,
+            wrap_offset: self.wrap_offset,
+            rect: self.window_rect(self.active_window),
+            config: &self.config,
+            theme: &self.theme,
+            line_numbers: self.line_numbers,
+            soft_wrap: self.soft_wrap,
+            show_whitespace: self.show_whitespace,
+            indent_guides: self.indent_guides,
+            changes: self.comparison_changes(self.current_buffer),
+            color_column: self.config.color_column(&self.document.file_type()),
+            tab_width: self.tab_width(),
+            active: true,
         }
     }
 
-    fn search(&mut self) {
-        let old_position = self.cursor_position.clone();
-        let mut direction = SearchDirection::Forward;
-        let query = self
-            .prompt(
-                "Search (ESC to cancel, Arrows to navigate): ",
-                |editor, key, query| {
-                    let mut moved = false;
-                    match key.code {
-                        KeyCode::Right | KeyCode::Down => {
-                            direction = SearchDirection::Forward;
-                            editor.move_cursor(KeyCode::Right);
-                            moved = true;
-                        }
-                        KeyCode::Left | KeyCode::Up => direction = SearchDirection::Backword,
-                        _ => direction = SearchDirection::Forward,
-                    }
-                    if let Some(position) =
-                        editor
-                            .document
-                            .find(query, &editor.cursor_position, direction)
-                    {
-                        editor.cursor_position = position;
-                        editor.scroll();
-                    } else if moved {
-                        editor.move_cursor(KeyCode::Left);
-                    }
-                    editor.highlighted_word = Some(query.to_string());
-                },
-            )
-            .unwrap_or(None);
-        if query.is_none() {
-            self.cursor_position = old_position;
-            self.scroll();
+    fn window_view(&self, window: usize, rect: Rect) -> Option<View<'_>> {
+        if window == self.active_window {
+            return Some(View {
+                rect,
+                ..self.active_view()
+            });
         }
-        self.highlighted_word = None;
+        let state = self.windows.get(window)?;
+        let document = if state.buffer == self.current_buffer {
+            &self.document
+        } else {
+            &self.buffers.get(state.buffer)?.document
+        };
+        Some(View {
+            document,
+            cursor_position: &state.cursor_position,
+            offset: &state.offset,
+            wrap_offset: state.wrap_offset,
+            rect,
+            config: &self.config,
+            theme: &self.theme,
+            line_numbers: self.line_numbers,
+            soft_wrap: self.soft_wrap,
+            show_whitespace: self.show_whitespace,
+            indent_guides: self.indent_guides,
+            changes: self.comparison_changes(state.buffer),
+            color_column: self.config.color_column(&document.file_type()),
+            tab_width: self.config.tab_width(&document.file_type()),
+            active: false,
+        })
     }
 
-    fn process_keypress(&mut self, pressed_key: KeyEvent) {
-        match (pressed_key.modifiers, pressed_key.code) {
-            (KeyModifiers::CONTROL, KeyCode::Char('q')) => {
-                if self.quit_times > 0 && self.document.is_dirty() {
-                    self.status_message = StatusMessage::from(format!(
-                        "WARNING! FILE has unsaved changes. Press Ctrl-Q {} more times to quit.",
-                        self.quit_times
-                    ));
-                    self.quit_times -= 1;
-                    return;
-                }
-                self.should_quit = true;
-            }
+    fn comparison_changes(&self, buffer: usize) -> &[Option<Range<usize>>] {
+        self.comparison
+            .as_ref()
+            .map_or(&[], |comparison| comparison.changes(buffer))
+    }
 
-            (
-                _,
-                KeyCode::Up
-                | KeyCode::Left
-                | KeyCode::Down
-                | KeyCode::Right
-                | KeyCode::PageUp
-                | KeyCode::PageDown
-                | KeyCode::End
-                | KeyCode::Home,
-            ) => {
-                self.move_cursor(pressed_key.code);
-            }
+    fn tab_width(&self) -> usize {
+        self.config.tab_width(&self.document.file_type())
+    }
 
-            (KeyModifiers::CONTROL, KeyCode::Char('s')) => {
-                self.save();
-            }
+    fn expand_tab(&self) -> bool {
+        self.config.expand_tab(&self.document.file_type())
+    }
 
-            (KeyModifiers::CONTROL, KeyCode::Char('f')) => self.search(),
+    fn gutter_width(&self) -> usize {
+        self.active_view().gutter_width()
+    }
 
-            (_, KeyCode::Char(c)) => {
-                self.document.insert(&self.cursor_position, c);
-                self.move_cursor(KeyCode::Right);
-            }
+    fn text_width(&self) -> usize {
+        self.active_view().text_width()
+    }
 
-            (_, KeyCode::Enter) => {
-                self.document.insert(&self.cursor_position, '\n');
-                self.move_cursor(KeyCode::Right);
-            }
+    fn text_height(&self) -> usize {
+        self.active_view().text_height()
+    }
 
-            (_, KeyCode::Tab) => {
-                self.document.insert(&self.cursor_position, '\t');
-                self.move_cursor(KeyCode::Right);
-            }
+    fn cursor_column(&self) -> usize {
+        let Position { x, y } = self.cursor_position;
+        self.document
+            .row(y)
+            .map_or(0, |row| row.column(x, self.tab_width()))
+    }
 
-            (_, KeyCode::Delete) => {
-                self.document.delete(&self.cursor_position);
-            }
+    fn wrapped_cursor(&self) -> (usize, Range<usize>) {
+        let Position { x, y } = self.cursor_position;
+        let tab_width = self.tab_width();
+        let row = if let Some(row) = self.document.row(y) {
+            row
+        } else {
+            return (0, 0..0);
+        };
+        let lines = row.wrap(self.text_width(), tab_width);
+        let sub_line = lines
+            .iter()
+            .position(|range| x < range.end)
+            .unwrap_or_else(|| lines.len().saturating_sub(1));
+        let columns = lines.get(sub_line).map_or(0..0, |range| {
+            row.column(range.start, tab_width)..row.column(range.end, tab_width)
+        });
+        (sub_line, columns)
+    }
 
-            (_, KeyCode::Backspace) => {
-                if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
-                    self.move_cursor(KeyCode::Left);
-                    self.document.delete(&self.cursor_position);
-                }
+    #[allow(clippy::arithmetic_side_effects)]
+    fn scroll_wrapped(&mut self) {
+        let height = self.text_height();
+        let width = self.text_width();
+        let y = self.cursor_position.y;
+        let (sub_line, _) = self.wrapped_cursor();
+        self.offset.x = 0;
+        let margin = self.scroll_margin();
+        let above = cmp::min(margin, y);
+        let first = if above == 0 {
+            (y, sub_line)
+        } else {
+            (y.saturating_sub(above), 0)
+        };
+        if first < (self.offset.y, self.wrap_offset) {
+            (self.offset.y, self.wrap_offset) = first;
+            return;
+        }
+        let below = cmp::min(margin, self.document.len().saturating_sub(y));
+        let mut top = y;
+        let mut top_sub_line = sub_line;
+        let mut remaining = height.saturating_sub(1).saturating_sub(below);
+        loop {
+            if top_sub_line >= remaining {
+                top_sub_line -= remaining;
+                break;
             }
-
-            _ => {
-                println!("{:?} \r", pressed_key);
+            remaining -= top_sub_line + 1;
+            if top == 0 {
+                top_sub_line = 0;
+                break;
             }
+            top -= 1;
+            top_sub_line = self.document.row(top).map_or(0, |row| {
+                row.wrap(width, self.tab_width()).len().saturating_sub(1)
+            });
         }
-        self.scroll();
-        if self.quit_times < QUIT_TIMES {
-            self.quit_times = QUIT_TIMES;
-            self.status_message = StatusMessage::from(String::new());
+        if (self.offset.y, self.wrap_offset) < (top, top_sub_line) {
+            self.offset.y = top;
+            self.wrap_offset = top_sub_line;
         }
     }
 
+    #[allow(clippy::integer_division)]
+    fn scroll_margin(&self) -> usize {
+        cmp::min(
+            self.config.scrolloff,
+            self.text_height().saturating_sub(1) / 2,
+        )
+    }
+
+    fn center_cursor(&mut self) {
+        #[allow(clippy::integer_division)]
+        let half = self.text_height() / 2;
+        self.offset.y = self.cursor_position.y.saturating_sub(half);
+        self.wrap_offset = 0;
+        self.scroll();
+    }
+
     fn scroll(&mut self) {
-        let Position { x, y } = self.cursor_position;
-        let width = self.terminal.size().width as usize;
-        let height = self.terminal.size().height as usize;
-        let mut offset = &mut self.offset;
-        if y < offset.y {
-            offset.y = y;
-        } else if y >= offset.y.saturating_add(height) {
-            offset.y = y.saturating_sub(height).saturating_add(1);
+        if self.soft_wrap {
+            self.scroll_wrapped();
+            return;
+        }
+        let x = self.cursor_column();
+        let Position { x: index, y } = self.cursor_position;
+        let end = self
+            .document
+            .row(y)
+            .filter(|row| index < row.len())
+            .map_or(x.saturating_add(1), |row| {
+                row.column(index.saturating_add(1), self.tab_width())
+            });
+        let width = self.text_width();
+        let height = self.text_height();
+        let margin = self.scroll_margin();
+        let top = y.saturating_sub(margin);
+        let bottom = cmp::min(y.saturating_add(margin), self.document.len());
+        let offset = &mut self.offset;
+        if top < offset.y {
+            offset.y = top;
+        } else if bottom >= offset.y.saturating_add(height) {
+            offset.y = bottom.saturating_sub(height).saturating_add(1);
         }
         if x < offset.x {
             offset.x = x;
-        } else if x >= offset.x.saturating_add(width) {
-            offset.x = x.saturating_sub(width).saturating_add(1);
+        } else if end > offset.x.saturating_add(width) {
+            offset.x = end.saturating_sub(width);
         }
     }
 
     fn move_cursor(&mut self, key: KeyCode) {
-        let terminal_height = self.terminal.size().height as usize;
+        let tab_width = self.tab_width();
         let Position { mut x, mut y } = self.cursor_position;
+        let column = self.cursor_column();
         let height = self.document.len();
         let mut width = if let Some(row) = self.document.row(y) {
             row.len()
@@ -300,11 +3685,7 @@ impl Editor {
         };
         match key {
             KeyCode::Up => y = y.saturating_sub(1),
-            KeyCode::Down => {
-                if y < height {
-                    y = y.saturating_add(1);
-                }
-            }
+            KeyCode::Down if y < height => y = y.saturating_add(1),
             KeyCode::Left => {
                 if x > 0 {
                     x -= 1;
@@ -325,24 +3706,16 @@ impl Editor {
                     x = 0;
                 }
             }
-            KeyCode::PageUp => {
-                y = if y > terminal_height {
-                    y.saturating_sub(terminal_height)
-                } else {
-                    0
-                }
-            }
-            KeyCode::PageDown => {
-                y = if y.saturating_add(terminal_height) < height {
-                    y.saturating_add(terminal_height)
-                } else {
-                    height
-                }
-            }
             KeyCode::Home => x = 0,
             KeyCode::End => x = width,
             _ => (),
         }
+        if matches!(key, KeyCode::Up | KeyCode::Down) {
+            x = self
+                .document
+                .row(y)
+                .map_or(0, |row| row.index_at(column, tab_width));
+        }
         width = if let Some(row) = self.document.row(y) {
             row.len()
         } else {
@@ -354,130 +3727,701 @@ impl Editor {
         self.cursor_position = Position { x, y }
     }
 
-    fn draw_welcome_message(&self) {
+    fn draw_welcome_message(width: usize) -> String {
         let mut welcome_message = format!("Hecto editor -- version {}", VERSION);
-        let width = self.terminal.size().width as usize;
         let len = welcome_message.len();
-        #[allow(clippy::integer_arithmetic, clippy::integer_division)]
+        #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
         let padding = width.saturating_sub(len) / 2;
         let spaces = " ".repeat(padding.saturating_sub(1));
         welcome_message = format!("~{}{}", spaces, welcome_message);
         welcome_message.truncate(width);
-        println!("{}\r", welcome_message);
+        welcome_message
     }
 
-    #[allow(clippy::integer_division, clippy::integer_arithmetic)]
-    pub fn draw_row(&self, row: &Row) {
-        let width = self.terminal.size().width as usize;
-        let start = self.offset.x;
-        let end = self.offset.x.saturating_add(width);
-        let row = row.render(start, end);
-        println!("{}\r", row);
+    fn draw_gutter(view: &View, index: usize, continuation: bool) {
+        let width = view.gutter_width();
+        if width == 0 {
+            return;
+        }
+        if continuation {
+            Terminal::print(&" ".repeat(width));
+            return;
+        }
+        if view.document.has_signs() {
+            let ui = &view.theme.ui;
+            let (sign, color) = match (
+                view.document.diagnostic(index),
+                view.document.diff_change(index),
+            ) {
+                (Some(diagnostic), _) => (
+                    diagnostic.severity.sign(),
+                    severity_color(view.theme, diagnostic.severity),
+                ),
+                (None, Some(Change::Added)) => ("+", ui.diff_added),
+                (None, Some(Change::Modified)) => ("~", ui.diff_modified),
+                (None, Some(Change::Removed)) => ("_", ui.diff_removed),
+                (None, None) => (" ", ui.line_number),
+            };
+            Terminal::set_fg_color(color);
+            Terminal::print(sign);
+            Terminal::reset_color();
+            if view.line_numbers == LineNumbers::Off {
+                return;
+            }
+        }
+        let cursor_y = view.cursor_position.y;
+        let number = if view.line_numbers == LineNumbers::Relative && index != cursor_y {
+            index.abs_diff(cursor_y)
+        } else {
+            index.saturating_add(1)
+        };
+        let number = format!(
+            "{:>1$} ",
+            number,
+            width
+                .saturating_sub(1)
+                .saturating_sub(usize::from(view.document.has_signs()))
+        );
+        Terminal::set_fg_color(view.theme.ui.line_number);
+        Terminal::print(&number);
+        Terminal::reset_color();
+    }
+
+    #[allow(clippy::integer_division, clippy::arithmetic_side_effects)]
+    fn draw_row(&self, view: &View, row: &Row, index: usize, columns: Range<usize>) {
+        let selection = self
+            .selection_range()
+            .filter(|_| view.active)
+            .and_then(|(start, end)| {
+                if index < start.y || index > end.y {
+                    return None;
+                }
+                let from = if index == start.y { start.x } else { 0 };
+                let to = if index == end.y { end.x } else { row.len() };
+                Some(from..to)
+            });
+        let background =
+            (view.active && view.config.cursor_line && index == view.cursor_position.y)
+                .then_some(view.theme.ui.current_line);
+        let row_width = row.width(view.tab_width);
+        let visible = cmp::min(columns.end, row_width).saturating_sub(columns.start);
+        let padding = view.text_width().saturating_sub(visible);
+        let row = row.render(
+            view.theme,
+            view.tab_width,
+            columns.start,
+            columns.end,
+            selection,
+            self.matching_bracket
+                .as_ref()
+                .filter(|position| view.active && position.y == index)
+                .map(|position| position.x),
+            &self
+                .spell
+                .as_ref()
+                .map(|spell| row.misspelled(spell, view.document.is_prose()))
+                .unwrap_or_default(),
+            view.changes.get(index).cloned().flatten(),
+            view.color_column,
+            background,
+            view.show_whitespace,
+            view.indent_guides,
+        );
+        Terminal::print(&row);
+        let ruler = view
+            .color_column
+            .and_then(|column| column.checked_sub(columns.start))
+            .and_then(|column| column.checked_sub(visible))
+            .filter(|column| *column < padding);
+        let set_background = || {
+            if let Some(color) = background {
+                Terminal::set_bg_color(color);
+            }
+        };
+        set_background();
+        let message: String = view
+            .document
+            .diagnostic(index)
+            .filter(|_| view.config.inline_diagnostics && columns.end >= row_width)
+            .map(|diagnostic| {
+                let text = format!(
+                    "  {}: {}",
+                    diagnostic.severity.sign(),
+                    diagnostic.message.lines().next().unwrap_or_default()
+                );
+                Terminal::set_fg_color(severity_color(view.theme, diagnostic.severity));
+                text.chars().take(padding).collect()
+            })
+            .unwrap_or_default();
+        let message_width = message.chars().count();
+        if message_width > 0 {
+            Terminal::print(&message);
+            Terminal::reset_color();
+            set_background();
+        }
+        if let Some(column) = ruler.filter(|column| *column >= message_width) {
+            Terminal::print(&" ".repeat(column - message_width));
+            Terminal::set_bg_color(view.theme.ui.color_column);
+            Terminal::print(" ");
+            Terminal::reset_color();
+            set_background();
+            Terminal::print(&" ".repeat(padding - column - 1));
+        } else {
+            Terminal::print(&" ".repeat(padding - message_width));
+        }
+        Terminal::reset_color();
     }
 
-    fn draw_rows(&self) {
-        let height = self.terminal.size().height;
+    fn draw_window(&self, view: &View) {
+        let height = view.text_height();
+        let visual_lines = view.visual_lines();
         for terminal_row in 0..height {
-            Terminal::clear_current_line();
-            if let Some(row) = self
-                .document
-                .row(self.offset.y.saturating_add(terminal_row as usize))
+            Terminal::cursor_position(&Position {
+                x: view.rect.x,
+                y: view.rect.y.saturating_add(terminal_row),
+            });
+            if let Some((
+                row,
+                VisualLine {
+                    row: index,
+                    columns,
+                },
+            )) = visual_lines
+                .get(terminal_row)
+                .cloned()
+                .and_then(|line| Some((view.document.row(line.row)?, line)))
             {
-                self.draw_row(row);
-            } else if self.document.is_empty() && terminal_row == height / 3 {
-                self.draw_welcome_message();
+                Self::draw_gutter(view, index, view.soft_wrap && columns.start > 0);
+                self.draw_row(view, row, index, columns);
             } else {
-                println!("~\r");
+                #[allow(clippy::integer_division, clippy::arithmetic_side_effects)]
+                let line = if view.document.is_empty() && view.active && terminal_row == height / 3
+                {
+                    Self::draw_welcome_message(view.rect.width)
+                } else {
+                    "~".to_string()
+                };
+                Terminal::print(&format!(
+                    "{}{}",
+                    line,
+                    " ".repeat(view.rect.width.saturating_sub(line.len()))
+                ));
             }
         }
+        Terminal::cursor_position(&Position {
+            x: view.rect.x,
+            y: view.rect.y.saturating_add(height),
+        });
+        Self::draw_status_bar(view);
     }
 
-    fn draw_status_bar(&self) {
-        let mut status;
-        let width = self.terminal.size().width as usize;
-        let modified_indicator = if self.document.is_dirty() {
-            " (modified)"
+    fn draw_separator(theme: &Theme, separator: &Rect) {
+        Terminal::set_fg_color(theme.ui.separator);
+        for y in separator.y..separator.y.saturating_add(separator.height) {
+            Terminal::cursor_position(&Position { x: separator.x, y });
+            Terminal::print("|");
+        }
+        Terminal::reset_color();
+    }
+
+    fn draw_status_bar(view: &View) {
+        let width = view.rect.width;
+        let document = view.document;
+        let lookup = |name: &str| -> Option<String> {
+            let line = view.cursor_position.y.saturating_add(1);
+            Some(match name {
+                "filename" => {
+                    let mut file_name = document
+                        .file_name
+                        .clone()
+                        .unwrap_or_else(|| "[No Name]".to_string());
+                    file_name.truncate(20);
+                    file_name
+                }
+                "modified" if document.is_dirty() => " (modified)".to_string(),
+                "modified" => String::new(),
+                "dirty" if document.is_dirty() => "+".to_string(),
+                "dirty" => String::new(),
+                "readonly" if document.is_read_only() => " [RO]".to_string(),
+                "readonly" => String::new(),
+                "lines" => document.len().to_string(),
+                "line" => line.to_string(),
+                "col" => document
+                    .row(view.cursor_position.y)
+                    .map_or(0, |row| row.column(view.cursor_position.x, view.tab_width))
+                    .saturating_add(1)
+                    .to_string(),
+                #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+                "percent" => format!("{}%", line * 100 / cmp::max(document.len(), 1)),
+                "filetype" => document.file_type(),
+                "encoding" => document.encoding().name().to_string(),
+                "eol" => document.line_ending().name().to_string(),
+                "branch" => document.git_branch().unwrap_or_default().to_string(),
+                "diagnostics" => {
+                    let count = |severity| {
+                        document
+                            .diagnostics()
+                            .iter()
+                            .filter(|diagnostic| diagnostic.severity == severity)
+                            .count()
+                    };
+                    let errors = count(Severity::Error);
+                    let warnings = count(Severity::Warning);
+                    match (errors, warnings) {
+                        (0, 0) => String::new(),
+                        (errors, 0) => format!("{}E", errors),
+                        (0, warnings) => format!("{}W", warnings),
+                        (errors, warnings) => format!("{}E {}W", errors, warnings),
+                    }
+                }
+                "loading" => document
+                    .loading_progress()
+                    .map(|progress| format!("loading {}%", progress))
+                    .unwrap_or_default(),
+                _ => return None,
+            })
+        };
+        let options = &view.config.status_bar;
+        let mut status = status_bar::render(&options.left, lookup);
+        let right = status_bar::render(&options.right, lookup);
+        #[allow(clippy::arithmetic_side_effects)]
+        let len = status.len() + right.len();
+        status.push_str(&" ".repeat(width.saturating_sub(len)));
+        status.push_str(&right);
+        status.truncate(width);
+        let ui = &view.theme.ui;
+        if view.active {
+            Terminal::set_bg_color(ui.status_background);
         } else {
-            ""
+            Terminal::set_bg_color(ui.inactive_status_background);
+        }
+        Terminal::set_fg_color(ui.status_foreground);
+        Terminal::print(&status);
+        Terminal::reset_color();
+    }
+
+    fn draw_browser(&self) {
+        let Some(browser) = &self.browser else {
+            return;
         };
-        let mut file_name = "[No Name]".to_string();
-        if let Some(name) = &self.document.file_name {
-            file_name = name.clone();
-            file_name.truncate(20);
+        let size = self.terminal.size();
+        let width = cmp::min(32, usize::from(size.width).saturating_sub(1));
+        let height = usize::from(size.height).saturating_add(1);
+        let start = browser.selected().saturating_sub(height.saturating_sub(1));
+        let entries = browser.entries();
+        let ui = &self.theme.ui;
+        for row in 0..height {
+            Terminal::cursor_position(&Position { x: 0, y: row });
+            let index = row.saturating_add(start);
+            let line = entries.get(index).map_or_else(String::new, |entry| {
+                let marker = match (entry.is_dir, browser.is_expanded(&entry.path)) {
+                    (true, true) => "v ",
+                    (true, false) => "> ",
+                    (false, _) => "  ",
+                };
+                format!("{}{}{}", "  ".repeat(entry.depth), marker, entry.name)
+            });
+            let mut line: String = line.chars().take(width).collect();
+            line.push_str(&" ".repeat(width.saturating_sub(line.chars().count())));
+            if index == browser.selected() && index < entries.len() {
+                Terminal::set_bg_color(ui.selection_background);
+                Terminal::set_fg_color(ui.selection_foreground);
+            } else {
+                Terminal::set_fg_color(ui.text);
+            }
+            Terminal::print(&line);
+            Terminal::reset_color();
+            Terminal::set_fg_color(ui.separator);
+            Terminal::print("|");
+            Terminal::reset_color();
+        }
+    }
+
+    fn draw_pager(&self) {
+        let Some(pager) = &self.pager else {
+            return;
+        };
+        let width = usize::from(self.terminal.size().width);
+        let height = usize::from(self.terminal.size().height);
+        for row in 0..height {
+            Terminal::cursor_position(&Position { x: 0, y: row });
+            Terminal::clear_current_line();
+            match pager.line(pager.offset().saturating_add(row)) {
+                Some(line) => Terminal::print(&line.chars().take(width).collect::<String>()),
+                None => Terminal::print("~"),
+            }
         }
-        status = format!(
-            "{} - {} lines{}",
-            file_name,
-            self.document.len(),
-            modified_indicator
+        Terminal::cursor_position(&Position { x: 0, y: height });
+        let last = cmp::min(pager.offset().saturating_add(height), pager.len());
+        let status = format!(
+            "{} - lines {}-{} of {}",
+            pager.title(),
+            pager.offset().saturating_add(1),
+            last,
+            pager.len()
         );
-        let line_indicator = format!(
-            "{} | {}/{}",
-            self.document.file_type(),
-            self.cursor_position.y.saturating_add(1),
-            self.document.len()
+        let mut status: String = status.chars().take(width).collect();
+        status.push_str(&" ".repeat(width.saturating_sub(status.chars().count())));
+        let ui = &self.theme.ui;
+        Terminal::set_bg_color(ui.status_background);
+        Terminal::set_fg_color(ui.status_foreground);
+        Terminal::print(&status);
+        Terminal::reset_color();
+    }
+
+    fn draw_hex(&self) {
+        let Some(hex) = &self.hex else {
+            return;
+        };
+        let width = usize::from(self.terminal.size().width);
+        let height = usize::from(self.terminal.size().height);
+        let ui = &self.theme.ui;
+        for row in 0..height {
+            Terminal::cursor_position(&Position { x: 0, y: row });
+            Terminal::clear_current_line();
+            let line = hex.offset().saturating_add(row);
+            let Some(bytes) = hex.line(line) else {
+                Terminal::print("~");
+                continue;
+            };
+            let start = line.saturating_mul(hex::BYTES_PER_LINE);
+            Terminal::set_fg_color(ui.line_number);
+            Terminal::print(&format!("{:08x}  ", start));
+            Terminal::reset_color();
+            for index in 0..hex::BYTES_PER_LINE {
+                Terminal::cursor_position(&Position {
+                    x: HexView::hex_column(index),
+                    y: row,
+                });
+                let Some(byte) = bytes.get(index) else {
+                    break;
+                };
+                let selected = start.saturating_add(index) == hex.cursor();
+                if selected {
+                    Terminal::set_bg_color(ui.selection_background);
+                    Terminal::set_fg_color(ui.selection_foreground);
+                }
+                Terminal::print(&format!("{:02x}", byte));
+                Terminal::reset_color();
+                Terminal::cursor_position(&Position {
+                    x: HexView::ascii_column(index),
+                    y: row,
+                });
+                if selected {
+                    Terminal::set_bg_color(ui.selection_background);
+                    Terminal::set_fg_color(ui.selection_foreground);
+                }
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    Terminal::print(&char::from(*byte).to_string());
+                } else {
+                    Terminal::set_fg_color(ui.whitespace);
+                    Terminal::print(".");
+                }
+                Terminal::reset_color();
+            }
+        }
+        Terminal::cursor_position(&Position { x: 0, y: height });
+        let status = format!(
+            "{} - {} bytes{} | {} | {:08x}",
+            hex.file_name(),
+            hex.len(),
+            if hex.is_dirty() { " (modified)" } else { "" },
+            if hex.is_ascii() { "ASCII" } else { "HEX" },
+            hex.cursor()
         );
-        #[allow(clippy::integer_arithmetic)]
-        let len = status.len() + line_indicator.len();
-        status.push_str(&" ".repeat(width.saturating_sub(len)));
-        status = format!("{}{}", status, line_indicator);
-        status.truncate(width);
-        Terminal::set_bg_color(STATUS_BG_COLOR);
-        Terminal::set_fg_color(STATUS_FG_COLOR);
-        println!("{}\r", status);
+        let mut status: String = status.chars().take(width).collect();
+        status.push_str(&" ".repeat(width.saturating_sub(status.chars().count())));
+        Terminal::set_bg_color(ui.status_background);
+        Terminal::set_fg_color(ui.status_foreground);
+        Terminal::print(&status);
         Terminal::reset_color();
     }
 
+    fn draw_finder(&self) {
+        let Some(finder) = &self.finder else {
+            return;
+        };
+        let results = finder.results();
+        let height = cmp::min(results.len(), FINDER_HEIGHT);
+        let bottom = (self.terminal.size().height as usize).saturating_add(1);
+        let width = self.terminal.size().width as usize;
+        let start = finder.selected().saturating_sub(height.saturating_sub(1));
+        let ui = &self.theme.ui;
+        for (row, (index, file_name)) in results
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(height)
+            .enumerate()
+        {
+            Terminal::cursor_position(&Position {
+                x: 0,
+                y: bottom.saturating_sub(height).saturating_add(row),
+            });
+            Terminal::clear_current_line();
+            if index == finder.selected() {
+                Terminal::set_bg_color(ui.selection_background);
+                Terminal::set_fg_color(ui.selection_foreground);
+            } else {
+                Terminal::set_bg_color(ui.status_background);
+                Terminal::set_fg_color(ui.status_foreground);
+            }
+            let mut line = format!(" {}", file_name);
+            line.truncate(width);
+            Terminal::print(&format!(
+                "{}{}",
+                line,
+                " ".repeat(width.saturating_sub(line.len()))
+            ));
+            Terminal::reset_color();
+        }
+    }
+
+    fn draw_quickfix(&self) {
+        let Some(quickfix) = self.quickfix.as_ref().filter(|_| self.quickfix_open) else {
+            return;
+        };
+        let matches = quickfix.matches();
+        let height = cmp::min(matches.len(), FINDER_HEIGHT);
+        let bottom = (self.terminal.size().height as usize).saturating_add(1);
+        let width = self.terminal.size().width as usize;
+        let start = quickfix.selected().saturating_sub(height.saturating_sub(1));
+        let ui = &self.theme.ui;
+        for (row, (index, found)) in matches
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(height)
+            .enumerate()
+        {
+            Terminal::cursor_position(&Position {
+                x: 0,
+                y: bottom.saturating_sub(height).saturating_add(row),
+            });
+            Terminal::clear_current_line();
+            if index == quickfix.selected() {
+                Terminal::set_bg_color(ui.selection_background);
+                Terminal::set_fg_color(ui.selection_foreground);
+            } else {
+                Terminal::set_bg_color(ui.status_background);
+                Terminal::set_fg_color(ui.status_foreground);
+            }
+            let line = format!(
+                " {}:{}: {}",
+                found.path,
+                found.line.saturating_add(1),
+                found.text
+            );
+            let line: String = line.chars().take(width).collect();
+            Terminal::print(&format!(
+                "{}{}",
+                line,
+                " ".repeat(width.saturating_sub(line.chars().count()))
+            ));
+            Terminal::reset_color();
+        }
+    }
+
+    fn draw_word_popup(&self) {
+        let Some(popup) = &self.word_popup else {
+            return;
+        };
+        let results = popup.results();
+        let height = cmp::min(results.len(), POPUP_HEIGHT);
+        let screen_width = self.terminal.size().width as usize;
+        let screen_height = self.terminal.size().height as usize;
+        let width = results
+            .iter()
+            .map(|word| word.len().saturating_add(2))
+            .max()
+            .unwrap_or_default()
+            .min(screen_width);
+        let cursor = self.screen_cursor();
+        let prefix_width = self.word_prefix().map_or(0, |(start, _)| {
+            self.document.row(start.y).map_or(0, |row| {
+                self.cursor_column()
+                    .saturating_sub(row.column(start.x, self.tab_width()))
+            })
+        });
+        let x = cursor
+            .x
+            .saturating_sub(prefix_width)
+            .min(screen_width.saturating_sub(width));
+        let top = if cursor.y.saturating_add(height) < screen_height {
+            cursor.y.saturating_add(1)
+        } else {
+            cursor.y.saturating_sub(height)
+        };
+        let start = popup.selected().saturating_sub(height.saturating_sub(1));
+        let ui = &self.theme.ui;
+        for (row, (index, word)) in results
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(height)
+            .enumerate()
+        {
+            Terminal::cursor_position(&Position {
+                x,
+                y: top.saturating_add(row),
+            });
+            if index == popup.selected() {
+                Terminal::set_bg_color(ui.selection_background);
+                Terminal::set_fg_color(ui.selection_foreground);
+            } else {
+                Terminal::set_bg_color(ui.status_background);
+                Terminal::set_fg_color(ui.status_foreground);
+            }
+            let mut line = format!(" {} ", word);
+            line.truncate(width);
+            Terminal::print(&format!(
+                "{}{}",
+                line,
+                " ".repeat(width.saturating_sub(line.len()))
+            ));
+            Terminal::reset_color();
+        }
+    }
+
+    fn draw_hover(&self) {
+        let Some(lines) = &self.hover else {
+            return;
+        };
+        let height = cmp::min(lines.len(), HOVER_HEIGHT);
+        let screen_width = self.terminal.size().width as usize;
+        let screen_height = self.terminal.size().height as usize;
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count().saturating_add(2))
+            .max()
+            .unwrap_or_default()
+            .min(screen_width);
+        let cursor = self.screen_cursor();
+        let x = cursor.x.min(screen_width.saturating_sub(width));
+        let top = if cursor.y.saturating_add(height) < screen_height {
+            cursor.y.saturating_add(1)
+        } else {
+            cursor.y.saturating_sub(height)
+        };
+        let ui = &self.theme.ui;
+        for (row, text) in lines.iter().take(height).enumerate() {
+            Terminal::cursor_position(&Position {
+                x,
+                y: top.saturating_add(row),
+            });
+            Terminal::set_bg_color(ui.status_background);
+            Terminal::set_fg_color(ui.status_foreground);
+            let line: String = format!(" {} ", text).chars().take(width).collect();
+            Terminal::print(&format!(
+                "{}{}",
+                line,
+                " ".repeat(width.saturating_sub(line.chars().count()))
+            ));
+            Terminal::reset_color();
+        }
+    }
+
     fn draw_message_bar(&self) {
+        Terminal::cursor_position(&Position {
+            x: 0,
+            y: (self.terminal.size().height as usize).saturating_add(1),
+        });
         Terminal::clear_current_line();
+        let width = self.terminal.size().width as usize;
         let message = &self.status_message;
-        if Instant::now() - message.time < Duration::new(5, 0) {
-            let mut text = message.text.clone();
-            text.truncate(self.terminal.size().width as usize);
-            print!("{}", text);
+        let mut text = if message.time.elapsed() < STATUS_MESSAGE_TIMEOUT {
+            message.text.clone()
+        } else {
+            String::new()
+        };
+        if text.is_empty() && self.finder.is_none() && self.browser.is_none() {
+            if let Some(diagnostic) = self.document.diagnostic(self.cursor_position.y) {
+                text = format!(
+                    "{}: {}",
+                    diagnostic.severity.sign(),
+                    diagnostic.message.lines().next().unwrap_or_default()
+                );
+            }
         }
+        if let Some(search_status) = &self.search_status {
+            let len = text.len().saturating_add(search_status.len());
+            text.push_str(&" ".repeat(width.saturating_sub(len)));
+            text.push_str(search_status);
+        }
+        text.truncate(width);
+        Terminal::print(&text);
     }
 
     fn prompt<C>(
+        &mut self,
+        prompt: &str,
+        callback: C,
+    ) -> Result<Option<String>, crossterm::ErrorKind>
+    where
+        C: FnMut(&mut Self, KeyEvent, &mut String),
+    {
+        Ok(self
+            .prompt_raw(prompt, callback)?
+            .filter(|result| !result.is_empty()))
+    }
+
+    fn prompt_raw<C>(
         &mut self,
         prompt: &str,
         mut callback: C,
     ) -> Result<Option<String>, crossterm::ErrorKind>
     where
-        C: FnMut(&mut Self, KeyEvent, &String),
+        C: FnMut(&mut Self, KeyEvent, &mut String),
     {
         let mut result = String::new();
+        let mut cancelled = false;
         'input: loop {
             self.status_message = StatusMessage::from(format!("{}{}", prompt, result));
             self.refresh_screen()?;
             loop {
-                if let Event::Key(pressed_key) = crossterm::event::read()? {
+                if let Event::Key(pressed_key) = self.read_event()? {
                     match (pressed_key.modifiers, pressed_key.code) {
-                        (KeyModifiers::NONE, KeyCode::Char(c)) => {
+                        (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => {
                             result.push(c);
                         }
                         (_, KeyCode::Backspace) => result.truncate(result.len().saturating_sub(1)),
                         (_, KeyCode::Enter) => break 'input,
                         (_, KeyCode::Esc) => {
-                            result.truncate(0);
+                            cancelled = true;
                             break 'input;
                         }
                         _ => (),
                     }
-                    callback(self, pressed_key, &result);
+                    callback(self, pressed_key, &mut result);
                     break;
                 }
             }
         }
         self.status_message = StatusMessage::from(String::new());
-        if result.is_empty() {
+        if cancelled {
             return Ok(None);
         }
         Ok(Some(result))
     }
 }
 
-fn die(error: &crossterm::ErrorKind) {
-    Terminal::clear_screen();
-    panic!("{}", error);
+fn severity_color(theme: &Theme, severity: Severity) -> Color {
+    match severity {
+        Severity::Error => theme.ui.diagnostic_error,
+        Severity::Warning => theme.ui.diagnostic_warning,
+        _ => theme.ui.diagnostic_info,
+    }
+}
+
+fn clamp_position(document: &Document, position: Position) -> Position {
+    let y = cmp::min(position.y, document.len());
+    let len = document.row(y).map_or(0, Row::len);
+    Position {
+        x: cmp::min(position.x, len),
+        y,
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars().take_while(|c| *c == ' ' || *c == '\t').count()
 }
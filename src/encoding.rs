@@ -0,0 +1,113 @@
+use std::io::{Error, ErrorKind};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn from_bom(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(UTF8_BOM) {
+            Some(Self::Utf8Bom)
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            Some(Self::Utf16Le)
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            Some(Self::Utf16Be)
+        } else {
+            None
+        }
+    }
+
+    pub fn detect(bytes: &[u8]) -> Self {
+        Self::from_bom(bytes).unwrap_or_else(|| {
+            if std::str::from_utf8(bytes).is_ok() {
+                Self::Utf8
+            } else {
+                Self::Latin1
+            }
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 BOM",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+
+    pub fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8Bom => UTF8_BOM,
+            Self::Utf16Le => UTF16LE_BOM,
+            Self::Utf16Be => UTF16BE_BOM,
+            Self::Utf8 | Self::Latin1 => &[],
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        match self {
+            Self::Utf8 | Self::Utf8Bom => String::from_utf8_lossy(bytes).into_owned(),
+            Self::Utf16Le | Self::Utf16Be => String::from_utf16_lossy(&self.units(bytes)),
+            Self::Latin1 => bytes.iter().map(|&byte| char::from(byte)).collect(),
+        }
+    }
+
+    pub fn is_valid(self, bytes: &[u8]) -> bool {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        match self {
+            Self::Utf8 | Self::Utf8Bom => std::str::from_utf8(bytes).is_ok(),
+            Self::Utf16Le | Self::Utf16Be => {
+                bytes.len().is_multiple_of(2)
+                    && char::decode_utf16(self.units(bytes)).all(|result| result.is_ok())
+            }
+            Self::Latin1 => true,
+        }
+    }
+
+    fn units(self, bytes: &[u8]) -> Vec<u16> {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| {
+                let pair = <[u8; 2]>::try_from(pair).unwrap_or_default();
+                if self == Self::Utf16Le {
+                    u16::from_le_bytes(pair)
+                } else {
+                    u16::from_be_bytes(pair)
+                }
+            })
+            .collect()
+    }
+
+    pub fn encode(self, text: &str, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => bytes.extend_from_slice(text.as_bytes()),
+            Self::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Self::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+            Self::Latin1 => {
+                for c in text.chars() {
+                    let byte = u8::try_from(c).map_err(|_| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("'{}' cannot be encoded as Latin-1", c),
+                        )
+                    })?;
+                    bytes.push(byte);
+                }
+            }
+        }
+        Ok(())
+    }
+}
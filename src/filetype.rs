@@ -1,16 +1,47 @@
-#[derive(Debug)]
+use std::{fs, io::ErrorKind, path::Path};
+
+use serde::Deserialize;
+
+use crate::{Config, FileTypeConfig};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct FileType {
     name: String,
+    extensions: Vec<String>,
+    interpreters: Vec<String>,
+    indent_after: Vec<char>,
+    auto_pairs: Vec<(char, char)>,
+    #[serde(rename = "highlighting")]
     hl_opts: HighlightingOptions,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
 pub struct HighlightingOptions {
     numbers: bool,
-    strings: bool,
+    string_delimiters: Vec<char>,
+    string_prefixes: Vec<String>,
+    multiline_strings: Vec<String>,
+    raw_strings: bool,
+    heredocs: bool,
+    interpolation: Option<(String, String)>,
+    variables: bool,
     characters: bool,
-    comments: bool,
-    multiline_comments: bool,
+    lifetimes: bool,
+    comment: Option<String>,
+    doc_comments: Vec<String>,
+    multiline_comment: Option<(String, String)>,
+    nested_comments: bool,
+    decorators: bool,
+    attributes: bool,
+    preprocessor: bool,
+    regex_literals: bool,
+    markdown: bool,
+    diff: bool,
+    log_levels: bool,
+    key_separator: Option<char>,
+    table_headers: bool,
     primary_keywords: Vec<String>,
     secondary_keywords: Vec<String>,
 }
@@ -19,103 +50,892 @@ impl Default for FileType {
     fn default() -> Self {
         Self {
             name: String::from("No filetype"),
+            extensions: Vec::new(),
+            interpreters: Vec::new(),
+            indent_after: Vec::new(),
+            auto_pairs: Vec::new(),
             hl_opts: HighlightingOptions::default(),
         }
     }
 }
 
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| (*word).to_string()).collect()
+}
+
+fn modeline(line: &str) -> Option<&str> {
+    if let Some((_, rest)) = line.split_once("-*-") {
+        let (variables, _) = rest.split_once("-*-")?;
+        return variables
+            .split(';')
+            .find_map(|variable| match variable.split_once(':') {
+                Some((key, value)) if key.trim().eq_ignore_ascii_case("mode") => Some(value),
+                Some(_) => None,
+                None => Some(variable),
+            })
+            .map(str::trim)
+            .filter(|mode| !mode.is_empty());
+    }
+    let options = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(index, _)| {
+                line[..*index]
+                    .chars()
+                    .next_back()
+                    .is_none_or(char::is_whitespace)
+            })
+            .map(|(index, _)| &line[index + marker.len()..])
+    })?;
+    options
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syn" | "syntax").then_some(value)
+        })
+        .filter(|value| !value.is_empty())
+}
+
 impl FileType {
     pub fn name(&self) -> String {
         self.name.clone()
     }
 
+    pub fn is_plain(&self) -> bool {
+        self.hl_opts.comment.is_none()
+            && self.hl_opts.multiline_comment.is_none()
+            && self.hl_opts.string_delimiters.is_empty()
+    }
+
+    pub fn indents_after(&self, c: char) -> bool {
+        self.indent_after.contains(&c)
+    }
+
+    pub fn closing_pair(&self, c: char) -> Option<char> {
+        self.auto_pairs
+            .iter()
+            .find(|(open, _)| *open == c)
+            .map(|(_, close)| *close)
+    }
+
+    pub fn is_closing_pair(&self, c: char) -> bool {
+        self.auto_pairs.iter().any(|(_, close)| *close == c)
+    }
+
     pub fn highlighting_options(&self) -> &HighlightingOptions {
         &self.hl_opts
     }
 
-    pub fn from(file_name: &str) -> Self {
-        if file_name.ends_with(".rs") {
-            return Self {
-                name: String::from("Rust"),
-                hl_opts: HighlightingOptions {
-                    numbers: true,
-                    strings: true,
-                    characters: true,
-                    comments: true,
-                    multiline_comments: true,
-                    primary_keywords: vec![
-                        "as".to_string(),
-                        "break".to_string(),
-                        "const".to_string(),
-                        "continue".to_string(),
-                        "crate".to_string(),
-                        "else".to_string(),
-                        "enum".to_string(),
-                        "extern".to_string(),
-                        "false".to_string(),
-                        "fn".to_string(),
-                        "for".to_string(),
-                        "if".to_string(),
-                        "impl".to_string(),
-                        "in".to_string(),
-                        "let".to_string(),
-                        "loop".to_string(),
-                        "match".to_string(),
-                        "mod".to_string(),
-                        "move".to_string(),
-                        "mut".to_string(),
-                        "pub".to_string(),
-                        "ref".to_string(),
-                        "return".to_string(),
-                        "self".to_string(),
-                        "Self".to_string(),
-                        "static".to_string(),
-                        "struct".to_string(),
-                        "super".to_string(),
-                        "trait".to_string(),
-                        "true".to_string(),
-                        "type".to_string(),
-                        "unsafe".to_string(),
-                        "use".to_string(),
-                        "where".to_string(),
-                        "while".to_string(),
-                        "dyn".to_string(),
-                        "abstract".to_string(),
-                        "become".to_string(),
-                        "box".to_string(),
-                        "do".to_string(),
-                        "final".to_string(),
-                        "macro".to_string(),
-                        "override".to_string(),
-                        "priv".to_string(),
-                        "typeof".to_string(),
-                        "unsized".to_string(),
-                        "virtual".to_string(),
-                        "yield".to_string(),
-                        "async".to_string(),
-                        "await".to_string(),
-                        "try".to_string(),
-                    ],
-                    secondary_keywords: vec![
-                        "bool".to_string(),
-                        "char".to_string(),
-                        "i8".to_string(),
-                        "i16".to_string(),
-                        "i32".to_string(),
-                        "i64".to_string(),
-                        "isize".to_string(),
-                        "u8".to_string(),
-                        "u16".to_string(),
-                        "u32".to_string(),
-                        "u64".to_string(),
-                        "usize".to_string(),
-                        "f32".to_string(),
-                        "f64".to_string(),
-                    ],
-                },
+    pub fn matches(&self, file_name: &str) -> bool {
+        Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.extensions.iter().any(|known| known == extension))
+    }
+
+    pub fn runs(&self, first_line: &str) -> bool {
+        let command = if let Some(command) = first_line.strip_prefix("#!") {
+            command
+        } else {
+            return false;
+        };
+        let mut words = command.split_whitespace();
+        let mut interpreter = words.next().and_then(|path| path.rsplit('/').next());
+        if interpreter == Some("env") {
+            interpreter = words.find(|word| !word.starts_with('-'));
+        }
+        interpreter
+            .map(|interpreter| {
+                interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+            })
+            .is_some_and(|interpreter| self.interpreters.iter().any(|known| known == interpreter))
+    }
+
+    pub fn detect(file_name: &str, first_line: Option<&str>, file_types: &[Self]) -> Self {
+        file_types
+            .iter()
+            .find(|file_type| file_type.matches(file_name))
+            .or_else(|| {
+                let first_line = first_line?;
+                file_types
+                    .iter()
+                    .find(|file_type| file_type.runs(first_line))
+            })
+            .or_else(|| {
+                let name = modeline(first_line?)?;
+                file_types.iter().find(|file_type| file_type.is_named(name))
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn add_keywords(&mut self, config: &FileTypeConfig) {
+        self.hl_opts
+            .primary_keywords
+            .extend(config.keywords.iter().cloned());
+        self.hl_opts
+            .secondary_keywords
+            .extend(config.types.iter().cloned());
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self
+                .extensions
+                .iter()
+                .chain(&self.interpreters)
+                .any(|known| known.eq_ignore_ascii_case(name))
+    }
+
+    pub fn find(name: &str, file_types: &[Self]) -> Option<Self> {
+        file_types
+            .iter()
+            .find(|file_type| file_type.is_named(name))
+            .cloned()
+    }
+
+    pub fn load() -> (Vec<Self>, Vec<String>) {
+        let mut file_types = Vec::new();
+        let mut errors = Vec::new();
+        if let Some(dir) = Config::dir() {
+            let dir = dir.join("syntaxes");
+            let paths = match fs::read_dir(&dir) {
+                Ok(entries) => {
+                    let mut paths: Vec<_> = entries
+                        .filter_map(Result::ok)
+                        .map(|entry| entry.path())
+                        .filter(|path| {
+                            path.extension()
+                                .is_some_and(|extension| extension == "toml")
+                        })
+                        .collect();
+                    paths.sort();
+                    paths
+                }
+                Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(error) => {
+                    errors.push(format!("Could not read {}: {}", dir.display(), error));
+                    Vec::new()
+                }
             };
+            for path in paths {
+                let contents = match fs::read_to_string(&path) {
+                    Ok(contents) => contents,
+                    Err(error) => {
+                        errors.push(format!("Could not read {}: {}", path.display(), error));
+                        continue;
+                    }
+                };
+                match toml::from_str(&contents) {
+                    Ok(file_type) => file_types.push(file_type),
+                    Err(error) => errors.push(format!("Invalid {}: {}", path.display(), error)),
+                }
+            }
+        }
+        file_types.append(&mut Self::builtin());
+        (file_types, errors)
+    }
+
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::rust(),
+            Self::python(),
+            Self::c(),
+            Self::cpp(),
+            Self::javascript(),
+            Self::typescript(),
+            Self::shell(),
+            Self::markdown(),
+            Self::diff(),
+            Self::log(),
+            Self::json(),
+            Self::toml(),
+            Self::yaml(),
+        ]
+    }
+
+    fn rust() -> Self {
+        Self {
+            name: String::from("Rust"),
+            extensions: words(&["rs"]),
+            interpreters: Vec::new(),
+            indent_after: vec!['{', '[', '('],
+            auto_pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"'],
+                string_prefixes: words(&["b"]),
+                multiline_strings: Vec::new(),
+                raw_strings: true,
+                heredocs: false,
+                interpolation: None,
+                variables: false,
+                characters: true,
+                lifetimes: true,
+                comment: Some(String::from("//")),
+                doc_comments: words(&["///", "//!"]),
+                multiline_comment: Some((String::from("/*"), String::from("*/"))),
+                nested_comments: true,
+                decorators: false,
+                attributes: true,
+                preprocessor: false,
+                regex_literals: false,
+                markdown: false,
+                diff: false,
+                log_levels: false,
+                key_separator: None,
+                table_headers: false,
+                primary_keywords: words(&[
+                    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false",
+                    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+                    "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+                    "true", "type", "unsafe", "use", "where", "while", "dyn", "abstract", "become",
+                    "box", "do", "final", "macro", "override", "priv", "typeof", "unsized",
+                    "virtual", "yield", "async", "await", "try",
+                ]),
+                secondary_keywords: words(&[
+                    "bool", "char", "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64",
+                    "usize", "f32", "f64",
+                ]),
+            },
+        }
+    }
+
+    fn python() -> Self {
+        Self {
+            name: String::from("Python"),
+            extensions: words(&["py", "pyw", "pyi"]),
+            interpreters: words(&["python"]),
+            indent_after: vec!['{', '[', '(', ':'],
+            auto_pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"', '\''],
+                string_prefixes: words(&[
+                    "f", "r", "b", "u", "rb", "br", "fr", "rf", "F", "R", "B", "U", "Rb", "bR",
+                    "RB", "BR", "Fr", "fR", "FR", "rF", "Rf", "RF",
+                ]),
+                multiline_strings: words(&["\"\"\"", "'''"]),
+                raw_strings: false,
+                heredocs: false,
+                interpolation: None,
+                variables: false,
+                characters: false,
+                lifetimes: false,
+                comment: Some(String::from("#")),
+                doc_comments: Vec::new(),
+                multiline_comment: None,
+                nested_comments: false,
+                decorators: true,
+                attributes: false,
+                preprocessor: false,
+                regex_literals: false,
+                markdown: false,
+                diff: false,
+                log_levels: false,
+                key_separator: None,
+                table_headers: false,
+                primary_keywords: words(&[
+                    "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                    "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+                    "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise",
+                    "return", "try", "while", "with", "yield", "match", "case", "True", "False",
+                    "None", "self",
+                ]),
+                secondary_keywords: words(&[
+                    "bool",
+                    "bytes",
+                    "complex",
+                    "dict",
+                    "float",
+                    "frozenset",
+                    "int",
+                    "list",
+                    "object",
+                    "set",
+                    "str",
+                    "tuple",
+                    "type",
+                ]),
+            },
+        }
+    }
+
+    fn c() -> Self {
+        Self {
+            name: String::from("C"),
+            extensions: words(&["c", "h"]),
+            interpreters: Vec::new(),
+            indent_after: vec!['{', '[', '('],
+            auto_pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"'],
+                string_prefixes: words(&["L", "u8", "u", "U"]),
+                multiline_strings: Vec::new(),
+                raw_strings: false,
+                heredocs: false,
+                interpolation: None,
+                variables: false,
+                characters: true,
+                lifetimes: false,
+                comment: Some(String::from("//")),
+                doc_comments: Vec::new(),
+                multiline_comment: Some((String::from("/*"), String::from("*/"))),
+                nested_comments: false,
+                decorators: false,
+                attributes: false,
+                preprocessor: true,
+                regex_literals: false,
+                markdown: false,
+                diff: false,
+                log_levels: false,
+                key_separator: None,
+                table_headers: false,
+                primary_keywords: words(&[
+                    "auto",
+                    "break",
+                    "case",
+                    "const",
+                    "continue",
+                    "default",
+                    "do",
+                    "else",
+                    "enum",
+                    "extern",
+                    "for",
+                    "goto",
+                    "if",
+                    "inline",
+                    "register",
+                    "restrict",
+                    "return",
+                    "sizeof",
+                    "static",
+                    "struct",
+                    "switch",
+                    "typedef",
+                    "union",
+                    "volatile",
+                    "while",
+                    "_Alignas",
+                    "_Alignof",
+                    "_Atomic",
+                    "_Generic",
+                    "_Noreturn",
+                    "_Static_assert",
+                    "_Thread_local",
+                    "NULL",
+                    "true",
+                    "false",
+                ]),
+                secondary_keywords: words(&[
+                    "bool",
+                    "char",
+                    "double",
+                    "float",
+                    "int",
+                    "long",
+                    "short",
+                    "signed",
+                    "unsigned",
+                    "void",
+                    "size_t",
+                    "ssize_t",
+                    "ptrdiff_t",
+                    "int8_t",
+                    "int16_t",
+                    "int32_t",
+                    "int64_t",
+                    "uint8_t",
+                    "uint16_t",
+                    "uint32_t",
+                    "uint64_t",
+                    "FILE",
+                ]),
+            },
+        }
+    }
+
+    fn cpp() -> Self {
+        Self {
+            name: String::from("C++"),
+            extensions: words(&["cpp", "hpp", "cc", "hh", "cxx", "hxx"]),
+            interpreters: Vec::new(),
+            indent_after: vec!['{', '[', '('],
+            auto_pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"'],
+                string_prefixes: words(&["L", "u8", "u", "U"]),
+                multiline_strings: Vec::new(),
+                raw_strings: false,
+                heredocs: false,
+                interpolation: None,
+                variables: false,
+                characters: true,
+                lifetimes: false,
+                comment: Some(String::from("//")),
+                doc_comments: Vec::new(),
+                multiline_comment: Some((String::from("/*"), String::from("*/"))),
+                nested_comments: false,
+                decorators: false,
+                attributes: false,
+                preprocessor: true,
+                regex_literals: false,
+                markdown: false,
+                diff: false,
+                log_levels: false,
+                key_separator: None,
+                table_headers: false,
+                primary_keywords: words(&[
+                    "auto",
+                    "break",
+                    "case",
+                    "const",
+                    "continue",
+                    "default",
+                    "do",
+                    "else",
+                    "enum",
+                    "extern",
+                    "for",
+                    "goto",
+                    "if",
+                    "inline",
+                    "register",
+                    "restrict",
+                    "return",
+                    "sizeof",
+                    "static",
+                    "struct",
+                    "switch",
+                    "typedef",
+                    "union",
+                    "volatile",
+                    "while",
+                    "_Alignas",
+                    "_Alignof",
+                    "_Atomic",
+                    "_Generic",
+                    "_Noreturn",
+                    "_Static_assert",
+                    "_Thread_local",
+                    "NULL",
+                    "true",
+                    "false",
+                    "alignas",
+                    "alignof",
+                    "catch",
+                    "class",
+                    "concept",
+                    "consteval",
+                    "constexpr",
+                    "constinit",
+                    "const_cast",
+                    "co_await",
+                    "co_return",
+                    "co_yield",
+                    "decltype",
+                    "delete",
+                    "dynamic_cast",
+                    "explicit",
+                    "export",
+                    "friend",
+                    "mutable",
+                    "namespace",
+                    "new",
+                    "noexcept",
+                    "nullptr",
+                    "operator",
+                    "override",
+                    "final",
+                    "private",
+                    "protected",
+                    "public",
+                    "reinterpret_cast",
+                    "requires",
+                    "static_assert",
+                    "static_cast",
+                    "template",
+                    "this",
+                    "throw",
+                    "try",
+                    "typeid",
+                    "typename",
+                    "using",
+                    "virtual",
+                ]),
+                secondary_keywords: words(&[
+                    "bool",
+                    "char",
+                    "double",
+                    "float",
+                    "int",
+                    "long",
+                    "short",
+                    "signed",
+                    "unsigned",
+                    "void",
+                    "size_t",
+                    "ssize_t",
+                    "ptrdiff_t",
+                    "int8_t",
+                    "int16_t",
+                    "int32_t",
+                    "int64_t",
+                    "uint8_t",
+                    "uint16_t",
+                    "uint32_t",
+                    "uint64_t",
+                    "FILE",
+                    "wchar_t",
+                    "char8_t",
+                    "char16_t",
+                    "char32_t",
+                    "std",
+                    "string",
+                    "vector",
+                    "map",
+                    "unique_ptr",
+                    "shared_ptr",
+                ]),
+            },
+        }
+    }
+
+    fn javascript() -> Self {
+        Self {
+            name: String::from("JavaScript"),
+            extensions: words(&["js", "jsx", "mjs", "cjs"]),
+            interpreters: words(&["node"]),
+            indent_after: vec!['{', '[', '('],
+            auto_pairs: vec![
+                ('(', ')'),
+                ('[', ']'),
+                ('{', '}'),
+                ('"', '"'),
+                ('\'', '\''),
+                ('`', '`'),
+            ],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"', '\''],
+                string_prefixes: Vec::new(),
+                multiline_strings: words(&["`"]),
+                raw_strings: false,
+                heredocs: false,
+                interpolation: Some((String::from("${"), String::from("}"))),
+                variables: false,
+                characters: false,
+                lifetimes: false,
+                comment: Some(String::from("//")),
+                doc_comments: Vec::new(),
+                multiline_comment: Some((String::from("/*"), String::from("*/"))),
+                nested_comments: false,
+                decorators: true,
+                attributes: false,
+                preprocessor: false,
+                regex_literals: true,
+                markdown: false,
+                diff: false,
+                log_levels: false,
+                key_separator: None,
+                table_headers: false,
+                primary_keywords: words(&[
+                    "async",
+                    "await",
+                    "break",
+                    "case",
+                    "catch",
+                    "class",
+                    "const",
+                    "continue",
+                    "debugger",
+                    "default",
+                    "delete",
+                    "do",
+                    "else",
+                    "export",
+                    "extends",
+                    "finally",
+                    "for",
+                    "from",
+                    "function",
+                    "if",
+                    "import",
+                    "in",
+                    "instanceof",
+                    "let",
+                    "new",
+                    "of",
+                    "return",
+                    "static",
+                    "super",
+                    "switch",
+                    "this",
+                    "throw",
+                    "try",
+                    "typeof",
+                    "var",
+                    "void",
+                    "while",
+                    "with",
+                    "yield",
+                    "get",
+                    "set",
+                    "null",
+                    "undefined",
+                    "true",
+                    "false",
+                ]),
+                secondary_keywords: words(&[
+                    "Array", "BigInt", "Boolean", "Date", "Error", "JSON", "Map", "Math", "Number",
+                    "Object", "Promise", "RegExp", "Set", "String", "Symbol", "WeakMap", "WeakSet",
+                ]),
+            },
+        }
+    }
+
+    fn typescript() -> Self {
+        Self {
+            name: String::from("TypeScript"),
+            extensions: words(&["ts", "tsx", "mts", "cts"]),
+            interpreters: words(&["deno", "ts-node"]),
+            indent_after: vec!['{', '[', '('],
+            auto_pairs: vec![
+                ('(', ')'),
+                ('[', ']'),
+                ('{', '}'),
+                ('"', '"'),
+                ('\'', '\''),
+                ('`', '`'),
+            ],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"', '\''],
+                string_prefixes: Vec::new(),
+                multiline_strings: words(&["`"]),
+                raw_strings: false,
+                heredocs: false,
+                interpolation: Some((String::from("${"), String::from("}"))),
+                variables: false,
+                characters: false,
+                lifetimes: false,
+                comment: Some(String::from("//")),
+                doc_comments: Vec::new(),
+                multiline_comment: Some((String::from("/*"), String::from("*/"))),
+                nested_comments: false,
+                decorators: true,
+                attributes: false,
+                preprocessor: false,
+                regex_literals: true,
+                markdown: false,
+                diff: false,
+                log_levels: false,
+                key_separator: None,
+                table_headers: false,
+                primary_keywords: words(&[
+                    "async",
+                    "await",
+                    "break",
+                    "case",
+                    "catch",
+                    "class",
+                    "const",
+                    "continue",
+                    "debugger",
+                    "default",
+                    "delete",
+                    "do",
+                    "else",
+                    "export",
+                    "extends",
+                    "finally",
+                    "for",
+                    "from",
+                    "function",
+                    "if",
+                    "import",
+                    "in",
+                    "instanceof",
+                    "let",
+                    "new",
+                    "of",
+                    "return",
+                    "static",
+                    "super",
+                    "switch",
+                    "this",
+                    "throw",
+                    "try",
+                    "typeof",
+                    "var",
+                    "void",
+                    "while",
+                    "with",
+                    "yield",
+                    "get",
+                    "set",
+                    "null",
+                    "undefined",
+                    "true",
+                    "false",
+                    "abstract",
+                    "as",
+                    "declare",
+                    "enum",
+                    "implements",
+                    "infer",
+                    "interface",
+                    "is",
+                    "keyof",
+                    "module",
+                    "namespace",
+                    "private",
+                    "protected",
+                    "public",
+                    "readonly",
+                    "satisfies",
+                    "type",
+                ]),
+                secondary_keywords: words(&[
+                    "Array", "BigInt", "Boolean", "Date", "Error", "JSON", "Map", "Math", "Number",
+                    "Object", "Promise", "RegExp", "Set", "String", "Symbol", "WeakMap", "WeakSet",
+                    "any", "bigint", "boolean", "never", "number", "object", "string", "symbol",
+                    "unknown",
+                ]),
+            },
+        }
+    }
+
+    fn shell() -> Self {
+        Self {
+            name: String::from("Shell"),
+            extensions: words(&["sh", "bash", "zsh", "ksh"]),
+            interpreters: words(&["sh", "bash", "zsh", "ksh", "dash"]),
+            indent_after: vec!['{', '('],
+            auto_pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"', '\''],
+                heredocs: true,
+                variables: true,
+                comment: Some(String::from("#")),
+                primary_keywords: words(&[
+                    "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until",
+                    "do", "done", "in", "function", "select", "time", "return", "break",
+                    "continue", "exit", "local", "export", "readonly", "declare",
+                ]),
+                secondary_keywords: words(&[
+                    "echo", "printf", "read", "cd", "pwd", "test", "source", "alias", "unset",
+                    "shift", "eval", "exec", "set", "trap", "wait", "kill", "true", "false",
+                ]),
+                ..HighlightingOptions::default()
+            },
+        }
+    }
+
+    fn markdown() -> Self {
+        Self {
+            name: String::from("Markdown"),
+            extensions: words(&["md", "markdown", "mdown", "mkd"]),
+            interpreters: Vec::new(),
+            indent_after: Vec::new(),
+            auto_pairs: vec![('(', ')'), ('[', ']'), ('`', '`')],
+            hl_opts: HighlightingOptions {
+                multiline_comment: Some((String::from("<!--"), String::from("-->"))),
+                markdown: true,
+                ..HighlightingOptions::default()
+            },
+        }
+    }
+
+    fn diff() -> Self {
+        Self {
+            name: String::from("Diff"),
+            extensions: words(&["diff", "patch", "rej"]),
+            interpreters: Vec::new(),
+            indent_after: Vec::new(),
+            auto_pairs: Vec::new(),
+            hl_opts: HighlightingOptions {
+                diff: true,
+                ..HighlightingOptions::default()
+            },
+        }
+    }
+
+    fn log() -> Self {
+        Self {
+            name: String::from("Log"),
+            extensions: words(&["log"]),
+            interpreters: Vec::new(),
+            indent_after: Vec::new(),
+            auto_pairs: Vec::new(),
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"'],
+                log_levels: true,
+                ..HighlightingOptions::default()
+            },
+        }
+    }
+
+    fn json() -> Self {
+        Self {
+            name: String::from("JSON"),
+            extensions: words(&["json", "jsonc"]),
+            interpreters: Vec::new(),
+            indent_after: vec!['{', '['],
+            auto_pairs: vec![('[', ']'), ('{', '}'), ('"', '"')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"'],
+                key_separator: Some(':'),
+                primary_keywords: words(&["true", "false", "null"]),
+                ..HighlightingOptions::default()
+            },
+        }
+    }
+
+    fn toml() -> Self {
+        Self {
+            name: String::from("TOML"),
+            extensions: words(&["toml"]),
+            interpreters: Vec::new(),
+            indent_after: vec!['['],
+            auto_pairs: vec![('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"', '\''],
+                multiline_strings: words(&["\"\"\"", "'''"]),
+                comment: Some(String::from("#")),
+                key_separator: Some('='),
+                table_headers: true,
+                primary_keywords: words(&["true", "false", "inf", "nan"]),
+                ..HighlightingOptions::default()
+            },
+        }
+    }
+
+    fn yaml() -> Self {
+        Self {
+            name: String::from("YAML"),
+            extensions: words(&["yaml", "yml"]),
+            interpreters: Vec::new(),
+            indent_after: vec![':'],
+            auto_pairs: vec![('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            hl_opts: HighlightingOptions {
+                numbers: true,
+                string_delimiters: vec!['"', '\''],
+                comment: Some(String::from("#")),
+                key_separator: Some(':'),
+                primary_keywords: words(&[
+                    "true", "false", "null", "True", "False", "Null", "TRUE", "FALSE", "NULL",
+                    "yes", "no", "on", "off",
+                ]),
+                ..HighlightingOptions::default()
+            },
         }
-        Self::default()
     }
 }
 
@@ -124,16 +944,86 @@ impl HighlightingOptions {
         self.numbers
     }
 
-    pub fn strings(&self) -> bool {
-        self.strings
+    pub fn string_delimiters(&self) -> &Vec<char> {
+        &self.string_delimiters
+    }
+
+    pub fn string_prefixes(&self) -> &Vec<String> {
+        &self.string_prefixes
+    }
+
+    pub fn multiline_strings(&self) -> &Vec<String> {
+        &self.multiline_strings
+    }
+
+    pub fn raw_strings(&self) -> bool {
+        self.raw_strings
+    }
+
+    pub fn heredocs(&self) -> bool {
+        self.heredocs
+    }
+
+    pub fn interpolation(&self) -> Option<(&str, &str)> {
+        self.interpolation
+            .as_ref()
+            .map(|(start, end)| (start.as_str(), end.as_str()))
+    }
+
+    pub fn variables(&self) -> bool {
+        self.variables
     }
 
     pub fn characters(&self) -> bool {
         self.characters
     }
 
-    pub fn comments(&self) -> bool {
-        self.comments
+    pub fn lifetimes(&self) -> bool {
+        self.lifetimes
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn doc_comments(&self) -> &Vec<String> {
+        &self.doc_comments
+    }
+
+    pub fn decorators(&self) -> bool {
+        self.decorators
+    }
+
+    pub fn attributes(&self) -> bool {
+        self.attributes
+    }
+
+    pub fn preprocessor(&self) -> bool {
+        self.preprocessor
+    }
+
+    pub fn regex_literals(&self) -> bool {
+        self.regex_literals
+    }
+
+    pub fn markdown(&self) -> bool {
+        self.markdown
+    }
+
+    pub fn diff(&self) -> bool {
+        self.diff
+    }
+
+    pub fn log_levels(&self) -> bool {
+        self.log_levels
+    }
+
+    pub fn key_separator(&self) -> Option<char> {
+        self.key_separator
+    }
+
+    pub fn table_headers(&self) -> bool {
+        self.table_headers
     }
 
     pub fn primary_keywords(&self) -> &Vec<String> {
@@ -144,7 +1034,13 @@ impl HighlightingOptions {
         &self.secondary_keywords
     }
 
-    pub fn multiline_comments(&self) -> bool {
-        self.multiline_comments
+    pub fn nested_comments(&self) -> bool {
+        self.nested_comments
+    }
+
+    pub fn multiline_comment(&self) -> Option<(&str, &str)> {
+        self.multiline_comment
+            .as_ref()
+            .map(|(start, end)| (start.as_str(), end.as_str()))
     }
 }
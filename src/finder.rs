@@ -0,0 +1,223 @@
+use std::{cmp::Reverse, fs, path::Path};
+
+use regex::Regex;
+
+const MAX_FILES: usize = 50_000;
+
+#[derive(Debug)]
+struct IgnoreRule {
+    base: String,
+    pattern: Regex,
+    anchored: bool,
+    negate: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRule {
+    fn parse(base: &str, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let pattern = Regex::new(&format!("^{}$", glob_to_regex(line))).ok()?;
+        Some(Self {
+            base: base.to_string(),
+            pattern,
+            anchored,
+            negate,
+            dir_only,
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Some(path) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        if self.anchored {
+            self.pattern.is_match(path)
+        } else {
+            self.pattern
+                .is_match(path.rsplit('/').next().unwrap_or(path))
+        }
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => regex.push('['),
+            ']' => regex.push(']'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+fn is_ignored(rules: &[IgnoreRule], path: &str, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(path, is_dir))
+        .is_some_and(|rule| !rule.negate)
+}
+
+pub fn index(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut rules = Vec::new();
+    let mut dirs = vec![String::new()];
+    while let Some(dir) = dirs.pop() {
+        let path = root.join(&dir);
+        if let Ok(contents) = fs::read_to_string(path.join(".gitignore")) {
+            rules.extend(
+                contents
+                    .lines()
+                    .filter_map(|line| IgnoreRule::parse(&dir, line)),
+            );
+        }
+        let Ok(entries) = fs::read_dir(&path) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+        entries.sort_by_key(fs::DirEntry::file_name);
+        for entry in entries {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name == ".git" {
+                continue;
+            }
+            let relative = format!("{}{}", dir, name);
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_ignored(&rules, &relative, is_dir) {
+                continue;
+            }
+            if is_dir {
+                dirs.push(format!("{}/", relative));
+            } else {
+                files.push(relative);
+                if files.len() >= MAX_FILES {
+                    return files;
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+pub fn score(query: &str, candidate: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut consecutive = false;
+    let mut candidate_chars = candidate.chars();
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let c = candidate_chars.next()?;
+            let boundary = previous.is_none_or(|previous| "/_-. ".contains(previous));
+            previous = Some(c);
+            if c.to_lowercase().eq(wanted.to_lowercase()) {
+                score += 1;
+                if consecutive {
+                    score += 4;
+                }
+                if boundary {
+                    score += 6;
+                }
+                consecutive = true;
+                break;
+            }
+            consecutive = false;
+        }
+    }
+    Some(score)
+}
+
+#[derive(Debug, Default)]
+pub struct Finder {
+    files: Vec<String>,
+    results: Vec<String>,
+    selected: usize,
+}
+
+impl Finder {
+    pub fn new(root: &Path) -> Self {
+        let files = index(root);
+        Self {
+            results: files.clone(),
+            files,
+            selected: 0,
+        }
+    }
+
+    pub fn from_files(files: Vec<String>) -> Self {
+        Self {
+            results: files.clone(),
+            files,
+            selected: 0,
+        }
+    }
+
+    pub fn update(&mut self, query: &str) {
+        self.selected = 0;
+        if query.is_empty() {
+            self.results.clone_from(&self.files);
+            return;
+        }
+        let mut scored: Vec<(usize, &String)> = self
+            .files
+            .iter()
+            .filter_map(|file| Some((score(query, file)?, file)))
+            .collect();
+        scored.sort_by_key(|(score, file)| (Reverse(*score), file.len()));
+        self.results = scored.into_iter().map(|(_, file)| file.clone()).collect();
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected.saturating_add(1) < self.results.len() {
+            self.selected = self.selected.saturating_add(1);
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn results(&self) -> &[String] {
+        &self.results
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selection(&self) -> Option<&String> {
+        self.results.get(self.selected)
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    iter::{Chain, Rev},
+    ops::{Index, IndexMut, Range},
+    slice,
+};
+
+#[derive(Debug, Clone)]
+pub struct GapBuffer<T> {
+    front: Vec<T>,
+    back: Vec<T>,
+}
+
+impl<T> Default for GapBuffer<T> {
+    fn default() -> Self {
+        Self {
+            front: Vec::new(),
+            back: Vec::new(),
+        }
+    }
+}
+
+impl<T> FromIterator<T> for GapBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            front: iter.into_iter().collect(),
+            back: Vec::new(),
+        }
+    }
+}
+
+impl<T> GapBuffer<T> {
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.front.is_empty() && self.back.is_empty()
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn back_index(&self, index: usize) -> Option<usize> {
+        let offset = index.checked_sub(self.front.len())?;
+        if offset < self.back.len() {
+            Some(self.back.len() - 1 - offset)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.front.len() {
+            return self.front.get(index);
+        }
+        self.back.get(self.back_index(index)?)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.front.len() {
+            return self.front.get_mut(index);
+        }
+        let index = self.back_index(index)?;
+        self.back.get_mut(index)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    fn move_gap(&mut self, index: usize) {
+        while self.front.len() > index {
+            if let Some(item) = self.front.pop() {
+                self.back.push(item);
+            }
+        }
+        while self.front.len() < index {
+            if let Some(item) = self.back.pop() {
+                self.front.push(item);
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        let len = self.len();
+        self.insert(len, item);
+    }
+
+    pub fn insert(&mut self, index: usize, item: T) {
+        self.move_gap(index);
+        self.front.push(item);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.move_gap(index);
+        self.back.pop()
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn remove_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.len());
+        if range.start >= end {
+            return;
+        }
+        self.move_gap(range.start);
+        self.back.truncate(self.back.len() - (end - range.start));
+    }
+
+    pub fn iter(&self) -> Chain<slice::Iter<'_, T>, Rev<slice::Iter<'_, T>>> {
+        self.front.iter().chain(self.back.iter().rev())
+    }
+
+    pub fn iter_mut(&mut self) -> Chain<slice::IterMut<'_, T>, Rev<slice::IterMut<'_, T>>> {
+        self.front.iter_mut().chain(self.back.iter_mut().rev())
+    }
+}
+
+impl<T> Index<usize> for GapBuffer<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for GapBuffer<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("index out of bounds")
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fs, path::Path, thread};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{finder, Matcher};
+
+const MAX_MATCHES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+pub fn search(root: &Path, matcher: &Matcher) -> Vec<Match> {
+    let files = finder::index(root);
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let chunk = files.len().div_ceil(threads).max(1);
+    let mut matches: Vec<Match> = thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk)
+            .map(|files| {
+                scope.spawn(move || {
+                    files
+                        .iter()
+                        .flat_map(|file| search_file(root, file, matcher))
+                        .collect::<Vec<Match>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+    matches.truncate(MAX_MATCHES);
+    matches
+}
+
+fn search_file(root: &Path, file: &str, matcher: &Matcher) -> Vec<Match> {
+    let Ok(bytes) = fs::read(root.join(file)) else {
+        return Vec::new();
+    };
+    if bytes.contains(&0) {
+        return Vec::new();
+    }
+    let Ok(contents) = String::from_utf8(bytes) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let found = matcher.find(text, 0)?;
+            Some(Match {
+                path: file.to_string(),
+                line,
+                column: text.get(..found.start)?.graphemes(true).count(),
+                text: text.trim().to_string(),
+            })
+        })
+        .take(MAX_MATCHES)
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct Quickfix {
+    query: String,
+    matches: Vec<Match>,
+    selected: usize,
+}
+
+impl Quickfix {
+    pub fn new(query: String, matches: Vec<Match>) -> Self {
+        Self {
+            query,
+            matches,
+            selected: 0,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn matches(&self) -> &[Match] {
+        &self.matches
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selection(&self) -> Option<&Match> {
+        self.matches.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected.saturating_add(1) < self.matches.len() {
+            self.selected = self.selected.saturating_add(1);
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
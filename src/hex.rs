@@ -0,0 +1,167 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+};
+
+use crate::{encoding::Encoding, Position};
+
+pub const BYTES_PER_LINE: usize = 16;
+const OFFSET_WIDTH: usize = 10;
+const SNIFF_LENGTH: usize = 8192;
+
+pub fn is_binary(file_name: &str) -> bool {
+    let mut bytes = Vec::with_capacity(SNIFF_LENGTH);
+    File::open(file_name)
+        .and_then(|file| file.take(SNIFF_LENGTH as u64).read_to_end(&mut bytes))
+        .map(|_| match Encoding::from_bom(&bytes) {
+            Some(Encoding::Utf16Le | Encoding::Utf16Be) => false,
+            _ => bytes.contains(&0),
+        })
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Default)]
+pub struct HexView {
+    file_name: String,
+    bytes: Vec<u8>,
+    cursor: usize,
+    offset: usize,
+    low_nibble: bool,
+    ascii: bool,
+    dirty: bool,
+}
+
+impl HexView {
+    pub fn open(file_name: &str) -> io::Result<Self> {
+        Ok(Self {
+            file_name: file_name.to_string(),
+            bytes: fs::read(file_name)?,
+            ..Self::default()
+        })
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        fs::write(&self.file_name, &self.bytes)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn is_ascii(&self) -> bool {
+        self.ascii
+    }
+
+    pub fn toggle_column(&mut self) {
+        self.ascii = !self.ascii;
+        self.low_nibble = false;
+    }
+
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.bytes.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+        self.low_nibble = false;
+    }
+
+    pub fn move_to(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.bytes.len().saturating_sub(1));
+        self.low_nibble = false;
+    }
+
+    pub fn line_start(&self) -> usize {
+        self.cursor.saturating_sub(self.cursor % BYTES_PER_LINE)
+    }
+
+    pub fn input(&mut self, c: char) -> bool {
+        let Some(byte) = self.bytes.get_mut(self.cursor) else {
+            return false;
+        };
+        if self.ascii {
+            let Ok(value) = u8::try_from(u32::from(c)) else {
+                return false;
+            };
+            if !value.is_ascii() || value.is_ascii_control() {
+                return false;
+            }
+            *byte = value;
+        } else {
+            let Some(digit) = c.to_digit(16).and_then(|digit| u8::try_from(digit).ok()) else {
+                return false;
+            };
+            *byte = if self.low_nibble {
+                (*byte & 0xf0) | digit
+            } else {
+                (*byte & 0x0f) | (digit << 4)
+            };
+            self.low_nibble = !self.low_nibble;
+            if self.low_nibble {
+                self.dirty = true;
+                return true;
+            }
+        }
+        self.dirty = true;
+        self.move_by(1);
+        true
+    }
+
+    pub fn scroll(&mut self, height: usize) {
+        let line = self.cursor / BYTES_PER_LINE;
+        if line < self.offset {
+            self.offset = line;
+        } else if line >= self.offset.saturating_add(height) {
+            self.offset = line.saturating_add(1).saturating_sub(height);
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn line(&self, line: usize) -> Option<&[u8]> {
+        let start = line.checked_mul(BYTES_PER_LINE)?;
+        if start >= self.bytes.len() && start > 0 {
+            return None;
+        }
+        self.bytes
+            .get(start..start.saturating_add(BYTES_PER_LINE).min(self.bytes.len()))
+    }
+
+    pub fn hex_column(index: usize) -> usize {
+        OFFSET_WIDTH
+            .saturating_add(index.saturating_mul(3))
+            .saturating_add(usize::from(index >= BYTES_PER_LINE / 2))
+    }
+
+    pub fn ascii_column(index: usize) -> usize {
+        Self::hex_column(BYTES_PER_LINE)
+            .saturating_add(1)
+            .saturating_add(index)
+    }
+
+    pub fn screen_cursor(&self) -> Position {
+        let index = self.cursor % BYTES_PER_LINE;
+        let x = if self.ascii {
+            Self::ascii_column(index)
+        } else {
+            Self::hex_column(index).saturating_add(usize::from(self.low_nibble))
+        };
+        Position {
+            x,
+            y: (self.cursor / BYTES_PER_LINE).saturating_sub(self.offset),
+        }
+    }
+}
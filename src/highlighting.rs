@@ -1,5 +1,7 @@
 use crossterm::style;
 
+use crate::Theme;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Type {
     None,
@@ -11,24 +13,88 @@ pub enum Type {
     MultilineComment,
     PrimaryKeywords,
     SecondaryKeywords,
+    Decorator,
+    Preprocessor,
+    Regex,
+    Heading,
+    Emphasis,
+    Code,
+    Link,
+    Bullet,
+    Key,
+    Todo,
+    DocComment,
+    Lifetime,
+    Attribute,
+    Escape,
+    Variable,
+    Inserted,
+    Deleted,
+    Hunk,
+    Error,
+    Warning,
+    Info,
+    Timestamp,
+    Function,
+    Typename,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum State {
+    #[default]
+    Normal,
+    MultilineComment(usize),
+    MultilineString(String),
+    RawString(String),
+    Fence(String),
+    Heredoc(String),
 }
 
 impl Type {
-    pub fn to_color(self) -> style::Color {
+    pub fn from_semantic(kind: &str) -> Option<Self> {
+        match kind {
+            "type" | "class" | "enum" | "interface" | "struct" | "typeParameter"
+            | "builtinType" => Some(Self::Typename),
+            "function" | "method" | "macro" => Some(Self::Function),
+            _ => None,
+        }
+    }
+
+    pub fn to_color(self, theme: &Theme) -> style::Color {
+        let syntax = &theme.syntax;
         match self {
-            Self::Number => style::Color::Magenta,
-            Self::Match => style::Color::Blue,
-            Self::String => style::Color::Green,
-            Self::Character => style::Color::Green,
-            Self::Comment => style::Color::Grey,
-            Self::MultilineComment => style::Color::Grey,
-            Self::PrimaryKeywords => style::Color::Yellow,
-            Self::SecondaryKeywords => style::Color::Cyan,
-            _ => style::Color::Rgb {
-                r: 255,
-                g: 255,
-                b: 255,
-            },
+            Self::Number => syntax.number,
+            Self::Match => syntax.search_match,
+            Self::String => syntax.string,
+            Self::Character => syntax.character,
+            Self::Comment | Self::MultilineComment => syntax.comment,
+            Self::PrimaryKeywords => syntax.primary_keyword,
+            Self::SecondaryKeywords => syntax.secondary_keyword,
+            Self::Decorator => syntax.decorator,
+            Self::Preprocessor => syntax.preprocessor,
+            Self::Regex => syntax.regex,
+            Self::Heading => syntax.heading,
+            Self::Emphasis => syntax.emphasis,
+            Self::Code => syntax.code,
+            Self::Link => syntax.link,
+            Self::Bullet => syntax.bullet,
+            Self::Key => syntax.key,
+            Self::Todo => syntax.todo,
+            Self::DocComment => syntax.doc_comment,
+            Self::Lifetime => syntax.lifetime,
+            Self::Attribute => syntax.attribute,
+            Self::Escape => syntax.escape,
+            Self::Variable => syntax.variable,
+            Self::Inserted => theme.ui.diff_added,
+            Self::Deleted => theme.ui.diff_removed,
+            Self::Hunk => theme.ui.diff_modified,
+            Self::Error => theme.ui.diagnostic_error,
+            Self::Warning => theme.ui.diagnostic_warning,
+            Self::Info => theme.ui.diagnostic_info,
+            Self::Timestamp => syntax.comment,
+            Self::Function => syntax.function,
+            Self::Typename => syntax.typename,
+            Self::None => theme.ui.text,
         }
     }
 }
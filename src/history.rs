@@ -0,0 +1,83 @@
+use std::{env, fs, path::PathBuf};
+
+use crate::{Config, Position};
+
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: String,
+    pub position: Position,
+}
+
+fn path() -> Option<PathBuf> {
+    Some(Config::state_dir()?.join("history"))
+}
+
+pub fn absolute(file_name: &str) -> String {
+    env::current_dir()
+        .map(|dir| dir.join(file_name))
+        .and_then(fs::canonicalize)
+        .map_or_else(
+            |_| file_name.to_string(),
+            |path| path.to_string_lossy().into_owned(),
+        )
+}
+
+pub fn load() -> Vec<Entry> {
+    let Some(contents) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let y = fields.next()?.parse().ok()?;
+            let x = fields.next()?.parse().ok()?;
+            Some(Entry {
+                path: fields.next()?.to_string(),
+                position: Position { x, y },
+            })
+        })
+        .collect()
+}
+
+pub fn find(file_name: &str) -> Option<Position> {
+    let path = absolute(file_name);
+    load()
+        .into_iter()
+        .find(|entry| entry.path == path)
+        .map(|entry| entry.position)
+}
+
+pub fn record(files: &[(String, Position)]) {
+    let Some(path) = path() else {
+        return;
+    };
+    let mut entries = load();
+    for (file_name, position) in files.iter().rev() {
+        let file_name = absolute(file_name);
+        entries.retain(|entry| entry.path != file_name);
+        entries.insert(
+            0,
+            Entry {
+                path: file_name,
+                position: position.clone(),
+            },
+        );
+    }
+    entries.truncate(MAX_ENTRIES);
+    let contents: String = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}\t{}\t{}\n",
+                entry.position.y, entry.position.x, entry.path
+            )
+        })
+        .collect();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, contents);
+}
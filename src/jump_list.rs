@@ -0,0 +1,44 @@
+use crate::Position;
+
+const MAX_JUMPS: usize = 100;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Jump {
+    pub file: Option<String>,
+    pub position: Position,
+}
+
+#[derive(Debug, Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    index: usize,
+}
+
+impl JumpList {
+    pub fn record(&mut self, jump: Jump) {
+        self.jumps.truncate(self.index);
+        self.jumps
+            .retain(|other| other.file != jump.file || other.position.y != jump.position.y);
+        self.jumps.push(jump);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.index = self.jumps.len();
+    }
+
+    pub fn back(&mut self, current: Jump) -> Option<Jump> {
+        if self.index == self.jumps.len() {
+            self.record(current);
+            self.index = self.jumps.len().saturating_sub(1);
+        }
+        self.index = self.index.checked_sub(1)?;
+        self.jumps.get(self.index).cloned()
+    }
+
+    pub fn forward(&mut self) -> Option<Jump> {
+        let index = self.index.saturating_add(1);
+        let jump = self.jumps.get(index).cloned()?;
+        self.index = index;
+        Some(jump)
+    }
+}
@@ -0,0 +1,618 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Key {
+    pub modifiers: KeyModifiers,
+    pub code: KeyCode,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        let mut code = event.code;
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                code = KeyCode::Char(c.to_ascii_uppercase());
+            }
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { modifiers, code }
+    }
+}
+
+impl Key {
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            if let Some(stripped) = rest.strip_prefix("Ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("Alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("Shift-") {
+                modifiers |= KeyModifiers::SHIFT;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+        let code = match rest {
+            "Space" => KeyCode::Char(' '),
+            "Enter" => KeyCode::Enter,
+            "Tab" => KeyCode::Tab,
+            "Esc" => KeyCode::Esc,
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+                        KeyCode::Char(c.to_ascii_lowercase())
+                    }
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some('F'), Some(_)) => KeyCode::F(rest.get(1..)?.parse().ok()?),
+                    _ => return None,
+                }
+            }
+        };
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                modifiers.remove(KeyModifiers::SHIFT);
+                return Some(Self {
+                    modifiers,
+                    code: KeyCode::Char(c.to_ascii_uppercase()),
+                });
+            }
+        }
+        Some(Self { modifiers, code })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.is_empty() => write!(f, "{}", c),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Quit,
+    Suspend,
+    Undo,
+    Redo,
+    UndoEarlier,
+    UndoLater,
+    UndoTree,
+    #[serde(skip)]
+    Script(usize),
+    Help,
+    Messages,
+    Save,
+    Find,
+    FindNext,
+    FindPrevious,
+    Replace,
+    Open,
+    FindFile,
+    FindInFiles,
+    ReplaceInFiles,
+    QuickfixList,
+    TodoList,
+    BrowseFiles,
+    RecentFiles,
+    ReopenLast,
+    SwitchBuffer,
+    SplitHorizontal,
+    SplitVertical,
+    CloseWindow,
+    NextWindow,
+    ToggleSelection,
+    ClearSelection,
+    Copy,
+    Cut,
+    Paste,
+    ToggleLineNumbers,
+    ToggleSoftWrap,
+    ToggleWhitespace,
+    ToggleIndentGuides,
+    ToggleLineEnding,
+    ToggleReadOnly,
+    SetFileType,
+    ToggleComment,
+    Uppercase,
+    Lowercase,
+    Titlecase,
+    FilterShell,
+    Format,
+    ToggleSpellCheck,
+    SpellSuggest,
+    AddToDictionary,
+    ToggleMacroRecording,
+    ReplayMacro,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    CenterView,
+    LineStart,
+    LineEnd,
+    WordLeft,
+    WordRight,
+    MatchingBracket,
+    JumpBack,
+    JumpForward,
+    NextHunk,
+    PreviousHunk,
+    NextDiagnostic,
+    PreviousDiagnostic,
+    Complete,
+    CompleteWord,
+    GotoDefinition,
+    FindReferences,
+    Hover,
+    KillLine,
+    DeleteLine,
+    JoinLines,
+    MoveLineUp,
+    MoveLineDown,
+    DuplicateLine,
+    OpenLineBelow,
+    OpenLineAbove,
+    InsertNewline,
+    InsertTab,
+    DeleteForward,
+    DeleteBackward,
+    DeleteWordForward,
+    DeleteWordBackward,
+}
+
+impl Command {
+    pub fn is_edit(self) -> bool {
+        matches!(
+            self,
+            Self::Save
+                | Self::Undo
+                | Self::Redo
+                | Self::UndoEarlier
+                | Self::UndoLater
+                | Self::Replace
+                | Self::Cut
+                | Self::Paste
+                | Self::ToggleLineEnding
+                | Self::ToggleComment
+                | Self::Uppercase
+                | Self::Lowercase
+                | Self::Titlecase
+                | Self::FilterShell
+                | Self::Format
+                | Self::Complete
+                | Self::CompleteWord
+                | Self::SpellSuggest
+                | Self::KillLine
+                | Self::DeleteLine
+                | Self::JoinLines
+                | Self::MoveLineUp
+                | Self::MoveLineDown
+                | Self::DuplicateLine
+                | Self::OpenLineBelow
+                | Self::OpenLineAbove
+                | Self::InsertNewline
+                | Self::InsertTab
+                | Self::DeleteForward
+                | Self::DeleteBackward
+                | Self::DeleteWordForward
+                | Self::DeleteWordBackward
+        )
+    }
+
+    pub fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Self::Find
+                | Self::FindNext
+                | Self::FindPrevious
+                | Self::Open
+                | Self::FindFile
+                | Self::FindInFiles
+                | Self::QuickfixList
+                | Self::TodoList
+                | Self::BrowseFiles
+                | Self::RecentFiles
+                | Self::ReopenLast
+                | Self::SwitchBuffer
+                | Self::MatchingBracket
+                | Self::NextHunk
+                | Self::PreviousHunk
+                | Self::NextDiagnostic
+                | Self::PreviousDiagnostic
+        )
+    }
+
+    pub fn is_movement(self) -> bool {
+        matches!(
+            self,
+            Self::MoveUp
+                | Self::MoveDown
+                | Self::MoveLeft
+                | Self::MoveRight
+                | Self::PageUp
+                | Self::PageDown
+                | Self::HalfPageUp
+                | Self::HalfPageDown
+                | Self::LineStart
+                | Self::LineEnd
+                | Self::WordLeft
+                | Self::WordRight
+                | Self::MatchingBracket
+                | Self::FindNext
+                | Self::FindPrevious
+                | Self::NextHunk
+                | Self::PreviousHunk
+                | Self::NextDiagnostic
+                | Self::PreviousDiagnostic
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    Default,
+    Emacs,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Lookup {
+    Command(Command),
+    Prefix,
+    Unbound,
+}
+
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Command)>,
+}
+
+fn key(code: KeyCode) -> Key {
+    Key {
+        modifiers: KeyModifiers::NONE,
+        code,
+    }
+}
+
+fn char(c: char) -> Key {
+    key(KeyCode::Char(c))
+}
+
+fn ctrl(c: char) -> Key {
+    Key {
+        modifiers: KeyModifiers::CONTROL,
+        code: KeyCode::Char(c),
+    }
+}
+
+fn alt(c: char) -> Key {
+    Key {
+        modifiers: KeyModifiers::ALT,
+        code: KeyCode::Char(c),
+    }
+}
+
+impl Keymap {
+    pub fn new(preset: KeymapPreset) -> Self {
+        let mut bindings = match preset {
+            KeymapPreset::Default => vec![
+                (vec![ctrl('q')], Command::Quit),
+                (vec![ctrl('s')], Command::Save),
+                (vec![ctrl('f')], Command::Find),
+                (vec![ctrl('r')], Command::Replace),
+                (vec![ctrl('o')], Command::Open),
+                (vec![ctrl('p')], Command::FindFile),
+                (vec![alt('o')], Command::BrowseFiles),
+                (vec![alt('h')], Command::RecentFiles),
+                (vec![alt('l')], Command::ReopenLast),
+                (vec![ctrl('b')], Command::SwitchBuffer),
+                (vec![ctrl('w'), char('s')], Command::SplitHorizontal),
+                (vec![ctrl('w'), char('v')], Command::SplitVertical),
+                (vec![ctrl('w'), char('c')], Command::CloseWindow),
+                (vec![ctrl('w'), char('w')], Command::NextWindow),
+                (vec![ctrl(' ')], Command::ToggleSelection),
+                (vec![ctrl('c')], Command::Copy),
+                (vec![ctrl('x')], Command::Cut),
+                (vec![ctrl('v')], Command::Paste),
+                (vec![ctrl('z')], Command::Undo),
+                (vec![alt('x')], Command::Suspend),
+                (vec![ctrl('y')], Command::Redo),
+                (vec![alt('q')], Command::ToggleMacroRecording),
+                (vec![alt('@')], Command::ReplayMacro),
+                (vec![alt('r')], Command::ToggleReadOnly),
+                (vec![ctrl('n')], Command::CompleteWord),
+                (vec![alt('/')], Command::Complete),
+                (vec![ctrl('d')], Command::HalfPageDown),
+                (vec![ctrl('u')], Command::HalfPageUp),
+                (vec![ctrl('k')], Command::DeleteLine),
+                (vec![alt('u')], Command::Uppercase),
+                (vec![alt('d')], Command::Lowercase),
+                (vec![alt('c')], Command::Titlecase),
+            ],
+            KeymapPreset::Emacs => vec![
+                (vec![ctrl('x'), ctrl('c')], Command::Quit),
+                (vec![ctrl('x'), ctrl('s')], Command::Save),
+                (vec![ctrl('s')], Command::Find),
+                (vec![alt('%')], Command::Replace),
+                (vec![ctrl('x'), ctrl('f')], Command::Open),
+                (vec![ctrl('x'), char('p')], Command::FindFile),
+                (vec![ctrl('x'), char('d')], Command::BrowseFiles),
+                (vec![ctrl('x'), ctrl('r')], Command::RecentFiles),
+                (vec![ctrl('x'), char('l')], Command::ReopenLast),
+                (vec![ctrl('x'), char('b')], Command::SwitchBuffer),
+                (vec![ctrl('x'), char('2')], Command::SplitHorizontal),
+                (vec![ctrl('x'), char('3')], Command::SplitVertical),
+                (vec![ctrl('x'), char('0')], Command::CloseWindow),
+                (vec![ctrl('x'), char('o')], Command::NextWindow),
+                (vec![ctrl(' ')], Command::ToggleSelection),
+                (vec![ctrl('g')], Command::ClearSelection),
+                (vec![alt('w')], Command::Copy),
+                (vec![ctrl('w')], Command::Cut),
+                (vec![ctrl('y')], Command::Paste),
+                (vec![ctrl('x'), char('u')], Command::Undo),
+                (vec![ctrl('x'), ctrl('z')], Command::Suspend),
+                (vec![alt('_')], Command::Redo),
+                (vec![ctrl('a')], Command::LineStart),
+                (vec![ctrl('e')], Command::LineEnd),
+                (vec![ctrl('f')], Command::MoveRight),
+                (vec![ctrl('b')], Command::MoveLeft),
+                (vec![ctrl('n')], Command::MoveDown),
+                (vec![ctrl('p')], Command::MoveUp),
+                (vec![alt('f')], Command::WordRight),
+                (vec![alt('b')], Command::WordLeft),
+                (vec![ctrl('v')], Command::PageDown),
+                (vec![alt('v')], Command::PageUp),
+                (vec![ctrl('l')], Command::CenterView),
+                (vec![ctrl('k')], Command::KillLine),
+                (
+                    vec![Key {
+                        modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+                        code: KeyCode::Backspace,
+                    }],
+                    Command::DeleteLine,
+                ),
+                (vec![ctrl('d')], Command::DeleteForward),
+                (vec![alt('d')], Command::DeleteWordForward),
+                (vec![alt('u')], Command::Uppercase),
+                (vec![alt('l')], Command::Lowercase),
+                (vec![alt('c')], Command::Titlecase),
+                (
+                    vec![Key {
+                        modifiers: KeyModifiers::ALT,
+                        code: KeyCode::Backspace,
+                    }],
+                    Command::DeleteWordBackward,
+                ),
+                (vec![ctrl('x'), char('(')], Command::ToggleMacroRecording),
+                (vec![ctrl('x'), char(')')], Command::ToggleMacroRecording),
+                (vec![ctrl('x'), char('e')], Command::ReplayMacro),
+                (vec![ctrl('x'), ctrl('q')], Command::ToggleReadOnly),
+                (vec![alt('/')], Command::CompleteWord),
+                (
+                    vec![Key {
+                        modifiers: KeyModifiers::ALT,
+                        code: KeyCode::Tab,
+                    }],
+                    Command::Complete,
+                ),
+            ],
+        };
+        bindings.extend(vec![
+            (vec![ctrl('l')], Command::ToggleLineNumbers),
+            (vec![ctrl('z')], Command::Suspend),
+            (vec![key(KeyCode::F(1))], Command::Help),
+            (vec![alt('M')], Command::Messages),
+            (vec![alt('y')], Command::SetFileType),
+            (vec![alt('<')], Command::UndoEarlier),
+            (vec![alt('>')], Command::UndoLater),
+            (vec![alt('U')], Command::UndoTree),
+            (vec![alt('z')], Command::ToggleSoftWrap),
+            (vec![alt('Z')], Command::CenterView),
+            (vec![alt('W')], Command::ToggleWhitespace),
+            (vec![alt('i')], Command::ToggleIndentGuides),
+            (vec![alt('e')], Command::ToggleLineEnding),
+            (vec![alt('m')], Command::MatchingBracket),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::ALT,
+                    code: KeyCode::Left,
+                }],
+                Command::JumpBack,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::ALT,
+                    code: KeyCode::Right,
+                }],
+                Command::JumpForward,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::ALT,
+                    code: KeyCode::Up,
+                }],
+                Command::MoveLineUp,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::ALT,
+                    code: KeyCode::Down,
+                }],
+                Command::MoveLineDown,
+            ),
+            (vec![ctrl('D')], Command::DuplicateLine),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Enter,
+                }],
+                Command::OpenLineBelow,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+                    code: KeyCode::Enter,
+                }],
+                Command::OpenLineAbove,
+            ),
+            (vec![alt('j')], Command::JoinLines),
+            (vec![alt('|')], Command::FilterShell),
+            (vec![alt('=')], Command::Format),
+            (vec![alt('F')], Command::FindInFiles),
+            (vec![alt('R')], Command::ReplaceInFiles),
+            (vec![alt('Q')], Command::QuickfixList),
+            (vec![alt('T')], Command::TodoList),
+            (vec![key(KeyCode::F(3))], Command::FindNext),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::SHIFT,
+                    code: KeyCode::F(3),
+                }],
+                Command::FindPrevious,
+            ),
+            (vec![alt('g')], Command::FindNext),
+            (vec![alt('G')], Command::FindPrevious),
+            (vec![alt('n')], Command::NextHunk),
+            (vec![alt('p')], Command::PreviousHunk),
+            (vec![key(KeyCode::F(8))], Command::NextDiagnostic),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::SHIFT,
+                    code: KeyCode::F(8),
+                }],
+                Command::PreviousDiagnostic,
+            ),
+            (vec![alt('.')], Command::GotoDefinition),
+            (vec![alt(',')], Command::FindReferences),
+            (vec![alt('k')], Command::Hover),
+            (vec![alt('s')], Command::ToggleSpellCheck),
+            (vec![alt('$')], Command::SpellSuggest),
+            (vec![alt('a')], Command::AddToDictionary),
+            (vec![ctrl('/')], Command::ToggleComment),
+            (vec![ctrl('7')], Command::ToggleComment),
+            (vec![key(KeyCode::Esc)], Command::ClearSelection),
+            (vec![key(KeyCode::Up)], Command::MoveUp),
+            (vec![key(KeyCode::Down)], Command::MoveDown),
+            (vec![key(KeyCode::Left)], Command::MoveLeft),
+            (vec![key(KeyCode::Right)], Command::MoveRight),
+            (vec![key(KeyCode::PageUp)], Command::PageUp),
+            (vec![key(KeyCode::PageDown)], Command::PageDown),
+            (vec![key(KeyCode::Home)], Command::LineStart),
+            (vec![key(KeyCode::End)], Command::LineEnd),
+            (vec![key(KeyCode::Enter)], Command::InsertNewline),
+            (vec![key(KeyCode::Tab)], Command::InsertTab),
+            (vec![key(KeyCode::Delete)], Command::DeleteForward),
+            (vec![key(KeyCode::Backspace)], Command::DeleteBackward),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Left,
+                }],
+                Command::WordLeft,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Right,
+                }],
+                Command::WordRight,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Backspace,
+                }],
+                Command::DeleteWordBackward,
+            ),
+            (
+                vec![Key {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Delete,
+                }],
+                Command::DeleteWordForward,
+            ),
+        ]);
+        Self { bindings }
+    }
+
+    pub fn bind(&mut self, keys: Vec<Key>, command: Command) {
+        self.bindings.insert(0, (keys, command));
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut result = Lookup::Unbound;
+        for (sequence, command) in &self.bindings {
+            if sequence == keys {
+                return Lookup::Command(*command);
+            }
+            if sequence.starts_with(keys) {
+                result = Lookup::Prefix;
+            }
+        }
+        result
+    }
+
+    pub fn bindings(&self) -> Vec<(&[Key], Command)> {
+        let mut bindings: Vec<(&[Key], Command)> = Vec::new();
+        for (sequence, command) in &self.bindings {
+            if bindings.iter().all(|(keys, _)| keys != sequence) {
+                bindings.push((sequence, *command));
+            }
+        }
+        bindings
+    }
+
+    pub fn describe(&self, command: Command) -> String {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == command)
+            .map(|(sequence, _)| describe_keys(sequence))
+            .unwrap_or_default()
+    }
+}
+
+pub fn describe_keys(keys: &[Key]) -> String {
+    keys.iter()
+        .map(Key::to_string)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
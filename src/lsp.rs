@@ -0,0 +1,652 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::json::{self, Value};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    fn from_number(number: usize) -> Self {
+        match number {
+            1 => Self::Error,
+            2 => Self::Warning,
+            3 => Self::Information,
+            _ => Self::Hint,
+        }
+    }
+
+    pub fn sign(self) -> &'static str {
+        match self {
+            Self::Error => "E",
+            Self::Warning => "W",
+            Self::Information => "I",
+            Self::Hint => "H",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub character: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SemanticToken {
+    pub line: usize,
+    pub start: usize,
+    pub length: usize,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: String,
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug)]
+pub enum Response {
+    Diagnostics(String, Vec<Diagnostic>),
+    Completion(Vec<String>),
+    Definition(String, usize, usize),
+    References(Vec<Location>),
+    Hover(Vec<String>),
+    SemanticTokens(String, Vec<SemanticToken>),
+}
+
+#[derive(Debug)]
+enum Pending {
+    Initialize,
+    Completion,
+    Definition,
+    References,
+    Hover,
+    SemanticTokens(String, usize),
+}
+
+const TOKEN_TYPES: [&str; 15] = [
+    "namespace",
+    "type",
+    "class",
+    "enum",
+    "interface",
+    "struct",
+    "typeParameter",
+    "parameter",
+    "variable",
+    "property",
+    "enumMember",
+    "function",
+    "method",
+    "macro",
+    "keyword",
+];
+
+#[derive(Debug)]
+pub struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    receiver: Receiver<Value>,
+    next_id: usize,
+    pending: HashMap<usize, Pending>,
+    initialized: bool,
+    queue: Vec<Value>,
+    versions: HashMap<String, usize>,
+    token_types: Vec<String>,
+}
+
+impl Client {
+    pub fn start(command: &str, root: &Path) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "no stdio"));
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Some(message) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+        let mut client = Self {
+            child,
+            stdin,
+            receiver,
+            next_id: 0,
+            pending: HashMap::new(),
+            initialized: false,
+            queue: Vec::new(),
+            versions: HashMap::new(),
+            token_types: Vec::new(),
+        };
+        let root_uri = path_to_uri(&root.to_string_lossy());
+        client.request(
+            "initialize",
+            Value::object(vec![
+                (
+                    "processId",
+                    Value::from(usize::try_from(std::process::id()).unwrap_or_default()),
+                ),
+                ("rootUri", Value::from(root_uri)),
+                (
+                    "capabilities",
+                    Value::object(vec![(
+                        "textDocument",
+                        Value::object(vec![
+                            ("publishDiagnostics", Value::object(Vec::new())),
+                            ("completion", Value::object(Vec::new())),
+                            ("definition", Value::object(Vec::new())),
+                            ("references", Value::object(Vec::new())),
+                            (
+                                "hover",
+                                Value::object(vec![(
+                                    "contentFormat",
+                                    Value::Array(vec![
+                                        Value::from("plaintext"),
+                                        Value::from("markdown"),
+                                    ]),
+                                )]),
+                            ),
+                            (
+                                "semanticTokens",
+                                Value::object(vec![
+                                    ("requests", Value::object(vec![("full", Value::from(true))])),
+                                    (
+                                        "tokenTypes",
+                                        Value::Array(
+                                            TOKEN_TYPES
+                                                .iter()
+                                                .map(|kind| Value::from(*kind))
+                                                .collect(),
+                                        ),
+                                    ),
+                                    ("tokenModifiers", Value::Array(Vec::new())),
+                                    ("formats", Value::Array(vec![Value::from("relative")])),
+                                ]),
+                            ),
+                        ]),
+                    )]),
+                ),
+            ]),
+            Pending::Initialize,
+        )?;
+        Ok(client)
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        let is_initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+        if !self.initialized && !is_initialize {
+            self.queue.push(message);
+            return Ok(());
+        }
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stdin.flush()
+    }
+
+    fn request(&mut self, method: &str, params: Value, pending: Pending) -> io::Result<()> {
+        self.next_id = self.next_id.saturating_add(1);
+        self.pending.insert(self.next_id, pending);
+        self.send(Value::object(vec![
+            ("jsonrpc", Value::from("2.0")),
+            ("id", Value::from(self.next_id)),
+            ("method", Value::from(method)),
+            ("params", params),
+        ]))
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(Value::object(vec![
+            ("jsonrpc", Value::from("2.0")),
+            ("method", Value::from(method)),
+            ("params", params),
+        ]))
+    }
+
+    pub fn sync(
+        &mut self,
+        path: &str,
+        language_id: &str,
+        version: usize,
+        text: impl FnOnce() -> String,
+    ) -> io::Result<()> {
+        let sent = self.versions.insert(path.to_string(), version);
+        if sent == Some(version) {
+            return Ok(());
+        }
+        let uri = path_to_uri(path);
+        let text = text();
+        let result = match sent {
+            Some(_) => self.notify(
+                "textDocument/didChange",
+                Value::object(vec![
+                    (
+                        "textDocument",
+                        Value::object(vec![
+                            ("uri", Value::from(uri)),
+                            ("version", Value::from(version)),
+                        ]),
+                    ),
+                    (
+                        "contentChanges",
+                        Value::Array(vec![Value::object(vec![("text", Value::from(text))])]),
+                    ),
+                ]),
+            ),
+            None => self.notify(
+                "textDocument/didOpen",
+                Value::object(vec![(
+                    "textDocument",
+                    Value::object(vec![
+                        ("uri", Value::from(uri)),
+                        ("languageId", Value::from(language_id)),
+                        ("version", Value::from(version)),
+                        ("text", Value::from(text)),
+                    ]),
+                )]),
+            ),
+        };
+        result?;
+        if self.initialized {
+            self.semantic_tokens(path, version)?;
+        }
+        Ok(())
+    }
+
+    fn semantic_tokens(&mut self, path: &str, version: usize) -> io::Result<()> {
+        if self.token_types.is_empty() {
+            return Ok(());
+        }
+        self.request(
+            "textDocument/semanticTokens/full",
+            Value::object(vec![(
+                "textDocument",
+                Value::object(vec![("uri", Value::from(path_to_uri(path)))]),
+            )]),
+            Pending::SemanticTokens(path.to_string(), version),
+        )
+    }
+
+    pub fn did_save(&mut self, path: &str) -> io::Result<()> {
+        self.notify(
+            "textDocument/didSave",
+            Value::object(vec![(
+                "textDocument",
+                Value::object(vec![("uri", Value::from(path_to_uri(path)))]),
+            )]),
+        )
+    }
+
+    pub fn completion(&mut self, path: &str, line: usize, character: usize) -> io::Result<()> {
+        self.request(
+            "textDocument/completion",
+            text_document_position(path, line, character),
+            Pending::Completion,
+        )
+    }
+
+    pub fn definition(&mut self, path: &str, line: usize, character: usize) -> io::Result<()> {
+        self.request(
+            "textDocument/definition",
+            text_document_position(path, line, character),
+            Pending::Definition,
+        )
+    }
+
+    pub fn references(&mut self, path: &str, line: usize, character: usize) -> io::Result<()> {
+        let mut params = text_document_position(path, line, character);
+        if let Value::Object(pairs) = &mut params {
+            pairs.push((
+                "context".to_string(),
+                Value::object(vec![("includeDeclaration", Value::from(true))]),
+            ));
+        }
+        self.request("textDocument/references", params, Pending::References)
+    }
+
+    pub fn hover(&mut self, path: &str, line: usize, character: usize) -> io::Result<()> {
+        self.request(
+            "textDocument/hover",
+            text_document_position(path, line, character),
+            Pending::Hover,
+        )
+    }
+
+    pub fn poll(&mut self) -> Vec<Response> {
+        let mut responses = Vec::new();
+        while let Ok(message) = self.receiver.try_recv() {
+            let id = message.get("id").and_then(Value::as_usize);
+            let method = message.get("method").and_then(Value::as_str);
+            match (id, method) {
+                (Some(id), Some(_)) => {
+                    let _ = self.send(Value::object(vec![
+                        ("jsonrpc", Value::from("2.0")),
+                        ("id", Value::from(id)),
+                        ("result", Value::Null),
+                    ]));
+                }
+                (None, Some("textDocument/publishDiagnostics")) => {
+                    if let Some(response) = message.get("params").and_then(parse_diagnostics) {
+                        responses.push(response);
+                    }
+                }
+                (Some(id), None) => {
+                    let result = message.get("result").unwrap_or(&Value::Null);
+                    match self.pending.remove(&id) {
+                        Some(Pending::Initialize) => self.initialize(result),
+                        Some(Pending::Completion) => {
+                            responses.push(Response::Completion(parse_completion(result)));
+                        }
+                        Some(Pending::Definition) => {
+                            responses.extend(parse_definition(result));
+                        }
+                        Some(Pending::References) => {
+                            responses.push(Response::References(parse_references(result)));
+                        }
+                        Some(Pending::Hover) => {
+                            responses.push(Response::Hover(parse_hover(result)));
+                        }
+                        Some(Pending::SemanticTokens(path, version))
+                            if self.versions.get(&path) == Some(&version) =>
+                        {
+                            let tokens = parse_semantic_tokens(result, &self.token_types);
+                            responses.push(Response::SemanticTokens(path, tokens));
+                        }
+                        Some(Pending::SemanticTokens(..)) | None => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+        responses
+    }
+
+    fn initialize(&mut self, result: &Value) {
+        self.initialized = true;
+        self.token_types = result
+            .get("capabilities")
+            .and_then(|capabilities| capabilities.get("semanticTokensProvider"))
+            .and_then(|provider| provider.get("legend"))
+            .and_then(|legend| legend.get("tokenTypes"))
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .map(|kind| kind.as_str().unwrap_or_default().to_string())
+            .collect();
+        let _ = self.notify("initialized", Value::object(Vec::new()));
+        for message in std::mem::take(&mut self.queue) {
+            let _ = self.send(message);
+        }
+        let versions: Vec<(String, usize)> = self
+            .versions
+            .iter()
+            .map(|(path, version)| (path.clone(), *version))
+            .collect();
+        for (path, version) in versions {
+            let _ = self.semantic_tokens(&path, version);
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    json::parse(&String::from_utf8_lossy(&body))
+}
+
+fn text_document_position(path: &str, line: usize, character: usize) -> Value {
+    Value::object(vec![
+        (
+            "textDocument",
+            Value::object(vec![("uri", Value::from(path_to_uri(path)))]),
+        ),
+        (
+            "position",
+            Value::object(vec![
+                ("line", Value::from(line)),
+                ("character", Value::from(character)),
+            ]),
+        ),
+    ])
+}
+
+fn parse_diagnostics(params: &Value) -> Option<Response> {
+    let path = uri_to_path(params.get("uri")?.as_str()?)?;
+    let diagnostics = params
+        .get("diagnostics")?
+        .as_array()?
+        .iter()
+        .filter_map(|diagnostic| {
+            let start = diagnostic.get("range")?.get("start")?;
+            Some(Diagnostic {
+                line: start.get("line")?.as_usize()?,
+                character: start.get("character")?.as_usize()?,
+                severity: Severity::from_number(
+                    diagnostic
+                        .get("severity")
+                        .and_then(Value::as_usize)
+                        .unwrap_or(1),
+                ),
+                message: diagnostic.get("message")?.as_str()?.to_string(),
+            })
+        })
+        .collect();
+    Some(Response::Diagnostics(path, diagnostics))
+}
+
+fn parse_completion(result: &Value) -> Vec<String> {
+    let items = result
+        .as_array()
+        .or_else(|| result.get("items").and_then(Value::as_array))
+        .unwrap_or_default();
+    let mut words: Vec<String> = items
+        .iter()
+        .filter_map(|item| {
+            let word = item
+                .get("insertText")
+                .or_else(|| item.get("label"))?
+                .as_str()?;
+            Some(word.trim().to_string())
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.dedup();
+    words
+}
+
+fn parse_definition(result: &Value) -> Option<Response> {
+    let location = result.as_array().map_or(Some(result), <[Value]>::first)?;
+    let uri = location
+        .get("uri")
+        .or_else(|| location.get("targetUri"))?
+        .as_str()?;
+    let start = location
+        .get("range")
+        .or_else(|| location.get("targetSelectionRange"))?
+        .get("start")?;
+    Some(Response::Definition(
+        uri_to_path(uri)?,
+        start.get("line")?.as_usize()?,
+        start.get("character")?.as_usize()?,
+    ))
+}
+
+fn parse_references(result: &Value) -> Vec<Location> {
+    result
+        .as_array()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|location| {
+            let start = location.get("range")?.get("start")?;
+            Some(Location {
+                path: uri_to_path(location.get("uri")?.as_str()?)?,
+                line: start.get("line")?.as_usize()?,
+                character: start.get("character")?.as_usize()?,
+            })
+        })
+        .collect()
+}
+
+fn parse_hover(result: &Value) -> Vec<String> {
+    let text = |value: &Value| {
+        value
+            .as_str()
+            .or_else(|| value.get("value").and_then(Value::as_str))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let contents = result.get("contents").unwrap_or(&Value::Null);
+    let text = contents.as_array().map_or_else(
+        || text(contents),
+        |items| items.iter().map(text).collect::<Vec<_>>().join("\n\n"),
+    );
+    text.lines()
+        .filter(|line| !line.starts_with("```"))
+        .map(str::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn parse_semantic_tokens(result: &Value, token_types: &[String]) -> Vec<SemanticToken> {
+    let data: Vec<usize> = result
+        .get("data")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Value::as_usize)
+        .collect();
+    let mut tokens = Vec::new();
+    let (mut line, mut start) = (0, 0);
+    for token in data.chunks_exact(5) {
+        let &[delta_line, delta_start, length, kind, _] = token else {
+            continue;
+        };
+        if delta_line > 0 {
+            line += delta_line;
+            start = delta_start;
+        } else {
+            start += delta_start;
+        }
+        if let Some(kind) = token_types.get(kind) {
+            tokens.push(SemanticToken {
+                line,
+                start,
+                length,
+                kind: kind.clone(),
+            });
+        }
+    }
+    tokens
+}
+
+pub fn language_id(file_type: &str) -> String {
+    match file_type {
+        "C++" => "cpp".to_string(),
+        name => name.to_lowercase(),
+    }
+}
+
+fn path_to_uri(path: &str) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+fn uri_to_path(uri: &str) -> Option<String> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::new();
+    let mut index = 0;
+    while let Some(&byte) = encoded.get(index) {
+        let decoded = (byte == b'%')
+            .then(|| encoded.get(index.saturating_add(1)..index.saturating_add(3)))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        if let Some(decoded) = decoded {
+            bytes.push(decoded);
+            index = index.saturating_add(3);
+        } else {
+            bytes.push(byte);
+            index = index.saturating_add(1);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+pub fn utf16_column(line: &str, x: usize) -> usize {
+    line.graphemes(true)
+        .take(x)
+        .map(|grapheme| grapheme.encode_utf16().count())
+        .sum()
+}
+
+pub fn grapheme_column(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    line.graphemes(true)
+        .take_while(|grapheme| {
+            units = grapheme.encode_utf16().count().saturating_add(units);
+            units <= character
+        })
+        .count()
+}
@@ -7,23 +7,89 @@
     clippy::wildcard_enum_match_arm,
     clippy::else_if_without_else
 )]
+mod browser;
+mod case;
+mod cli;
+mod completion;
+mod config;
+mod diff;
 mod document;
 mod editor;
+mod encoding;
 mod filetype;
+mod finder;
+mod gap_buffer;
+mod grep;
+mod hex;
 mod highlighting;
+mod history;
+mod json;
+mod jump_list;
+mod keymap;
+mod lsp;
+mod matcher;
+mod pager;
 mod row;
+mod script;
+mod shell;
+mod spell;
+mod status_bar;
+mod swap;
+mod tags;
 mod terminal;
+mod theme;
+mod undo;
+mod window;
 
+pub use config::Config;
+pub use config::FileTypeConfig;
+pub use config::LineNumbers;
+pub use config::StatusBarConfig;
 pub use document::Document;
+pub use document::LineEnding;
 use editor::Editor;
 pub use editor::Position;
 pub use editor::SearchDirection;
+pub use encoding::Encoding;
 pub use filetype::FileType;
 pub use filetype::HighlightingOptions;
+pub use gap_buffer::GapBuffer;
+pub use keymap::describe_keys;
+pub use keymap::Command;
+pub use keymap::Key;
+pub use keymap::Keymap;
+pub use keymap::KeymapPreset;
+pub use keymap::Lookup;
+pub use matcher::Matcher;
+pub use matcher::SearchOptions;
 pub use row::Row;
+pub use terminal::ColorSupport;
 pub use terminal::Terminal;
+pub use theme::Theme;
+pub use window::Layout;
+pub use window::Rect;
+pub use window::SplitDirection;
+pub use window::Window;
 
 fn main() {
-    let mut editor = Editor::default();
-    editor.run();
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Action::Run(args)) => args,
+        Ok(cli::Action::Help) => {
+            println!("{}", cli::usage());
+            return;
+        }
+        Ok(cli::Action::Version) => {
+            println!("hecto {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(error) => {
+            eprintln!("hecto: {}\n\n{}", error, cli::usage());
+            std::process::exit(2);
+        }
+    };
+    let result = Editor::new(&args).and_then(|mut editor| editor.run());
+    if let Err(error) = result {
+        eprintln!("hecto: {}", error);
+        std::process::exit(1);
+    }
 }
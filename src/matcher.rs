@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+const REGEX_PREFIX: &str = "re:";
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum Case {
+    #[default]
+    Sensitive,
+    Insensitive,
+    Smart,
+}
+
+impl Case {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Sensitive => Self::Insensitive,
+            Self::Insensitive => Self::Smart,
+            Self::Smart => Self::Sensitive,
+        }
+    }
+
+    fn ignores(self, query: &str) -> bool {
+        match self {
+            Self::Sensitive => false,
+            Self::Insensitive => true,
+            Self::Smart => !query.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct SearchOptions {
+    pub case: Case,
+    pub whole_word: bool,
+}
+
+impl SearchOptions {
+    pub fn describe(self) -> String {
+        let case = match self.case {
+            Case::Sensitive => "Aa",
+            Case::Insensitive => "aa",
+            Case::Smart => "aA",
+        };
+        let word = if self.whole_word { "[w]" } else { "[ ]" };
+        format!("[{}]{}", case, word)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Literal(String),
+    Escaped(Regex),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(query: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        if let Some(pattern) = query.strip_prefix(REGEX_PREFIX) {
+            let pattern = if options.whole_word && !pattern.is_empty() {
+                format!(r"\b(?:{})\b", pattern)
+            } else {
+                pattern.to_string()
+            };
+            return Ok(Self::Regex(
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(options.case.ignores(query))
+                    .build()?,
+            ));
+        }
+        let ignore_case = options.case.ignores(query);
+        if query.is_empty() || (!ignore_case && !options.whole_word) {
+            return Ok(Self::Literal(query.to_string()));
+        }
+        let mut pattern = regex::escape(query);
+        if options.whole_word {
+            if query.starts_with(is_word_char) {
+                pattern.insert_str(0, r"\b");
+            }
+            if query.ends_with(is_word_char) {
+                pattern.push_str(r"\b");
+            }
+        }
+        Ok(Self::Escaped(
+            RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()?,
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Literal(query) => query.is_empty(),
+            Self::Escaped(regex) | Self::Regex(regex) => regex.as_str().is_empty(),
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn find(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        match self {
+            Self::Literal(query) => {
+                let index = haystack.get(start..)?.find(query.as_str())?;
+                Some(start + index..start + index + query.len())
+            }
+            Self::Escaped(regex) | Self::Regex(regex) => {
+                let mut start = start;
+                while start <= haystack.len() {
+                    let found = regex.find_at(haystack, start)?;
+                    if !found.range().is_empty() {
+                        return Some(found.range());
+                    }
+                    start = found.end() + 1;
+                    while !haystack.is_char_boundary(start) && start < haystack.len() {
+                        start += 1;
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn rfind(&self, haystack: &str, end: usize) -> Option<Range<usize>> {
+        match self {
+            Self::Literal(query) => {
+                let index = haystack.get(..end)?.rfind(query.as_str())?;
+                Some(index..index + query.len())
+            }
+            Self::Escaped(regex) | Self::Regex(regex) => regex
+                .find_iter(haystack)
+                .take_while(|found| found.start() < end)
+                .filter(|found| !found.range().is_empty() && found.end() <= end)
+                .last()
+                .map(|found| found.range()),
+        }
+    }
+
+    pub fn expand(&self, haystack: &str, found: Range<usize>, replacement: &str) -> String {
+        match self {
+            Self::Literal(_) | Self::Escaped(_) => replacement.to_string(),
+            Self::Regex(regex) => {
+                let mut result = String::new();
+                if let Some(captures) = regex
+                    .captures_at(haystack, found.start)
+                    .filter(|captures| captures.get(0).is_some_and(|m| m.range() == found))
+                {
+                    captures.expand(replacement, &mut result);
+                } else {
+                    result.push_str(replacement);
+                }
+                result
+            }
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,88 @@
+use crate::{describe_keys, Command, Keymap};
+
+const INTRO: &[&str] = &[
+    "hecto - a small terminal text editor",
+    "",
+    "Type to insert text and use the arrow keys to move the cursor.",
+    "Hold Shift with a movement key to extend the selection.",
+    "Keys separated by spaces are pressed one after another.",
+    "",
+    "Up/Down, PageUp/PageDown and Home/End scroll this page; Esc or q closes it.",
+    "",
+    "Key bindings",
+    "",
+];
+
+#[derive(Debug, Default)]
+pub struct Pager {
+    title: String,
+    lines: Vec<String>,
+    offset: usize,
+}
+
+impl Pager {
+    pub fn new(title: &str, lines: Vec<String>) -> Self {
+        Self {
+            title: title.to_string(),
+            lines,
+            offset: 0,
+        }
+    }
+
+    pub fn help(keymap: &Keymap) -> Self {
+        let bindings = keymap.bindings();
+        let width = bindings
+            .iter()
+            .map(|(keys, _)| describe_keys(keys).len())
+            .max()
+            .unwrap_or_default();
+        let mut lines: Vec<String> = INTRO.iter().map(ToString::to_string).collect();
+        lines.extend(bindings.iter().map(|(keys, command)| {
+            format!(
+                "  {:width$}  {}",
+                describe_keys(keys),
+                describe_command(*command),
+                width = width
+            )
+        }));
+        Self::new("Help", lines)
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn line(&self, index: usize) -> Option<&str> {
+        self.lines.get(index).map(String::as_str)
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn scroll_by(&mut self, lines: isize, height: usize) {
+        let last = self.lines.len().saturating_sub(height);
+        self.offset = self.offset.saturating_add_signed(lines).min(last);
+    }
+}
+
+fn describe_command(command: Command) -> String {
+    if let Command::Script(index) = command {
+        return format!("Script function {}", index.saturating_add(1));
+    }
+    let name = format!("{:?}", command);
+    let mut description = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if index > 0 && c.is_ascii_uppercase() {
+            description.push(' ');
+            description.push(c.to_ascii_lowercase());
+        } else {
+            description.push(c);
+        }
+    }
+    description
+}
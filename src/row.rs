@@ -1,14 +1,87 @@
-use std::cmp;
+use std::{cmp, ops::Range};
 
-use crossterm::style::Stylize;
+use crossterm::style::{Color, Stylize};
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::{highlighting, HighlightingOptions, SearchDirection};
+use crate::{
+    highlighting, spell::SpellChecker, HighlightingOptions, Matcher, SearchDirection, Theme,
+};
+
+const TODO_MARKERS: [&str; 4] = ["TODO", "FIXME", "HACK", "NOTE"];
+
+const WIDE_RANGES: [(u32, u32); 63] = [
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x17000, 0x18cff),
+    (0x1b000, 0x1b2ff),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f1e6, 0x1f1ff),
+    (0x1f200, 0x1f251),
+    (0x1f300, 0x1f64f),
+    (0x1f680, 0x1f6ff),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f900, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
 
 #[derive(Debug, Default)]
 pub struct Row {
     string: String,
     highlighting: Vec<highlighting::Type>,
+    semantic: Vec<(Range<usize>, highlighting::Type)>,
+    entry: highlighting::State,
+    state: highlighting::State,
     len: usize,
     pub is_highlighted: bool,
 }
@@ -18,6 +91,9 @@ impl From<&str> for Row {
         Self {
             string: String::from(slice),
             highlighting: Vec::new(),
+            semantic: Vec::new(),
+            entry: highlighting::State::Normal,
+            state: highlighting::State::Normal,
             len: slice.graphemes(true).count(),
             is_highlighted: false,
         }
@@ -25,32 +101,166 @@ impl From<&str> for Row {
 }
 
 impl Row {
-    pub fn render(&self, start: usize, end: usize) -> String {
-        let end = cmp::min(end, self.string.len());
-        let start = cmp::min(start, end);
-        let mut result = String::new();
-        #[allow(clippy::integer_arithmetic)]
-        for (index, grapheme) in self.string[..]
+    #[allow(clippy::arithmetic_side_effects, clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        theme: &Theme,
+        tab_width: usize,
+        start: usize,
+        end: usize,
+        selection: Option<Range<usize>>,
+        bracket: Option<usize>,
+        misspelled: &[Range<usize>],
+        changed: Option<Range<usize>>,
+        color_column: Option<usize>,
+        background: Option<Color>,
+        show_whitespace: bool,
+        indent_guides: bool,
+    ) -> String {
+        let is_blank = |grapheme: &str| grapheme == " " || grapheme == "\t";
+        let indent = self.string[..]
             .graphemes(true)
-            .enumerate()
-            .skip(start)
-            .take(end - start)
-        {
-            if let Some(c) = grapheme.chars().next() {
-                let highlightling_type = self
-                    .highlighting
-                    .get(index)
-                    .unwrap_or(&highlighting::Type::None);
-                if c == '\t' {
-                    result.push(' ');
-                } else {
-                    result.push_str(&c.with(highlightling_type.to_color()).to_string());
-                }
+            .take_while(|grapheme| is_blank(grapheme))
+            .count();
+        let trailing = self.len
+            - self.string[..]
+                .graphemes(true)
+                .rev()
+                .take_while(|grapheme| is_blank(grapheme))
+                .count();
+        let mut result = String::new();
+        let mut column = 0;
+        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
+            if column >= end {
+                break;
+            }
+            let width = grapheme_width(grapheme, column, tab_width);
+            let next_column = column + width;
+            let ruler = color_column.is_some_and(|ruler| (column..next_column).contains(&ruler));
+            let visible = cmp::min(next_column, end).saturating_sub(cmp::max(column, start));
+            let first_visible = column >= start;
+            let marker = if show_whitespace && grapheme == "\t" {
+                Some(('→', theme.ui.whitespace))
+            } else if show_whitespace && grapheme == " " && index >= trailing {
+                Some(('·', theme.ui.whitespace))
+            } else if indent_guides
+                && index < indent
+                && index < trailing
+                && column % cmp::max(tab_width, 1) == 0
+            {
+                Some(('│', theme.ui.indent_guide))
+            } else {
+                None
+            };
+            column = next_column;
+            if visible == 0 {
+                continue;
+            }
+            let highlightling_type = self.highlighting_at(index);
+            let selected = selection
+                .as_ref()
+                .is_some_and(|selection| selection.contains(&index));
+            let placeholder = control_placeholder(grapheme);
+            let text = if let Some((marker, _)) = marker.filter(|_| first_visible) {
+                format!("{}{}", marker, " ".repeat(visible - 1))
+            } else if grapheme == "\t" || visible < width {
+                " ".repeat(visible)
+            } else if let Some(placeholder) = &placeholder {
+                placeholder.clone()
+            } else {
+                grapheme.to_string()
+            };
+            let syntax = highlightling_type.to_color(theme);
+            let is_changed = changed
+                .as_ref()
+                .is_some_and(|changed| changed.contains(&index));
+            let is_misspelled = misspelled.iter().any(|word| word.contains(&index));
+            let (foreground, highlight) = if selected {
+                (
+                    theme.ui.selection_foreground,
+                    Some(theme.ui.selection_background),
+                )
+            } else if let Some((_, color)) = marker {
+                (color, None)
+            } else if placeholder.is_some() {
+                (theme.ui.control_character, None)
+            } else if ruler {
+                (syntax, Some(theme.ui.color_column))
+            } else if bracket == Some(index) {
+                (syntax, Some(theme.ui.matching_bracket))
+            } else if is_changed {
+                (syntax, Some(theme.ui.diff_text))
+            } else if is_misspelled {
+                (theme.ui.misspelled, None)
+            } else {
+                (syntax, None)
+            };
+            let mut styled = text.with(foreground);
+            if let Some(color) = highlight.or(background) {
+                styled = styled.on(color);
             }
+            if is_misspelled && highlight.is_none() && marker.is_none() && placeholder.is_none() {
+                styled = styled.underlined();
+            }
+            result.push_str(&styled.to_string());
         }
         result
     }
 
+    pub fn column(&self, x: usize, tab_width: usize) -> usize {
+        let mut column: usize = 0;
+        for grapheme in self.string[..].graphemes(true).take(x) {
+            column = column.saturating_add(grapheme_width(grapheme, column, tab_width));
+        }
+        column
+    }
+
+    pub fn index_at(&self, column: usize, tab_width: usize) -> usize {
+        let mut current: usize = 0;
+        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
+            current = current.saturating_add(grapheme_width(grapheme, current, tab_width));
+            if current > column {
+                return index;
+            }
+        }
+        self.len
+    }
+
+    pub fn width(&self, tab_width: usize) -> usize {
+        self.column(self.len, tab_width)
+    }
+
+    #[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+    pub fn wrap(&self, width: usize, tab_width: usize) -> Vec<Range<usize>> {
+        let width = cmp::max(width, 1);
+        let graphemes: Vec<&str> = self.string[..].graphemes(true).collect();
+        let mut columns = Vec::with_capacity(graphemes.len() + 1);
+        let mut column = 0;
+        for grapheme in &graphemes {
+            columns.push(column);
+            column += grapheme_width(grapheme, column, tab_width);
+        }
+        columns.push(column);
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut index = 0;
+        while index < graphemes.len() {
+            if index > start && columns[index + 1] - columns[start] > width {
+                let end = (start + 1..=index)
+                    .rev()
+                    .find(|index| graphemes[index - 1].chars().all(char::is_whitespace))
+                    .unwrap_or(index);
+                lines.push(start..end);
+                start = end;
+                index = end;
+                continue;
+            }
+            index += 1;
+        }
+        lines.push(start..self.len);
+        lines
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -60,29 +270,30 @@ impl Row {
     }
 
     pub fn insert(&mut self, at: usize, c: char) {
+        self.is_highlighted = false;
+        self.semantic.clear();
         if at >= self.len() {
             self.string.push(c);
-            self.len += 1;
+            self.len = self.string[..].graphemes(true).count();
             return;
         }
         let mut result: String = String::new();
-        let mut length = 0;
         for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            length += 1;
             if index == at {
-                length += 1;
                 result.push(c);
             }
             result.push_str(grapheme);
         }
-        self.len = length;
         self.string = result;
+        self.len = self.string[..].graphemes(true).count();
     }
 
     pub fn delete(&mut self, at: usize) {
         if at >= self.len() {
             return;
         }
+        self.is_highlighted = false;
+        self.semantic.clear();
         let mut result: String = String::new();
         let mut length = 0;
         for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
@@ -96,8 +307,10 @@ impl Row {
     }
 
     pub fn append(&mut self, new: &Self) {
+        self.is_highlighted = false;
+        self.semantic.clear();
         self.string = format!("{}{}", self.string, new.string);
-        self.len += new.len;
+        self.len = self.string[..].graphemes(true).count();
     }
 
     pub fn split(&mut self, at: usize) -> Self {
@@ -116,73 +329,227 @@ impl Row {
         }
         self.string = row;
         self.len = length;
+        self.is_highlighted = false;
+        self.semantic.clear();
         Self {
             string: splitted_row,
             highlighting: Vec::new(),
+            semantic: Vec::new(),
+            entry: highlighting::State::Normal,
+            state: highlighting::State::Normal,
             len: splitted_length,
             is_highlighted: false,
         }
     }
 
+    pub fn slice(&self, start: usize, end: usize) -> String {
+        self.string[..]
+            .graphemes(true)
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect()
+    }
+
+    pub fn insert_str(&mut self, at: usize, string: &str) {
+        let tail = self.split(at);
+        self.append(&Self::from(string));
+        self.append(&tail);
+    }
+
+    pub fn delete_range(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        let tail = self.split(end);
+        self.split(start);
+        self.append(&tail);
+    }
+
+    fn words(&self) -> Vec<Range<usize>> {
+        self.string
+            .split_word_bound_indices()
+            .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
+            .map(|(index, word)| {
+                self.grapheme_index(index)..self.grapheme_index(index.saturating_add(word.len()))
+            })
+            .collect()
+    }
+
+    pub fn next_word_end(&self, at: usize) -> Option<usize> {
+        self.words()
+            .into_iter()
+            .find(|word| word.end > at)
+            .map(|word| word.end)
+    }
+
+    pub fn previous_word_start(&self, at: usize) -> Option<usize> {
+        self.words()
+            .into_iter()
+            .rev()
+            .find(|word| word.start < at)
+            .map(|word| word.start)
+    }
+
+    pub fn word_at(&self, at: usize) -> Option<Range<usize>> {
+        self.words()
+            .into_iter()
+            .find(|word| word.start <= at && word.end >= at)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn misspelled(&self, spell: &SpellChecker, prose: bool) -> Vec<Range<usize>> {
+        let mut misspelled = Vec::new();
+        let mut index = 0;
+        for word in self.string.split_word_bounds() {
+            let len = word.graphemes(true).count();
+            let checked = self
+                .highlighting
+                .get(index)
+                .is_some_and(|hl_type| match hl_type {
+                    highlighting::Type::Comment
+                    | highlighting::Type::MultilineComment
+                    | highlighting::Type::DocComment
+                    | highlighting::Type::String => true,
+                    highlighting::Type::None
+                    | highlighting::Type::Emphasis
+                    | highlighting::Type::Heading => prose,
+                    _ => false,
+                });
+            if checked && SpellChecker::is_checked(word) && !spell.check(word) {
+                misspelled.push(index..index + len);
+            }
+            index += len;
+        }
+        misspelled
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn todos(&self) -> Vec<usize> {
+        self.highlighting
+            .iter()
+            .enumerate()
+            .filter(|&(index, hl_type)| {
+                *hl_type == highlighting::Type::Todo
+                    && (index == 0
+                        || self.highlighting.get(index - 1) != Some(&highlighting::Type::Todo))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_todos(&mut self) {
+        let mut index = 0;
+        for word in self.string.split_word_bounds() {
+            let len = word.graphemes(true).count();
+            let in_comment = matches!(
+                self.highlighting.get(index),
+                Some(
+                    highlighting::Type::Comment
+                        | highlighting::Type::MultilineComment
+                        | highlighting::Type::DocComment
+                )
+            );
+            if in_comment && TODO_MARKERS.contains(&word) {
+                for hl_type in self.highlighting.iter_mut().skip(index).take(len) {
+                    *hl_type = highlighting::Type::Todo;
+                }
+            }
+            index += len;
+        }
+    }
+
+    pub fn word_start(&self, at: usize) -> usize {
+        self.words()
+            .into_iter()
+            .find(|word| word.start < at && word.end >= at)
+            .map_or(at, |word| word.start)
+    }
+
+    pub fn brackets(&self) -> Vec<(usize, char)> {
+        self.string[..]
+            .graphemes(true)
+            .enumerate()
+            .filter_map(|(index, grapheme)| {
+                let c = grapheme.parse::<char>().ok()?;
+                let is_code = !matches!(
+                    self.highlighting.get(index),
+                    Some(
+                        highlighting::Type::String
+                            | highlighting::Type::Character
+                            | highlighting::Type::Comment
+                            | highlighting::Type::MultilineComment
+                            | highlighting::Type::DocComment
+                            | highlighting::Type::Regex
+                    )
+                );
+                ("()[]{}".contains(c) && is_code).then_some((index, c))
+            })
+            .collect()
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         self.string.as_bytes()
     }
 
-    pub fn find(&self, query: &str, at: usize, direction: SearchDirection) -> Option<usize> {
-        if at > self.len || query.is_empty() {
+    pub fn state(&self) -> &highlighting::State {
+        &self.state
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    fn byte_index(&self, grapheme_index: usize) -> usize {
+        self.string[..]
+            .grapheme_indices(true)
+            .nth(grapheme_index)
+            .map_or(self.string.len(), |(byte_index, _)| byte_index)
+    }
+
+    fn grapheme_index(&self, byte_index: usize) -> usize {
+        self.string[..]
+            .grapheme_indices(true)
+            .take_while(|(index, _)| *index < byte_index)
+            .count()
+    }
+
+    pub fn find(
+        &self,
+        matcher: &Matcher,
+        at: usize,
+        direction: SearchDirection,
+    ) -> Option<Range<usize>> {
+        if at > self.len || matcher.is_empty() {
             return None;
         }
-        let start = if direction == SearchDirection::Forward {
-            at
-        } else {
-            0
-        };
-        let end = if direction == SearchDirection::Forward {
-            self.len
-        } else {
-            at
-        };
-        #[allow(clippy::integer_arithmetic)]
-        let substring: String = self.string[..]
-            .graphemes(true)
-            .skip(start)
-            .take(end - start)
-            .collect();
-        let matching_byte_index = if direction == SearchDirection::Forward {
-            substring.find(query)
+        let at = self.byte_index(at);
+        let range = if direction == SearchDirection::Forward {
+            matcher.find(&self.string, at)
         } else {
-            substring.rfind(query)
-        };
-        if let Some(matching_byte_index) = matching_byte_index {
-            for (grapheme_index, (byte_index, _)) in
-                substring[..].grapheme_indices(true).enumerate()
-            {
-                if matching_byte_index == byte_index {
-                    #[allow(clippy::integer_arithmetic)]
-                    return Some(start + grapheme_index);
-                }
-            }
-        }
-        None
+            matcher.rfind(&self.string, at)
+        }?;
+        Some(self.grapheme_index(range.start)..self.grapheme_index(range.end))
     }
 
-    fn highlight_match(&mut self, word: &Option<String>) {
-        if let Some(word) = word {
-            if word.is_empty() {
-                return;
-            }
+    pub fn expand(&self, matcher: &Matcher, found: &Range<usize>, replacement: &str) -> String {
+        matcher.expand(
+            &self.string,
+            self.byte_index(found.start)..self.byte_index(found.end),
+            replacement,
+        )
+    }
+
+    fn highlight_match(&mut self, matcher: Option<&Matcher>) {
+        if let Some(matcher) = matcher {
             let mut index = 0;
-            while let Some(search_match) = self.find(word, index, SearchDirection::Forward) {
-                if let Some(next_index) = search_match.checked_add(word[..].graphemes(true).count())
-                {
-                    #[allow(clippy::indexing_slicing)]
-                    for i in search_match..next_index {
-                        self.highlighting[i] = highlighting::Type::Match;
+            while let Some(search_match) = self.find(matcher, index, SearchDirection::Forward) {
+                for i in search_match.clone() {
+                    if let Some(hl_type) = self.highlighting.get_mut(i) {
+                        *hl_type = highlighting::Type::Match;
                     }
-                    index = next_index;
-                } else {
-                    break;
                 }
+                index = search_match.end;
             }
         }
     }
@@ -221,7 +588,7 @@ impl Row {
         hl_type: highlighting::Type,
     ) -> bool {
         if *index > 0 {
-            #[allow(clippy::indexing_slicing, clippy::integer_arithmetic)]
+            #[allow(clippy::indexing_slicing, clippy::arithmetic_side_effects)]
             let prev_char = chars[*index - 1];
             if !is_separator(prev_char) {
                 return false;
@@ -229,14 +596,14 @@ impl Row {
         }
         for word in keywords {
             if *index < chars.len().saturating_sub(word.len()) {
-                #[allow(clippy::indexing_slicing, clippy::integer_arithmetic)]
+                #[allow(clippy::indexing_slicing, clippy::arithmetic_side_effects)]
                 let next_char = chars[*index + word.len()];
                 if !is_separator(next_char) {
                     continue;
                 }
             }
 
-            if self.highlight_str(index, &word, chars, hl_type) {
+            if self.highlight_str(index, word, chars, hl_type) {
                 return true;
             }
         }
@@ -271,6 +638,7 @@ impl Row {
         )
     }
 
+    #[allow(clippy::arithmetic_side_effects)]
     fn highlight_char(
         &mut self,
         index: &mut usize,
@@ -279,20 +647,23 @@ impl Row {
         chars: &[char],
     ) -> bool {
         if opts.characters() && c == '\'' {
-            if let Some(next_char) = chars.get(index.saturating_add(1)) {
-                let closing_index = if *next_char == '\\' {
-                    index.saturating_add(3)
+            if let Some(next_char) = chars.get(*index + 1) {
+                let escape = if *next_char == '\\' {
+                    escape_len(chars, *index + 1)
                 } else {
-                    index.saturating_add(2)
+                    0
                 };
-                if let Some(closing_char) = chars.get(closing_index) {
-                    if *closing_char == '\'' {
-                        for _ in 0..=closing_index.saturating_sub(*index) {
-                            self.highlighting.push(highlighting::Type::Character);
-                            *index += 1;
-                        }
-                        return true;
+                let closing_index = *index + 1 + cmp::max(escape, 1);
+                if chars.get(closing_index) == Some(&'\'') {
+                    for offset in 0..=closing_index - *index {
+                        self.highlighting.push(if offset > 0 && offset <= escape {
+                            highlighting::Type::Escape
+                        } else {
+                            highlighting::Type::Character
+                        });
                     }
+                    *index = closing_index + 1;
+                    return true;
                 }
             }
         }
@@ -303,172 +674,1200 @@ impl Row {
         &mut self,
         index: &mut usize,
         opts: &HighlightingOptions,
-        c: char,
         chars: &[char],
     ) -> bool {
-        if opts.comments() && c == '/' && *index < chars.len() {
-            if let Some(next_char) = chars.get(index.saturating_add(1)) {
-                if *next_char == '/' {
-                    for _ in *index..chars.len() {
-                        self.highlighting.push(highlighting::Type::Comment);
-                        *index += 1;
-                    }
-                    return true;
+        if let Some(delimiter) = opts.comment() {
+            if starts_with_at(chars, *index, delimiter) {
+                for _ in *index..chars.len() {
+                    self.highlighting.push(highlighting::Type::Comment);
+                    *index += 1;
                 }
-            };
+                return true;
+            }
         }
         false
     }
 
-    #[allow(clippy::indexing_slicing, clippy::integer_arithmetic)]
-    fn highlight_multiline_comment(
+    fn highlight_doc_comment(
         &mut self,
         index: &mut usize,
         opts: &HighlightingOptions,
-        c: char,
         chars: &[char],
     ) -> bool {
-        if opts.comments() && c == '/' && *index < chars.len() {
-            if let Some(next_char) = chars.get(index.saturating_add(1)) {
-                if *next_char == '*' {
-                    let closing_index =
-                        if let Some(closing_index) = self.string[*index + 2..].find("*/") {
-                            *index + closing_index + 4
-                        } else {
-                            chars.len()
-                        };
-                    for _ in *index..closing_index {
-                        self.highlighting.push(highlighting::Type::MultilineComment);
-                        *index += 1;
-                    }
-                    return true;
-                }
-            };
+        let is_doc_comment = opts.doc_comments().iter().any(|delimiter| {
+            starts_with_at(chars, *index, delimiter)
+                && chars.get(index.saturating_add(delimiter.chars().count()))
+                    != delimiter.chars().last().as_ref()
+        });
+        if !is_doc_comment {
+            return false;
         }
-        false
+        for _ in *index..chars.len() {
+            self.highlighting.push(highlighting::Type::DocComment);
+            *index += 1;
+        }
+        true
     }
 
-    fn highlight_string(
+    fn highlight_multiline_comment(
         &mut self,
         index: &mut usize,
         opts: &HighlightingOptions,
-        c: char,
         chars: &[char],
-    ) -> bool {
-        if opts.strings() && c == '"' {
-            loop {
-                self.highlighting.push(highlighting::Type::String);
-                *index += 1;
-                if let Some(next_char) = chars.get(*index) {
-                    if *next_char == '"' {
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            }
-            self.highlighting.push(highlighting::Type::String);
+    ) -> Option<highlighting::State> {
+        let (start, _) = opts.multiline_comment()?;
+        if !starts_with_at(chars, *index, start) {
+            return None;
+        }
+        for _ in start.chars() {
+            self.highlighting.push(highlighting::Type::MultilineComment);
             *index += 1;
-            return true;
         }
-        false
+        Some(highlighting::State::MultilineComment(1))
     }
 
-    fn highlight_number(
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_multiline_string(
         &mut self,
         index: &mut usize,
         opts: &HighlightingOptions,
-        c: char,
         chars: &[char],
-    ) -> bool {
-        if opts.numbers() && c.is_ascii_digit() {
-            if *index > 0 {
-                #[allow(clippy::indexing_slicing, clippy::integer_arithmetic)]
-                let prev_char = chars[*index - 1];
-                if !prev_char.is_ascii_punctuation() && !prev_char.is_ascii_whitespace() {
-                    return false;
-                }
-            }
-            loop {
-                self.highlighting.push(highlighting::Type::Number);
-                *index += 1;
-                if let Some(next_char) = chars.get(*index) {
-                    if *next_char != '.' && !next_char.is_ascii_digit() {
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            }
-            return true;
+    ) -> Option<highlighting::State> {
+        let prefix = string_prefix(opts, chars, *index);
+        let delimiter = opts
+            .multiline_strings()
+            .iter()
+            .find(|delimiter| starts_with_at(chars, *index + prefix, delimiter))?;
+        let raw = is_raw_prefix(chars, *index, prefix);
+        for _ in 0..prefix + delimiter.chars().count() {
+            self.highlighting.push(highlighting::Type::String);
+            *index += 1;
         }
-        false
+        Some(if raw {
+            highlighting::State::RawString(delimiter.clone())
+        } else {
+            highlighting::State::MultilineString(delimiter.clone())
+        })
     }
 
-    #[allow(clippy::indexing_slicing, clippy::integer_arithmetic)]
-    pub fn highlight(
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_raw_string(
         &mut self,
+        index: &mut usize,
         opts: &HighlightingOptions,
-        word: &Option<String>,
-        start_with_comment: bool,
-    ) -> bool {
-        let chars: Vec<char> = self.string.chars().collect();
-        if self.is_highlighted && word.is_none() {
-            if let Some(hl_type) = self.highlighting.last() {
-                if *hl_type == highlighting::Type::MultilineComment
-                    && self.string.len() > 1
-                    && self.string[self.string.len() - 2..] == *"*/"
-                {
-                    return true;
-                }
-            }
-            return false;
+        chars: &[char],
+    ) -> Option<highlighting::State> {
+        if !opts.raw_strings()
+            || (*index > 0 && chars.get(*index - 1).is_some_and(|c| is_identifier(*c)))
+        {
+            return None;
         }
-        self.highlighting = Vec::new();
-        let mut index = 0;
-        let mut in_multiline_comment = start_with_comment;
-        if in_multiline_comment {
-            let closing_index = if let Some(closing_index) = self.string.find("*/") {
-                closing_index + 2
+        let mut end = *index;
+        if chars.get(end) == Some(&'b') {
+            end += 1;
+        }
+        if chars.get(end) != Some(&'r') {
+            return None;
+        }
+        end += 1;
+        let hashes = chars.iter().skip(end).take_while(|c| **c == '#').count();
+        end += hashes;
+        if chars.get(end) != Some(&'"') {
+            return None;
+        }
+        while *index <= end {
+            self.highlighting.push(highlighting::Type::String);
+            *index += 1;
+        }
+        Some(highlighting::State::RawString(format!(
+            "\"{}",
+            "#".repeat(hashes)
+        )))
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn close_comment(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        chars: &[char],
+        mut depth: usize,
+    ) -> highlighting::State {
+        let Some((start, end)) = opts.multiline_comment() else {
+            return highlighting::State::Normal;
+        };
+        while *index < chars.len() {
+            let delimiter = if starts_with_at(chars, *index, end) {
+                depth -= 1;
+                end
+            } else if opts.nested_comments() && starts_with_at(chars, *index, start) {
+                depth += 1;
+                start
             } else {
-                chars.len()
+                self.highlighting.push(highlighting::Type::MultilineComment);
+                *index += 1;
+                continue;
             };
-            for _ in 0..closing_index {
+            for _ in delimiter.chars() {
                 self.highlighting.push(highlighting::Type::MultilineComment);
+                *index += 1;
+            }
+            if depth == 0 {
+                return highlighting::State::Normal;
             }
-            index = closing_index;
         }
-        while let Some(c) = chars.get(index) {
-            if self.highlight_multiline_comment(&mut index, &opts, *c, &chars) {
-                in_multiline_comment = true;
-                continue;
+        highlighting::State::MultilineComment(depth)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn close_block(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        chars: &[char],
+        state: highlighting::State,
+    ) -> highlighting::State {
+        let escapes = matches!(state, highlighting::State::MultilineString(_));
+        let (end, hl_type, interpolation) = match &state {
+            highlighting::State::Normal => return state,
+            highlighting::State::MultilineComment(depth) => {
+                return self.close_comment(index, opts, chars, *depth);
             }
-            in_multiline_comment = false;
-            let old_index = index;
-            if self.highlight_char(&mut index, opts, *c, &chars)
-                || self.highlight_comment(&mut index, opts, *c, &chars)
-                || self.highlight_primary_keywords(&mut index, &opts, &chars)
-                || self.highlight_secondary_keywords(&mut index, &opts, &chars)
-                || self.highlight_string(&mut index, opts, *c, &chars)
-                || self.highlight_number(&mut index, opts, *c, &chars)
-            {
-                if index == old_index {
-                    index += 1;
+            highlighting::State::MultilineString(delimiter) => (
+                delimiter.as_str(),
+                highlighting::Type::String,
+                opts.interpolation(),
+            ),
+            highlighting::State::RawString(delimiter) => {
+                (delimiter.as_str(), highlighting::Type::String, None)
+            }
+            highlighting::State::Heredoc(delimiter) => {
+                let closed = self.string.trim() == delimiter;
+                while *index < chars.len() {
+                    self.highlighting.push(highlighting::Type::String);
+                    *index += 1;
+                }
+                return if closed {
+                    highlighting::State::Normal
+                } else {
+                    state
+                };
+            }
+            highlighting::State::Fence(fence) => {
+                let closed = self.string.trim_start().starts_with(fence.as_str());
+                while *index < chars.len() {
+                    self.highlighting.push(highlighting::Type::Code);
+                    *index += 1;
+                }
+                return if closed {
+                    highlighting::State::Normal
+                } else {
+                    state
+                };
+            }
+        };
+        while let Some(c) = chars.get(*index) {
+            if starts_with_at(chars, *index, end) {
+                for _ in end.chars() {
+                    self.highlighting.push(hl_type);
+                    *index += 1;
+                }
+                return highlighting::State::Normal;
+            }
+            if let Some((open, close)) = interpolation {
+                if starts_with_at(chars, *index, open) {
+                    let closing_index = find_at(chars, *index + open.chars().count(), close)
+                        .map_or(chars.len(), |closing_index| {
+                            closing_index + close.chars().count()
+                        });
+                    while *index < closing_index {
+                        self.highlighting.push(highlighting::Type::None);
+                        *index += 1;
+                    }
+                    continue;
                 }
+            }
+            if escapes && *c == '\\' {
+                self.highlight_escape(index, chars);
                 continue;
             }
-            self.highlighting.push(highlighting::Type::None);
-            index += 1;
+            self.highlighting.push(hl_type);
+            *index += 1;
         }
-        self.highlight_match(word);
-        if in_multiline_comment && &self.string[self.string.len().saturating_sub(2)..] != "*/" {
-            return true;
+        state
+    }
+
+    fn highlight_escape(&mut self, index: &mut usize, chars: &[char]) {
+        let end = cmp::min(index.saturating_add(escape_len(chars, *index)), chars.len());
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Escape);
+            *index += 1;
         }
-        self.is_highlighted = true;
-        false
     }
-}
 
-fn is_separator(c: char) -> bool {
-    c.is_ascii_punctuation() || c.is_ascii_whitespace()
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_string(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        chars: &[char],
+    ) -> bool {
+        let prefix = string_prefix(opts, chars, *index);
+        let delimiter = match chars.get(*index + prefix) {
+            Some(c) if opts.string_delimiters().contains(c) => *c,
+            _ => return false,
+        };
+        let raw = is_raw_prefix(chars, *index, prefix);
+        for _ in 0..=prefix {
+            self.highlighting.push(highlighting::Type::String);
+            *index += 1;
+        }
+        while let Some(c) = chars.get(*index) {
+            if *c == '\\' && !raw {
+                self.highlight_escape(index, chars);
+                continue;
+            }
+            if delimiter == '"' && self.highlight_variable(index, opts, *c, chars) {
+                continue;
+            }
+            self.highlighting.push(highlighting::Type::String);
+            *index += 1;
+            if *c == '\\' && *index < chars.len() {
+                self.highlighting.push(highlighting::Type::String);
+                *index += 1;
+            } else if *c == delimiter {
+                break;
+            }
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_heredoc(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        chars: &[char],
+    ) -> Option<String> {
+        if !opts.heredocs()
+            || !starts_with_at(chars, *index, "<<")
+            || chars.get(*index + 2) == Some(&'<')
+        {
+            return None;
+        }
+        let mut end = *index + 2;
+        if chars.get(end) == Some(&'-') {
+            end += 1;
+        }
+        while chars.get(end).is_some_and(|c| c.is_whitespace()) {
+            end += 1;
+        }
+        let quote = chars.get(end).copied().filter(|c| matches!(c, '"' | '\''));
+        if quote.is_some() {
+            end += 1;
+        }
+        let start = end;
+        while chars.get(end).is_some_and(|c| is_identifier(*c)) {
+            end += 1;
+        }
+        if end == start {
+            return None;
+        }
+        let delimiter = chars.get(start..end)?.iter().collect();
+        if let Some(quote) = quote {
+            if chars.get(end) != Some(&quote) {
+                return None;
+            }
+            end += 1;
+        }
+        while *index < end {
+            self.highlighting.push(highlighting::Type::String);
+            *index += 1;
+        }
+        Some(delimiter)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_variable(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.variables() {
+            return false;
+        }
+        let end = match (c, chars.get(*index + 1)) {
+            ('`', _) => find_at(chars, *index + 1, "`").map_or(chars.len(), |closing| closing + 1),
+            ('$', Some('{')) => {
+                find_at(chars, *index + 2, "}").map_or(chars.len(), |closing| closing + 1)
+            }
+            ('$', Some('(')) => {
+                let mut depth = 0_usize;
+                let mut end = *index + 1;
+                while let Some(next_char) = chars.get(end) {
+                    end += 1;
+                    match next_char {
+                        '(' => depth += 1,
+                        ')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                end
+            }
+            ('$', Some(next_char)) if next_char.is_alphabetic() || *next_char == '_' => {
+                *index
+                    + 1
+                    + chars
+                        .iter()
+                        .skip(*index + 1)
+                        .take_while(|c| is_identifier(**c))
+                        .count()
+            }
+            ('$', Some(next_char))
+                if next_char.is_ascii_digit() || "@*#?$!-".contains(*next_char) =>
+            {
+                *index + 2
+            }
+            _ => return false,
+        };
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Variable);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_regex(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.regex_literals() || c != '/' || !starts_expression(chars, *index) {
+            return false;
+        }
+        let mut end = *index + 1;
+        let mut in_class = false;
+        loop {
+            match chars.get(end) {
+                None => return false,
+                Some('\\') => end += 1,
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('/') if !in_class => break,
+                Some(_) => {}
+            }
+            end += 1;
+        }
+        end += 1;
+        while chars.get(end).is_some_and(char::is_ascii_alphabetic) {
+            end += 1;
+        }
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Regex);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_key(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        let separator = if let Some(separator) = opts.key_separator() {
+            separator
+        } else {
+            return false;
+        };
+        let mut end = *index;
+        if opts.string_delimiters().contains(&c) {
+            end += 1;
+            while let Some(next_char) = chars.get(end) {
+                end += 1;
+                if *next_char == '\\' {
+                    end += 1;
+                } else if *next_char == c {
+                    break;
+                }
+            }
+        } else if chars
+            .iter()
+            .take(*index)
+            .all(|prev_char| prev_char.is_whitespace() || *prev_char == '-')
+        {
+            while chars
+                .get(end)
+                .is_some_and(|next_char| next_char.is_alphanumeric() || "_-.".contains(*next_char))
+            {
+                end += 1;
+            }
+        }
+        if end == *index {
+            return false;
+        }
+        let mut after = end;
+        while chars
+            .get(after)
+            .is_some_and(|next_char| next_char.is_whitespace())
+        {
+            after += 1;
+        }
+        if chars.get(after) != Some(&separator) {
+            return false;
+        }
+        if !opts.string_delimiters().contains(&c)
+            && separator == ':'
+            && chars
+                .get(after + 1)
+                .is_some_and(|next_char| !next_char.is_whitespace())
+        {
+            return false;
+        }
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Key);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_table_header(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.table_headers()
+            || c != '['
+            || !chars
+                .iter()
+                .take(*index)
+                .all(|prev_char| prev_char.is_whitespace())
+        {
+            return false;
+        }
+        let end = if let Some(closing_index) = find_at(chars, *index, "]") {
+            if chars.get(closing_index + 1) == Some(&']') {
+                closing_index + 2
+            } else {
+                closing_index + 1
+            }
+        } else {
+            return false;
+        };
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Heading);
+            *index += 1;
+        }
+        true
+    }
+
+    fn highlight_decorator(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.decorators()
+            || c != '@'
+            || !chars
+                .iter()
+                .take(*index)
+                .all(|prev_char| prev_char.is_whitespace())
+        {
+            return false;
+        }
+        self.highlighting.push(highlighting::Type::Decorator);
+        *index += 1;
+        while let Some(next_char) = chars.get(*index) {
+            if !next_char.is_alphanumeric() && *next_char != '_' && *next_char != '.' {
+                break;
+            }
+            self.highlighting.push(highlighting::Type::Decorator);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_lifetime(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.lifetimes()
+            || c != '\''
+            || !chars
+                .get(*index + 1)
+                .is_some_and(|next_char| next_char.is_alphabetic() || *next_char == '_')
+        {
+            return false;
+        }
+        self.highlighting.push(highlighting::Type::Lifetime);
+        *index += 1;
+        while chars
+            .get(*index)
+            .is_some_and(|next_char| is_identifier(*next_char))
+        {
+            self.highlighting.push(highlighting::Type::Lifetime);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_attribute(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.attributes() || c != '#' {
+            return false;
+        }
+        let mut end = *index + 1;
+        if chars.get(end) == Some(&'!') {
+            end += 1;
+        }
+        if chars.get(end) != Some(&'[') {
+            return false;
+        }
+        let mut depth = 0_usize;
+        let mut in_string = false;
+        while let Some(next_char) = chars.get(end) {
+            end += 1;
+            match next_char {
+                '\\' if in_string => end += 1,
+                '"' => in_string = !in_string,
+                '[' if !in_string => depth += 1,
+                ']' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let end = cmp::min(end, chars.len());
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Attribute);
+            *index += 1;
+        }
+        true
+    }
+
+    fn highlight_preprocessor(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.preprocessor()
+            || c != '#'
+            || !chars
+                .iter()
+                .take(*index)
+                .all(|prev_char| prev_char.is_whitespace())
+        {
+            return false;
+        }
+        self.highlighting.push(highlighting::Type::Preprocessor);
+        *index += 1;
+        while chars
+            .get(*index)
+            .is_some_and(|next_char| next_char.is_whitespace())
+        {
+            self.highlighting.push(highlighting::Type::Preprocessor);
+            *index += 1;
+        }
+        while chars
+            .get(*index)
+            .is_some_and(|next_char| next_char.is_alphanumeric())
+        {
+            self.highlighting.push(highlighting::Type::Preprocessor);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_number(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.numbers() || !c.is_ascii_digit() {
+            return false;
+        }
+        if *index > 0
+            && chars
+                .get(*index - 1)
+                .is_some_and(|prev_char| is_identifier(*prev_char))
+        {
+            return false;
+        }
+        let digits = |end: usize, radix: u32| {
+            end + chars
+                .iter()
+                .skip(end)
+                .take_while(|next_char| next_char.is_digit(radix) || **next_char == '_')
+                .count()
+        };
+        let radix = match (c, chars.get(*index + 1)) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('o' | 'O')) => 8,
+            ('0', Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+        let mut end = if radix == 10 {
+            let mut end = digits(*index, 10);
+            if chars.get(end) == Some(&'.') && chars.get(end + 1).is_some_and(char::is_ascii_digit)
+            {
+                end = digits(end + 1, 10);
+            }
+            if matches!(chars.get(end), Some('e' | 'E')) {
+                let sign = usize::from(matches!(chars.get(end + 1), Some('+' | '-')));
+                if chars.get(end + 1 + sign).is_some_and(char::is_ascii_digit) {
+                    end = digits(end + 1 + sign, 10);
+                }
+            }
+            end
+        } else {
+            digits(*index + 2, radix)
+        };
+        while chars
+            .get(end)
+            .is_some_and(|next_char| is_identifier(*next_char))
+        {
+            end += 1;
+        }
+        while *index < end {
+            self.highlighting.push(highlighting::Type::Number);
+            *index += 1;
+        }
+        true
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_markdown_block(
+        &mut self,
+        index: &mut usize,
+        chars: &[char],
+    ) -> Option<highlighting::State> {
+        while chars.get(*index).is_some_and(|c| c.is_whitespace()) {
+            self.highlighting.push(highlighting::Type::None);
+            *index += 1;
+        }
+        let rest: String = chars.iter().skip(*index).collect();
+        let hl_type = if rest.starts_with("```") || rest.starts_with("~~~") {
+            highlighting::Type::Code
+        } else if rest.starts_with('#')
+            && rest
+                .trim_start_matches('#')
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+        {
+            highlighting::Type::Heading
+        } else {
+            let marker =
+                if rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ") {
+                    1
+                } else {
+                    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+                    let mut after = rest.chars().skip(digits);
+                    if digits > 0
+                        && matches!(after.next(), Some('.' | ')'))
+                        && after.next().is_none_or(char::is_whitespace)
+                    {
+                        digits + 1
+                    } else {
+                        0
+                    }
+                };
+            for _ in 0..marker {
+                self.highlighting.push(highlighting::Type::Bullet);
+                *index += 1;
+            }
+            return None;
+        };
+        while *index < chars.len() {
+            self.highlighting.push(hl_type);
+            *index += 1;
+        }
+        if hl_type == highlighting::Type::Code {
+            return Some(highlighting::State::Fence(rest.chars().take(3).collect()));
+        }
+        Some(highlighting::State::Normal)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_markdown(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        chars: &[char],
+    ) -> highlighting::State {
+        if *index == 0 {
+            if let Some(state) = self.highlight_markdown_block(index, chars) {
+                return state;
+            }
+        }
+        while let Some(c) = chars.get(*index) {
+            if let Some(opened) = self.highlight_multiline_comment(index, opts, chars) {
+                let state = self.close_block(index, opts, chars, opened);
+                if state != highlighting::State::Normal {
+                    return state;
+                }
+                continue;
+            }
+            let (end, hl_type) = match c {
+                '\\' => (*index + 2, highlighting::Type::None),
+                '`' => match find_at(chars, *index + 1, "`") {
+                    Some(closing_index) => (closing_index + 1, highlighting::Type::Code),
+                    None => (*index + 1, highlighting::Type::None),
+                },
+                '[' => match find_at(chars, *index + 1, "](")
+                    .and_then(|middle| find_at(chars, middle + 2, ")"))
+                {
+                    Some(closing_index) => (closing_index + 1, highlighting::Type::Link),
+                    None => (*index + 1, highlighting::Type::None),
+                },
+                '*' | '_' => {
+                    let run = chars
+                        .iter()
+                        .skip(*index)
+                        .take_while(|next| *next == c)
+                        .count();
+                    let inside_word = *index > 0
+                        && chars
+                            .get(*index - 1)
+                            .is_some_and(|prev| prev.is_alphanumeric())
+                        && chars
+                            .get(*index + run)
+                            .is_some_and(|next| next.is_alphanumeric());
+                    if *c == '_' && inside_word {
+                        (*index + run, highlighting::Type::None)
+                    } else {
+                        (*index + run, highlighting::Type::Emphasis)
+                    }
+                }
+                _ => (*index + 1, highlighting::Type::None),
+            };
+            while *index < cmp::min(end, chars.len()) {
+                self.highlighting.push(hl_type);
+                *index += 1;
+            }
+        }
+        highlighting::State::Normal
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_diff(&mut self, index: &mut usize, chars: &[char]) {
+        let line = &self.string;
+        let (end, hl_type) = if ["diff ", "index ", "--- ", "+++ "]
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+        {
+            (chars.len(), highlighting::Type::Heading)
+        } else if line.starts_with("@@") {
+            let end = find_at(chars, 2, "@@").map_or(chars.len(), |closing| closing + 2);
+            (end, highlighting::Type::Hunk)
+        } else if line.starts_with('+') {
+            (chars.len(), highlighting::Type::Inserted)
+        } else if line.starts_with('-') {
+            (chars.len(), highlighting::Type::Deleted)
+        } else {
+            (chars.len(), highlighting::Type::None)
+        };
+        while *index < end {
+            self.highlighting.push(hl_type);
+            *index += 1;
+        }
+        while *index < chars.len() {
+            self.highlighting.push(highlighting::Type::None);
+            *index += 1;
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn highlight_log(
+        &mut self,
+        index: &mut usize,
+        opts: &HighlightingOptions,
+        c: char,
+        chars: &[char],
+    ) -> bool {
+        if !opts.log_levels()
+            || (*index > 0
+                && chars
+                    .get(*index - 1)
+                    .is_some_and(|prev| is_identifier(*prev)))
+        {
+            return false;
+        }
+        let (len, hl_type) = if c.is_ascii_digit() {
+            (timestamp_len(chars, *index), highlighting::Type::Timestamp)
+        } else {
+            let len = chars
+                .iter()
+                .skip(*index)
+                .take_while(|next| is_identifier(**next))
+                .count();
+            let word: String = chars.iter().skip(*index).take(len).collect();
+            let hl_type = match word.as_str() {
+                "ERROR" | "ERR" | "FATAL" | "CRITICAL" | "PANIC" => highlighting::Type::Error,
+                "WARN" | "WARNING" => highlighting::Type::Warning,
+                "INFO" | "NOTICE" => highlighting::Type::Info,
+                _ => return false,
+            };
+            (len, hl_type)
+        };
+        if len == 0 {
+            return false;
+        }
+        for _ in 0..len {
+            self.highlighting.push(hl_type);
+            *index += 1;
+        }
+        true
+    }
+
+    fn highlighting_at(&self, index: usize) -> highlighting::Type {
+        let syntax = self
+            .highlighting
+            .get(index)
+            .copied()
+            .unwrap_or(highlighting::Type::None);
+        if !matches!(
+            syntax,
+            highlighting::Type::None | highlighting::Type::SecondaryKeywords
+        ) {
+            return syntax;
+        }
+        self.semantic
+            .iter()
+            .find(|(range, _)| range.contains(&index))
+            .map_or(syntax, |(_, hl_type)| *hl_type)
+    }
+
+    pub fn semantic(&self) -> &[(Range<usize>, highlighting::Type)] {
+        &self.semantic
+    }
+
+    pub fn set_semantic(&mut self, semantic: Vec<(Range<usize>, highlighting::Type)>) {
+        self.semantic = semantic;
+    }
+
+    pub fn is_highlighted_from(&self, state: &highlighting::State) -> bool {
+        self.is_highlighted && self.entry == *state
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn highlight(
+        &mut self,
+        opts: &HighlightingOptions,
+        matcher: Option<&Matcher>,
+        state: &highlighting::State,
+    ) -> highlighting::State {
+        if matcher.is_none() && self.is_highlighted_from(state) {
+            return self.state.clone();
+        }
+        self.entry = state.clone();
+        let chars: Vec<char> = self.string.chars().collect();
+        self.highlighting = Vec::new();
+        let mut index = 0;
+        let mut state = self.close_block(&mut index, opts, &chars, state.clone());
+        if opts.markdown() && state == highlighting::State::Normal {
+            state = self.highlight_markdown(&mut index, opts, &chars);
+        }
+        if opts.diff() {
+            self.highlight_diff(&mut index, &chars);
+        }
+        while let Some(c) = chars.get(index) {
+            if let Some(opened) = self
+                .highlight_multiline_comment(&mut index, opts, &chars)
+                .or_else(|| self.highlight_raw_string(&mut index, opts, &chars))
+                .or_else(|| self.highlight_multiline_string(&mut index, opts, &chars))
+            {
+                state = self.close_block(&mut index, opts, &chars, opened);
+                continue;
+            }
+            if let Some(delimiter) = self.highlight_heredoc(&mut index, opts, &chars) {
+                state = highlighting::State::Heredoc(delimiter);
+                continue;
+            }
+            let old_index = index;
+            if self.highlight_char(&mut index, opts, *c, &chars)
+                || self.highlight_variable(&mut index, opts, *c, &chars)
+                || self.highlight_lifetime(&mut index, opts, *c, &chars)
+                || self.highlight_doc_comment(&mut index, opts, &chars)
+                || self.highlight_comment(&mut index, opts, &chars)
+                || self.highlight_table_header(&mut index, opts, *c, &chars)
+                || self.highlight_key(&mut index, opts, *c, &chars)
+                || self.highlight_string(&mut index, opts, &chars)
+                || self.highlight_regex(&mut index, opts, *c, &chars)
+                || self.highlight_decorator(&mut index, opts, *c, &chars)
+                || self.highlight_attribute(&mut index, opts, *c, &chars)
+                || self.highlight_preprocessor(&mut index, opts, *c, &chars)
+                || self.highlight_primary_keywords(&mut index, opts, &chars)
+                || self.highlight_secondary_keywords(&mut index, opts, &chars)
+                || self.highlight_log(&mut index, opts, *c, &chars)
+                || self.highlight_number(&mut index, opts, *c, &chars)
+            {
+                if index == old_index {
+                    index += 1;
+                }
+                continue;
+            }
+            self.highlighting.push(highlighting::Type::None);
+            index += 1;
+        }
+        if self.len != chars.len() {
+            let mut index = 0;
+            self.highlighting = self.string[..]
+                .graphemes(true)
+                .map(|grapheme| {
+                    let hl_type = self
+                        .highlighting
+                        .get(index)
+                        .copied()
+                        .unwrap_or(highlighting::Type::None);
+                    index += grapheme.chars().count();
+                    hl_type
+                })
+                .collect();
+        }
+        self.highlight_todos();
+        self.highlight_match(matcher);
+        self.state = state.clone();
+        self.is_highlighted = true;
+        state
+    }
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn grapheme_width(grapheme: &str, column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = cmp::max(tab_width, 1);
+        tab_width - column % tab_width
+    } else if let Some(placeholder) = control_placeholder(grapheme) {
+        placeholder.len()
+    } else if grapheme.contains('\u{fe0f}') || grapheme.chars().any(is_wide) {
+        2
+    } else {
+        1
+    }
+}
+
+fn control_placeholder(grapheme: &str) -> Option<String> {
+    if grapheme == "\t" || !grapheme.chars().any(char::is_control) {
+        return None;
+    }
+    Some(
+        grapheme
+            .chars()
+            .map(|c| match u32::from(c) {
+                code @ 0..=0x1f => format!("^{}", char::from_u32(code ^ 0x40).unwrap_or('?')),
+                0x7f => "^?".to_string(),
+                code if c.is_control() => format!("<U+{:04X}>", code),
+                _ => c.to_string(),
+            })
+            .collect(),
+    )
+}
+
+fn is_wide(c: char) -> bool {
+    let code = u32::from(c);
+    WIDE_RANGES
+        .binary_search_by(|(start, end)| {
+            if code < *start {
+                cmp::Ordering::Greater
+            } else if code > *end {
+                cmp::Ordering::Less
+            } else {
+                cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn starts_with_at(chars: &[char], index: usize, pattern: &str) -> bool {
+    !pattern.is_empty()
+        && pattern
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| chars.get(index + offset) == Some(&c))
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn find_at(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    (from..chars.len()).find(|index| starts_with_at(chars, *index, pattern))
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn string_prefix(opts: &HighlightingOptions, chars: &[char], index: usize) -> usize {
+    if index > 0 && chars.get(index - 1).is_some_and(|c| !is_separator(*c)) {
+        return 0;
+    }
+    opts.string_prefixes()
+        .iter()
+        .filter(|prefix| starts_with_at(chars, index, prefix))
+        .map(|prefix| prefix.chars().count())
+        .filter(|length| {
+            chars
+                .get(index + length)
+                .is_some_and(|c| opts.string_delimiters().contains(c))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn starts_expression(chars: &[char], index: usize) -> bool {
+    let before: Vec<char> = chars
+        .iter()
+        .take(index)
+        .rev()
+        .skip_while(|c| c.is_whitespace())
+        .copied()
+        .collect();
+    match before.first() {
+        None => true,
+        Some(prev) if prev.is_alphanumeric() || *prev == '_' || *prev == '$' => {
+            let mut word: Vec<char> = before
+                .iter()
+                .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '$')
+                .copied()
+                .collect();
+            word.reverse();
+            let word: String = word.into_iter().collect();
+            [
+                "return",
+                "typeof",
+                "instanceof",
+                "case",
+                "do",
+                "else",
+                "in",
+                "of",
+                "new",
+                "delete",
+                "void",
+                "throw",
+                "yield",
+                "await",
+            ]
+            .contains(&word.as_str())
+        }
+        Some(prev) => !matches!(prev, ')' | ']' | '}' | '"' | '\'' | '`'),
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_ascii_punctuation() || c.is_ascii_whitespace()
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_raw_prefix(chars: &[char], index: usize, prefix: usize) -> bool {
+    chars
+        .iter()
+        .skip(index)
+        .take(prefix)
+        .any(|c| matches!(c, 'r' | 'R'))
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn escape_len(chars: &[char], at: usize) -> usize {
+    let hex_digits = |from: usize, max: usize| {
+        chars
+            .iter()
+            .skip(from)
+            .take(max)
+            .take_while(|c| c.is_ascii_hexdigit())
+            .count()
+    };
+    match chars.get(at + 1) {
+        None => 1,
+        Some('u') if chars.get(at + 2) == Some(&'{') => {
+            find_at(chars, at + 2, "}").map_or(chars.len() - at, |closing| closing + 1 - at)
+        }
+        Some('x') => 2 + hex_digits(at + 2, 2),
+        Some('u') => 2 + hex_digits(at + 2, 4),
+        Some('U') => 2 + hex_digits(at + 2, 8),
+        Some(_) => 2,
+    }
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn timestamp_len(chars: &[char], index: usize) -> usize {
+    let digits = |at: usize, count: usize| {
+        (at..at + count).all(|at| chars.get(at).is_some_and(char::is_ascii_digit))
+    };
+    let is_time = |at: usize| {
+        digits(at, 2)
+            && chars.get(at + 2) == Some(&':')
+            && digits(at + 3, 2)
+            && chars.get(at + 5) == Some(&':')
+            && digits(at + 6, 2)
+    };
+    let mut end = index;
+    if digits(end, 4)
+        && chars.get(end + 4) == Some(&'-')
+        && digits(end + 5, 2)
+        && chars.get(end + 7) == Some(&'-')
+        && digits(end + 8, 2)
+    {
+        end += 10;
+        if !matches!(chars.get(end), Some('T' | ' ')) || !is_time(end + 1) {
+            return end - index;
+        }
+        end += 1;
+    }
+    if !is_time(end) {
+        return end - index;
+    }
+    end += 8;
+    if matches!(chars.get(end), Some('.' | ',')) && digits(end + 1, 1) {
+        end += 1;
+        while digits(end, 1) {
+            end += 1;
+        }
+    }
+    if chars.get(end) == Some(&'Z') {
+        end += 1;
+    } else if matches!(chars.get(end), Some('+' | '-')) && digits(end + 1, 2) {
+        end += 3;
+        if chars.get(end) == Some(&':') && digits(end + 1, 2) {
+            end += 3;
+        } else if digits(end, 2) {
+            end += 2;
+        }
+    }
+    end - index
 }
@@ -0,0 +1,182 @@
+use std::{fs, io::ErrorKind};
+
+use serde::{de::IntoDeserializer, Deserialize};
+
+use crate::{Command, Config, Key};
+
+const SCRIPT_FILE: &str = "init.hecto";
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Hook {
+    Open,
+    Save,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Action {
+    Run(Command),
+    Goto(usize, usize),
+    Insert(String),
+    Buffer(usize),
+    Message(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Function {
+    pub name: String,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Default)]
+pub struct Script {
+    pub bindings: Vec<(Vec<Key>, Command)>,
+    pub hooks: Vec<(Hook, Command)>,
+    pub functions: Vec<Function>,
+    defining: Option<Function>,
+}
+
+impl Script {
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Config::dir().map(|dir| dir.join(SCRIPT_FILE)) else {
+            return Ok(Self::default());
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(format!("Could not read {}: {}", path.display(), error)),
+        };
+        let mut script = Self::default();
+        for (number, line) in contents.lines().enumerate() {
+            script.parse_line(line).map_err(|error| {
+                format!("{}:{}: {}", SCRIPT_FILE, number.saturating_add(1), error)
+            })?;
+        }
+        if let Some(function) = script.defining {
+            return Err(format!(
+                "{}: def {} is missing end",
+                SCRIPT_FILE, function.name
+            ));
+        }
+        Ok(script)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if words.first().is_none_or(|word| word.starts_with('#')) {
+            return Ok(());
+        }
+        let statement = words.remove(0);
+        if let Some(function) = self.defining.as_mut() {
+            if statement == "end" {
+                self.functions.extend(self.defining.take());
+            } else {
+                let rest = line.trim_start().get(statement.len()..).unwrap_or_default();
+                function
+                    .actions
+                    .push(parse_action(statement, &words, rest.trim())?);
+            }
+            return Ok(());
+        }
+        if statement == "def" {
+            let [name] = words.as_slice() else {
+                return Err("def needs a single name".to_string());
+            };
+            if parse_command(name).is_ok() || self.function(name).is_some() {
+                return Err(format!("{} is already defined", name));
+            }
+            self.defining = Some(Function {
+                name: (*name).to_string(),
+                actions: Vec::new(),
+            });
+            return Ok(());
+        }
+        let command = words
+            .pop()
+            .ok_or_else(|| format!("{} needs a command", statement))
+            .and_then(|name| self.function(name).map_or_else(|| parse_command(name), Ok))?;
+        match statement {
+            "bind" if !words.is_empty() => {
+                let keys = words
+                    .iter()
+                    .map(|word| Key::parse(word).ok_or_else(|| format!("Unknown key: {}", word)))
+                    .collect::<Result<Vec<Key>, String>>()?;
+                self.bindings.push((keys, command));
+            }
+            "bind" => return Err("bind needs a key".to_string()),
+            "on" => {
+                let hook = match words.as_slice() {
+                    ["open"] => Hook::Open,
+                    ["save"] => Hook::Save,
+                    _ => return Err(format!("Unknown hook: {}", words.join(" "))),
+                };
+                self.hooks.push((hook, command));
+            }
+            _ => return Err(format!("Unknown statement: {}", statement)),
+        }
+        Ok(())
+    }
+
+    fn function(&self, name: &str) -> Option<Command> {
+        self.functions
+            .iter()
+            .position(|function| function.name == name)
+            .map(Command::Script)
+    }
+
+    pub fn actions(&self, index: usize) -> Vec<Action> {
+        self.functions
+            .get(index)
+            .map(|function| function.actions.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn commands(&self, hook: Hook) -> Vec<Command> {
+        self.hooks
+            .iter()
+            .filter(|(bound, _)| *bound == hook)
+            .map(|(_, command)| *command)
+            .collect()
+    }
+}
+
+fn parse_action(statement: &str, words: &[&str], rest: &str) -> Result<Action, String> {
+    let number = |word: &str| {
+        word.parse::<usize>()
+            .ok()
+            .filter(|number| *number > 0)
+            .ok_or_else(|| format!("Expected a positive number: {}", word))
+    };
+    match (statement, words) {
+        ("run", [name]) => parse_command(name).map(Action::Run),
+        ("goto", [line]) => Ok(Action::Goto(number(line)?, 1)),
+        ("goto", [line, column]) => Ok(Action::Goto(number(line)?, number(column)?)),
+        ("insert", _) => Ok(Action::Insert(unescape(rest))),
+        ("buffer", [index]) => Ok(Action::Buffer(number(index)?)),
+        ("message", _) => Ok(Action::Message(rest.to_string())),
+        ("run" | "goto" | "buffer", _) => Err(format!("Wrong arguments for {}", statement)),
+        _ => Err(format!("Unknown action: {}", statement)),
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn parse_command(name: &str) -> Result<Command, String> {
+    Command::deserialize(name.into_deserializer())
+        .map_err(|_: serde::de::value::Error| format!("Unknown command: {}", name))
+}
@@ -0,0 +1,32 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+pub fn filter(command: &str, input: String) -> Result<String, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+    let mut stdin = child.stdin.take().ok_or("Could not open stdin")?;
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().find(|line| !line.trim().is_empty());
+        return Err(match (message, output.status.code()) {
+            (Some(message), _) => message.trim().to_string(),
+            (None, Some(code)) => format!("exited with status {}", code),
+            (None, None) => "terminated by signal".to_string(),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| "Output is not valid UTF-8".to_string())
+}
@@ -0,0 +1,127 @@
+use std::{
+    cmp,
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::Config;
+
+const DICTIONARIES: [&str; 3] = [
+    "/usr/share/hunspell/en_US.dic",
+    "/usr/share/myspell/en_US.dic",
+    "/usr/share/dict/words",
+];
+const MAX_SUGGESTIONS: usize = 10;
+const MAX_DISTANCE: usize = 2;
+
+#[derive(Debug, Default)]
+pub struct SpellChecker {
+    words: HashSet<String>,
+}
+
+impl SpellChecker {
+    pub fn load(dictionary: Option<&str>) -> Result<Self, String> {
+        let path = match dictionary {
+            Some(path) => PathBuf::from(path),
+            None => DICTIONARIES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.is_file())
+                .ok_or_else(|| "No dictionary found.".to_string())?,
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+        let is_hunspell = path.extension().is_some_and(|extension| extension == "dic");
+        let mut checker = Self::default();
+        for line in contents.lines().skip(usize::from(is_hunspell)) {
+            let word = line.split('/').next().unwrap_or_default().trim();
+            if !word.is_empty() {
+                checker.words.insert(word.to_string());
+            }
+        }
+        if let Some(personal) = personal_path() {
+            if let Ok(contents) = fs::read_to_string(personal) {
+                checker
+                    .words
+                    .extend(contents.lines().map(str::trim).map(str::to_string));
+            }
+        }
+        Ok(checker)
+    }
+
+    pub fn is_checked(word: &str) -> bool {
+        let mut chars = word.chars();
+        chars.next().is_some_and(char::is_alphabetic)
+            && word.chars().count() > 1
+            && word.chars().all(|c| c.is_alphabetic() || c == '\'')
+            && !chars.any(char::is_uppercase)
+    }
+
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.strip_suffix("'s").unwrap_or(word);
+        self.words.contains(word) || self.words.contains(&word.to_lowercase())
+    }
+
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let lowercase = word.to_lowercase();
+        let length = lowercase.chars().count();
+        let mut suggestions: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .filter(|candidate| candidate.chars().count().abs_diff(length) <= MAX_DISTANCE)
+            .filter_map(|candidate| {
+                let distance = distance(&lowercase, &candidate.to_lowercase());
+                (distance <= MAX_DISTANCE).then_some((distance, candidate))
+            })
+            .collect();
+        suggestions.sort();
+        suggestions
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| candidate.clone())
+            .collect()
+    }
+
+    pub fn add(&mut self, word: &str) -> io::Result<()> {
+        let path = personal_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        writeln!(file, "{}", word)?;
+        self.words.insert(word.to_string());
+        Ok(())
+    }
+}
+
+fn personal_path() -> Option<PathBuf> {
+    Some(Config::dir()?.join("words.txt"))
+}
+
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = cmp::min(
+                cmp::min(rows[i - 1][j] + 1, row[j - 1] + 1),
+                rows[i - 1][j - 1] + cost,
+            );
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = cmp::min(row[j], rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,75 @@
+use std::{fs, path::Path};
+
+pub fn render<F>(format: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    format
+        .split('|')
+        .map(|segment| expand(segment, &lookup))
+        .map(|segment| segment.trim().to_string())
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn expand<F>(format: &str, lookup: &F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::new();
+    let mut rest = format;
+    let mut placeholders = 0;
+    let mut empty = 0;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + length];
+        rest = &rest[start + length + 1..];
+        if let Some(value) = lookup(name) {
+            placeholders += 1;
+            if value.is_empty() {
+                empty += 1;
+            }
+            result.push_str(&value);
+        } else {
+            result.push('{');
+            result.push_str(name);
+            result.push('}');
+        }
+    }
+    result.push_str(rest);
+    if placeholders > 0 && placeholders == empty {
+        return String::new();
+    }
+    result
+}
+
+pub fn git_branch(file_name: &str) -> Option<String> {
+    let path = fs::canonicalize(file_name).ok()?;
+    let root = path.ancestors().find(|dir| dir.join(".git").exists())?;
+    let head = fs::read_to_string(git_dir(&root.join(".git"))?.join("HEAD")).ok()?;
+    let head = head.trim();
+    if let Some(reference) = head.strip_prefix("ref: ") {
+        Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        )
+    } else {
+        head.get(..7).map(str::to_string)
+    }
+}
+
+fn git_dir(dot_git: &Path) -> Option<std::path::PathBuf> {
+    if dot_git.is_dir() {
+        return Some(dot_git.to_path_buf());
+    }
+    let contents = fs::read_to_string(dot_git).ok()?;
+    let dir = contents.trim().strip_prefix("gitdir: ")?;
+    Some(dot_git.parent()?.join(dir))
+}
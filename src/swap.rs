@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    process,
+};
+
+use crate::{undo, Config};
+
+const HEADER: &str = "hecto-swap";
+
+#[derive(Debug)]
+pub struct Swap {
+    pub pid: u32,
+    pub base: String,
+    pub contents: String,
+}
+
+impl Swap {
+    pub fn is_foreign(&self) -> bool {
+        self.pid != process::id() && is_running(self.pid)
+    }
+
+    pub fn is_stale(&self, file_name: &str) -> bool {
+        self.base != base(file_name)
+    }
+}
+
+fn path(file_name: &str) -> Option<PathBuf> {
+    Config::state_file("swap", file_name, "swp")
+}
+
+fn base(file_name: &str) -> String {
+    undo::hash(&fs::read(file_name).unwrap_or_default())
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: kill with signal 0 only checks whether the process exists.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+pub fn write(file_name: &str, contents: &str) -> Result<(), Error> {
+    let path = path(file_name).ok_or_else(|| Error::new(ErrorKind::NotFound, "no swap path"))?;
+    if read(file_name).is_some_and(|swap| swap.is_foreign()) {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "swap file is owned by another process",
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(
+        path,
+        format!(
+            "{} {} {}\n{}",
+            HEADER,
+            process::id(),
+            base(file_name),
+            contents
+        ),
+    )
+}
+
+pub fn read(file_name: &str) -> Option<Swap> {
+    let text = fs::read_to_string(path(file_name)?).ok()?;
+    let (header, contents) = text.split_once('\n')?;
+    let mut fields = header.split(' ');
+    if fields.next()? != HEADER {
+        return None;
+    }
+    Some(Swap {
+        pid: fields.next()?.parse().ok()?,
+        base: fields.next()?.to_string(),
+        contents: contents.to_string(),
+    })
+}
+
+pub fn remove(file_name: &str) {
+    if read(file_name).is_some_and(|swap| swap.is_foreign()) {
+        return;
+    }
+    if let Some(path) = path(file_name) {
+        let _ = fs::remove_file(path);
+    }
+}
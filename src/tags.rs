@@ -0,0 +1,152 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const TAG_FILES: [&str; 2] = ["tags", ".tags"];
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub path: String,
+    pub line: usize,
+    pub kind: String,
+    pub text: String,
+}
+
+impl Tag {
+    pub fn describe(&self) -> String {
+        if self.kind.is_empty() {
+            format!(
+                "{}:{}: {}",
+                self.path,
+                self.line.saturating_add(1),
+                self.text
+            )
+        } else {
+            format!(
+                "{}:{}: [{}] {}",
+                self.path,
+                self.line.saturating_add(1),
+                self.kind,
+                self.text
+            )
+        }
+    }
+}
+
+fn tag_file(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(|dir| {
+        TAG_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+pub fn lookup(name: &str, start: &Path) -> Vec<Tag> {
+    let mut tags = tag_file(start)
+        .map(|path| ctags(name, &path))
+        .unwrap_or_default();
+    if tags.is_empty() {
+        tags = global(name, start);
+    }
+    let root = env::current_dir().unwrap_or_default();
+    for tag in &mut tags {
+        if let Ok(path) = Path::new(&tag.path).strip_prefix(&root) {
+            tag.path = path.to_string_lossy().into_owned();
+        }
+    }
+    tags
+}
+
+fn ctags(name: &str, path: &Path) -> Vec<Tag> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let root = path.parent().unwrap_or_else(|| Path::new(""));
+    contents
+        .lines()
+        .filter(|line| !line.starts_with("!_TAG_"))
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            if fields.next()? != name {
+                return None;
+            }
+            let file = root.join(fields.next()?);
+            let rest = fields.next()?;
+            let (address, extra) = rest.split_once(";\"").unwrap_or((rest, ""));
+            let (line, text) = resolve(&file, address)?;
+            Some(Tag {
+                path: file.to_string_lossy().into_owned(),
+                line,
+                kind: extra
+                    .split('\t')
+                    .map(|field| field.strip_prefix("kind:").unwrap_or(field))
+                    .find(|field| !field.is_empty() && !field.contains(':'))
+                    .unwrap_or_default()
+                    .to_string(),
+                text,
+            })
+        })
+        .collect()
+}
+
+fn resolve(file: &Path, address: &str) -> Option<(usize, String)> {
+    let contents = fs::read_to_string(file).ok()?;
+    let mut lines = contents.lines();
+    if let Ok(number) = address.parse::<usize>() {
+        let line = number.saturating_sub(1);
+        return Some((line, lines.nth(line)?.trim().to_string()));
+    }
+    let pattern = address
+        .strip_prefix('/')
+        .and_then(|pattern| pattern.strip_suffix('/'))
+        .or_else(|| {
+            address
+                .strip_prefix('?')
+                .and_then(|pattern| pattern.strip_suffix('?'))
+        })?;
+    let pattern = pattern.replace("\\/", "/").replace("\\\\", "\\");
+    let (pattern, anchored_start) = pattern
+        .strip_prefix('^')
+        .map_or((pattern.as_str(), false), |pattern| (pattern, true));
+    let (pattern, anchored_end) = pattern
+        .strip_suffix('$')
+        .map_or((pattern, false), |pattern| (pattern, true));
+    lines
+        .enumerate()
+        .find(|(_, line)| match (anchored_start, anchored_end) {
+            (true, true) => *line == pattern,
+            (true, false) => line.starts_with(pattern),
+            (false, true) => line.ends_with(pattern),
+            (false, false) => line.contains(pattern),
+        })
+        .map(|(index, line)| (index, line.trim().to_string()))
+}
+
+fn global(name: &str, start: &Path) -> Vec<Tag> {
+    let Ok(output) = Command::new("global")
+        .args(["-x", "--", name])
+        .current_dir(start)
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next()?;
+            let line_number: usize = fields.next()?.parse().ok()?;
+            let file = fields.next()?;
+            let text = fields.collect::<Vec<_>>().join(" ");
+            Some(Tag {
+                path: start.join(file).to_string_lossy().into_owned(),
+                line: line_number.saturating_sub(1),
+                kind: String::new(),
+                text,
+            })
+        })
+        .collect()
+}
@@ -1,19 +1,80 @@
-use std::io::{stdout, Write};
+use std::{
+    cell::RefCell,
+    cmp, env,
+    fs::File,
+    io::{self, stdout, Write},
+    panic,
+};
+
+#[cfg(unix)]
+use std::{
+    fs::OpenOptions,
+    io::IsTerminal,
+    os::unix::io::{AsRawFd, FromRawFd},
+};
 
 use crossterm::{
-    cursor::{self, MoveTo},
-    execute, style,
-    terminal::{self, Clear, ClearType},
+    cursor::{self, CursorShape, MoveTo, SetCursorShape},
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    queue, style,
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+    Command,
 };
 
+use serde::Deserialize;
+
 use crate::Position;
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
 #[derive(Debug)]
 pub struct Size {
     pub width: u16,
     pub height: u16,
 }
 
+#[derive(Debug, Default)]
+struct Frame {
+    active: bool,
+    row: usize,
+    rows: Vec<String>,
+    previous: Vec<String>,
+}
+
+impl Frame {
+    fn current_row(&mut self) -> &mut String {
+        if self.rows.len() <= self.row {
+            self.rows.resize(self.row.saturating_add(1), String::new());
+        }
+        #[allow(clippy::indexing_slicing)]
+        &mut self.rows[self.row]
+    }
+}
+
+#[derive(Debug, Default)]
+struct Output {
+    buffer: Vec<u8>,
+    frame: Frame,
+    entered: bool,
+    error: Option<io::Error>,
+}
+
+thread_local! {
+    static OUTPUT: RefCell<Output> = RefCell::default();
+}
+
 #[derive(Debug)]
 pub struct Terminal {
     size: Size,
@@ -22,6 +83,8 @@ pub struct Terminal {
 impl Terminal {
     pub fn defalut() -> Result<Self, crossterm::ErrorKind> {
         let size = terminal::size()?;
+        Self::enter()?;
+        Self::install_panic_hook();
         Ok(Self {
             size: Size {
                 width: size.0,
@@ -30,50 +93,226 @@ impl Terminal {
         })
     }
 
+    fn enter() -> Result<(), crossterm::ErrorKind> {
+        enable_raw_mode()?;
+        OUTPUT.with(|output| output.borrow_mut().entered = true);
+        Self::queue(EnterAlternateScreen);
+        Self::queue(EnableMouseCapture);
+        Self::queue(EnableFocusChange);
+        Self::queue(PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+        ));
+        Ok(())
+    }
+
+    fn install_panic_hook() {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            Self::restore();
+            previous(info);
+        }));
+    }
+
+    pub fn restore() {
+        if !OUTPUT.with(|output| std::mem::take(&mut output.borrow_mut().entered)) {
+            return;
+        }
+        Self::queue(PopKeyboardEnhancementFlags);
+        Self::disable_event_capture();
+        Self::reset_cursor_shape();
+        Self::cursor_show();
+        Self::queue(LeaveAlternateScreen);
+        let _ = Self::flush();
+        let _ = disable_raw_mode();
+    }
+
+    #[cfg(unix)]
+    pub fn suspend(&mut self) -> Result<(), crossterm::ErrorKind> {
+        Self::restore();
+        // SAFETY: raise only sends SIGTSTP to this process, which stops until SIGCONT.
+        if unsafe { libc::raise(libc::SIGTSTP) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Self::enter()?;
+        self.update_size()
+    }
+
     pub fn size(&self) -> &Size {
         &self.size
     }
 
+    pub fn begin_frame() {
+        OUTPUT.with(|output| {
+            let frame = &mut output.borrow_mut().frame;
+            frame.active = true;
+            frame.row = 0;
+            frame.rows.clear();
+        });
+    }
+
+    pub fn end_frame() {
+        OUTPUT.with(|output| {
+            let output = &mut *output.borrow_mut();
+            let frame = &mut output.frame;
+            let rows = cmp::max(frame.rows.len(), frame.previous.len());
+            for index in 0..rows {
+                let row = frame.rows.get(index);
+                if frame.previous.get(index) == row {
+                    continue;
+                }
+                let result = match row {
+                    Some(row) if !row.is_empty() => {
+                        output.buffer.extend_from_slice(row.as_bytes());
+                        Ok(())
+                    }
+                    _ => queue!(
+                        output.buffer,
+                        MoveTo(0, index as u16),
+                        Clear(ClearType::CurrentLine)
+                    ),
+                };
+                if let Err(error) = result {
+                    output.error.get_or_insert(error);
+                }
+            }
+            frame.previous = std::mem::take(&mut frame.rows);
+            frame.active = false;
+        });
+    }
+
+    pub fn invalidate() {
+        OUTPUT.with(|output| output.borrow_mut().frame.previous.clear());
+    }
+
+    fn queue(command: impl Command) {
+        OUTPUT.with(|output| {
+            let output = &mut *output.borrow_mut();
+            let result = if output.frame.active {
+                command
+                    .write_ansi(output.frame.current_row())
+                    .map_err(|_| io::Error::other("Failed to format command"))
+            } else {
+                queue!(output.buffer, command)
+            };
+            if let Err(error) = result {
+                output.error.get_or_insert(error);
+            }
+        });
+    }
+
+    pub fn print(text: &str) {
+        OUTPUT.with(|output| {
+            let output = &mut *output.borrow_mut();
+            if output.frame.active {
+                output.frame.current_row().push_str(text);
+            } else {
+                output.buffer.extend_from_slice(text.as_bytes());
+            }
+        });
+    }
+
     pub fn clear_screen() {
-        execute!(stdout(), Clear(ClearType::All)).unwrap();
+        Self::invalidate();
+        Self::queue(Clear(ClearType::All));
     }
 
     pub fn cursor_position(position: &Position) {
         let Position { x, y } = &position;
+        OUTPUT.with(|output| output.borrow_mut().frame.row = *y);
         let x = *x as u16;
         let y = *y as u16;
-        execute!(stdout(), MoveTo(x, y)).unwrap();
+        Self::queue(MoveTo(x, y));
     }
 
     pub fn flush() -> Result<(), std::io::Error> {
-        stdout().flush()
+        let (buffer, error) = OUTPUT.with(|output| {
+            let output = &mut *output.borrow_mut();
+            (std::mem::take(&mut output.buffer), output.error.take())
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        let mut stdout = stdout().lock();
+        stdout.write_all(&buffer)?;
+        stdout.flush()
     }
 
     pub fn cursor_hide() {
-        execute!(stdout(), cursor::Hide).unwrap();
+        Self::queue(cursor::Hide);
     }
 
     pub fn cursor_show() {
-        execute!(stdout(), cursor::Show).unwrap();
+        Self::queue(cursor::Show);
+    }
+
+    pub fn set_cursor_shape(shape: CursorShape) {
+        Self::queue(SetCursorShape(shape));
+    }
+
+    pub fn reset_cursor_shape() {
+        Self::print("\x1b[0 q");
+    }
+
+    pub fn disable_event_capture() {
+        Self::queue(DisableMouseCapture);
+        Self::queue(DisableFocusChange);
     }
 
     pub fn clear_current_line() {
-        execute!(stdout(), Clear(ClearType::CurrentLine)).unwrap();
+        Self::queue(Clear(ClearType::CurrentLine));
     }
 
     pub fn set_bg_color(color: style::Color) {
-        execute!(stdout(), style::SetBackgroundColor(color)).unwrap();
+        Self::queue(style::SetBackgroundColor(color));
     }
 
     pub fn reset_color() {
-        execute!(stdout(), style::ResetColor).unwrap();
+        Self::queue(style::ResetColor);
     }
 
     pub fn set_fg_color(color: style::Color) {
-        execute!(stdout(), style::SetForegroundColor(color)).unwrap();
+        Self::queue(style::SetForegroundColor(color));
+    }
+
+    pub fn color_support() -> ColorSupport {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if term.ends_with("-direct") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn redirect_stdout() -> io::Result<Option<File>> {
+        if stdout().is_terminal() {
+            return Ok(None);
+        }
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        // SAFETY: dup and dup2 only duplicate descriptors owned by this process.
+        unsafe {
+            let saved = libc::dup(libc::STDOUT_FILENO);
+            if saved < 0 || libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Some(File::from_raw_fd(saved)))
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn redirect_stdout() -> io::Result<Option<File>> {
+        Ok(None)
     }
 
     pub fn update_size(&mut self) -> Result<(), crossterm::ErrorKind> {
+        Self::invalidate();
         let size = terminal::size()?;
         self.size = Size {
             width: size.0,
@@ -82,3 +321,9 @@ impl Terminal {
         Ok(())
     }
 }
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        Self::restore();
+    }
+}
@@ -0,0 +1,495 @@
+use std::{fs, io::ErrorKind};
+
+use crossterm::style::Color;
+use serde::{de::Error, Deserialize, Deserializer};
+
+use crate::{ColorSupport, Config};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UiTheme {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub text: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub status_foreground: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub status_background: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub inactive_status_background: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub line_number: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub separator: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub selection_foreground: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub selection_background: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub matching_bracket: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diff_added: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diff_modified: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diff_removed: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diff_text: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diagnostic_error: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diagnostic_warning: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub diagnostic_info: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub misspelled: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub whitespace: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub indent_guide: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub control_character: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color_column: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub current_line: Color,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SyntaxTheme {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub number: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub search_match: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub string: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub character: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub comment: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub primary_keyword: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub secondary_keyword: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub decorator: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub preprocessor: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub regex: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub heading: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub emphasis: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub code: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub link: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub bullet: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub key: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub todo: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub doc_comment: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub lifetime: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub attribute: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub escape: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub variable: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub function: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub typename: Color,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub ui: UiTheme,
+    pub syntax: SyntaxTheme,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            text: Color::Rgb {
+                r: 255,
+                g: 255,
+                b: 255,
+            },
+            status_foreground: Color::Black,
+            status_background: Color::Grey,
+            inactive_status_background: Color::DarkGrey,
+            line_number: Color::DarkGrey,
+            separator: Color::DarkGrey,
+            selection_foreground: Color::Black,
+            selection_background: Color::Grey,
+            matching_bracket: Color::DarkCyan,
+            diff_added: Color::Green,
+            diff_modified: Color::Yellow,
+            diff_removed: Color::Red,
+            diff_text: Color::DarkRed,
+            diagnostic_error: Color::Red,
+            diagnostic_warning: Color::Yellow,
+            diagnostic_info: Color::Blue,
+            misspelled: Color::Red,
+            whitespace: Color::DarkGrey,
+            indent_guide: Color::DarkGrey,
+            control_character: Color::Blue,
+            color_column: Color::DarkGrey,
+            current_line: Color::AnsiValue(236),
+        }
+    }
+}
+
+impl Default for SyntaxTheme {
+    fn default() -> Self {
+        Self {
+            number: Color::Magenta,
+            search_match: Color::Blue,
+            string: Color::Green,
+            character: Color::Green,
+            comment: Color::Grey,
+            primary_keyword: Color::Yellow,
+            secondary_keyword: Color::Cyan,
+            decorator: Color::DarkCyan,
+            preprocessor: Color::DarkMagenta,
+            regex: Color::DarkGreen,
+            heading: Color::Yellow,
+            emphasis: Color::Magenta,
+            code: Color::Green,
+            link: Color::Cyan,
+            bullet: Color::DarkYellow,
+            key: Color::Blue,
+            todo: Color::Red,
+            doc_comment: Color::DarkGreen,
+            lifetime: Color::DarkYellow,
+            attribute: Color::DarkCyan,
+            escape: Color::Cyan,
+            variable: Color::Magenta,
+            function: Color::Blue,
+            typename: Color::Cyan,
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "light" => Some(Self::light()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    pub fn load(name: &str) -> Result<Self, String> {
+        if let Some(dir) = Config::dir() {
+            let path = dir.join("themes").join(format!("{}.toml", name));
+            match fs::read_to_string(&path) {
+                Ok(contents) => {
+                    return toml::from_str(&contents)
+                        .map_err(|error| format!("Invalid {}: {}", path.display(), error))
+                }
+                Err(error) if error.kind() == ErrorKind::NotFound => (),
+                Err(error) => return Err(format!("Could not read {}: {}", path.display(), error)),
+            }
+        }
+        Self::builtin(name).ok_or_else(|| format!("Unknown theme: {}", name))
+    }
+
+    pub fn adapt(&mut self, color_support: ColorSupport) {
+        let ui = &mut self.ui;
+        let syntax = &mut self.syntax;
+        for color in [
+            &mut ui.text,
+            &mut ui.status_foreground,
+            &mut ui.status_background,
+            &mut ui.inactive_status_background,
+            &mut ui.line_number,
+            &mut ui.separator,
+            &mut ui.selection_foreground,
+            &mut ui.selection_background,
+            &mut ui.matching_bracket,
+            &mut ui.diff_added,
+            &mut ui.diff_modified,
+            &mut ui.diff_removed,
+            &mut ui.diff_text,
+            &mut ui.diagnostic_error,
+            &mut ui.diagnostic_warning,
+            &mut ui.diagnostic_info,
+            &mut ui.misspelled,
+            &mut ui.whitespace,
+            &mut ui.indent_guide,
+            &mut ui.control_character,
+            &mut ui.color_column,
+            &mut ui.current_line,
+            &mut syntax.number,
+            &mut syntax.search_match,
+            &mut syntax.string,
+            &mut syntax.character,
+            &mut syntax.comment,
+            &mut syntax.primary_keyword,
+            &mut syntax.secondary_keyword,
+            &mut syntax.decorator,
+            &mut syntax.preprocessor,
+            &mut syntax.regex,
+            &mut syntax.heading,
+            &mut syntax.emphasis,
+            &mut syntax.code,
+            &mut syntax.link,
+            &mut syntax.bullet,
+            &mut syntax.key,
+            &mut syntax.todo,
+            &mut syntax.doc_comment,
+            &mut syntax.lifetime,
+            &mut syntax.attribute,
+            &mut syntax.escape,
+            &mut syntax.variable,
+            &mut syntax.function,
+            &mut syntax.typename,
+        ] {
+            *color = downconvert(*color, color_support);
+        }
+    }
+
+    fn light() -> Self {
+        Self {
+            ui: UiTheme {
+                text: Color::Black,
+                status_foreground: Color::White,
+                status_background: Color::DarkGrey,
+                inactive_status_background: Color::Grey,
+                line_number: Color::Grey,
+                separator: Color::Grey,
+                selection_foreground: Color::White,
+                selection_background: Color::DarkBlue,
+                matching_bracket: Color::Cyan,
+                diff_added: Color::DarkGreen,
+                diff_modified: Color::DarkYellow,
+                diff_removed: Color::DarkRed,
+                diff_text: Color::Yellow,
+                diagnostic_error: Color::DarkRed,
+                diagnostic_warning: Color::DarkYellow,
+                diagnostic_info: Color::DarkBlue,
+                misspelled: Color::DarkRed,
+                whitespace: Color::Grey,
+                indent_guide: Color::Grey,
+                control_character: Color::DarkBlue,
+                color_column: Color::Grey,
+                current_line: Color::AnsiValue(255),
+            },
+            syntax: SyntaxTheme {
+                number: Color::DarkMagenta,
+                search_match: Color::Blue,
+                string: Color::DarkGreen,
+                character: Color::DarkGreen,
+                comment: Color::DarkGrey,
+                primary_keyword: Color::DarkBlue,
+                secondary_keyword: Color::DarkCyan,
+                decorator: Color::DarkCyan,
+                preprocessor: Color::DarkMagenta,
+                regex: Color::DarkRed,
+                heading: Color::DarkBlue,
+                emphasis: Color::DarkMagenta,
+                code: Color::DarkGreen,
+                link: Color::DarkCyan,
+                bullet: Color::DarkYellow,
+                key: Color::DarkBlue,
+                todo: Color::DarkRed,
+                doc_comment: Color::DarkGreen,
+                lifetime: Color::DarkYellow,
+                attribute: Color::DarkCyan,
+                escape: Color::DarkMagenta,
+                variable: Color::DarkMagenta,
+                function: Color::DarkBlue,
+                typename: Color::DarkCyan,
+            },
+        }
+    }
+
+    fn monochrome() -> Self {
+        Self {
+            ui: UiTheme {
+                text: Color::Reset,
+                status_foreground: Color::Black,
+                status_background: Color::White,
+                inactive_status_background: Color::Grey,
+                line_number: Color::Reset,
+                separator: Color::Reset,
+                selection_foreground: Color::Black,
+                selection_background: Color::White,
+                matching_bracket: Color::DarkGrey,
+                diff_added: Color::Reset,
+                diff_modified: Color::Reset,
+                diff_removed: Color::Reset,
+                diff_text: Color::DarkGrey,
+                diagnostic_error: Color::Reset,
+                diagnostic_warning: Color::Reset,
+                diagnostic_info: Color::Reset,
+                misspelled: Color::Reset,
+                whitespace: Color::Reset,
+                indent_guide: Color::Reset,
+                control_character: Color::DarkGrey,
+                color_column: Color::DarkGrey,
+                current_line: Color::Reset,
+            },
+            syntax: SyntaxTheme {
+                number: Color::Reset,
+                search_match: Color::Reset,
+                string: Color::Reset,
+                character: Color::Reset,
+                comment: Color::Reset,
+                primary_keyword: Color::Reset,
+                secondary_keyword: Color::Reset,
+                decorator: Color::Reset,
+                preprocessor: Color::Reset,
+                regex: Color::Reset,
+                heading: Color::Reset,
+                emphasis: Color::Reset,
+                code: Color::Reset,
+                link: Color::Reset,
+                bullet: Color::Reset,
+                key: Color::Reset,
+                todo: Color::Reset,
+                doc_comment: Color::Reset,
+                lifetime: Color::Reset,
+                attribute: Color::Reset,
+                escape: Color::Reset,
+                variable: Color::Reset,
+                function: Color::Reset,
+                typename: Color::Reset,
+            },
+        }
+    }
+}
+
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn downconvert(color: Color, color_support: ColorSupport) -> Color {
+    let rgb = match (color, color_support) {
+        (_, ColorSupport::TrueColor) => return color,
+        (Color::Rgb { r, g, b }, _) => (r, g, b),
+        (Color::AnsiValue(value), ColorSupport::Ansi16) => ansi_to_rgb(value),
+        _ => return color,
+    };
+    if color_support == ColorSupport::Ansi256 {
+        Color::AnsiValue(nearest_ansi256(rgb))
+    } else {
+        nearest_ansi16(rgb)
+    }
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, candidate)| distance(rgb, *candidate))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+#[allow(
+    clippy::arithmetic_side_effects,
+    clippy::integer_division,
+    clippy::cast_possible_truncation
+)]
+fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| -> u8 {
+        match value {
+            0..=47 => 0,
+            48..=114 => 1,
+            _ => (value - 35) / 40,
+        }
+    };
+    let cube = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube_index = 16 + 36 * cube.0 + 6 * cube.1 + cube.2;
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_index = 232 + gray_step;
+    if distance(rgb, ansi_to_rgb(gray_index)) < distance(rgb, ansi_to_rgb(cube_index)) {
+        gray_index
+    } else {
+        cube_index
+    }
+}
+
+#[allow(
+    clippy::arithmetic_side_effects,
+    clippy::integer_division,
+    clippy::indexing_slicing
+)]
+fn ansi_to_rgb(value: u8) -> (u8, u8, u8) {
+    match value {
+        0..=15 => ANSI16[usize::from(value)].1,
+        16..=231 => {
+            let index = usize::from(value - 16);
+            (
+                CUBE_LEVELS[index / 36],
+                CUBE_LEVELS[index / 6 % 6],
+                CUBE_LEVELS[index % 6],
+            )
+        }
+        _ => {
+            let gray = 8 + (value - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |range| u8::from_str_radix(hex.get(range)?, 16).ok();
+    Some(Color::Rgb {
+        r: channel(0..2)?,
+        g: channel(2..4)?,
+        b: channel(4..6)?,
+    })
+}
+
+pub fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    match name.to_lowercase().as_str() {
+        "reset" | "default" => Some(Color::Reset),
+        name => Color::try_from(name)
+            .ok()
+            .or_else(|| name.parse().ok().map(Color::AnsiValue)),
+    }
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    parse_color(&name).ok_or_else(|| D::Error::custom(format!("unknown color `{}`", name)))
+}
@@ -0,0 +1,540 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    json::{self, Value},
+    Config, Position, SearchDirection,
+};
+
+const COALESCE_PAUSE: Duration = Duration::from_secs(1);
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Travel {
+    Steps(SearchDirection, usize),
+    Time(SearchDirection, Duration),
+}
+
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub start: usize,
+    pub removed: Vec<String>,
+    pub inserted: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub edits: Vec<Edit>,
+    pub before: Position,
+    pub after: Position,
+    kind: EditKind,
+    time: SystemTime,
+}
+
+impl Group {
+    #[allow(clippy::arithmetic_side_effects)]
+    fn continues(&self, kind: EditKind, at: &Position) -> bool {
+        if kind != self.kind
+            || self
+                .time
+                .elapsed()
+                .map_or(true, |elapsed| elapsed > COALESCE_PAUSE)
+        {
+            return false;
+        }
+        match kind {
+            EditKind::Insert => *at == self.after,
+            EditKind::Delete => {
+                at.y == self.after.y && (at.x == self.after.x || at.x + 1 == self.after.x)
+            }
+            EditKind::Other => true,
+        }
+    }
+
+    fn push(&mut self, edit: Edit) {
+        if let Some(last) = self.edits.last_mut() {
+            if last.start == edit.start && last.inserted.len() == edit.removed.len() {
+                last.inserted = edit.inserted;
+                return;
+            }
+        }
+        self.edits.push(edit);
+    }
+
+    fn summary(&self) -> String {
+        let removed: usize = self.edits.iter().map(|edit| edit.removed.len()).sum();
+        let inserted: usize = self.edits.iter().map(|edit| edit.inserted.len()).sum();
+        let line = self
+            .edits
+            .iter()
+            .map(|edit| edit.start)
+            .min()
+            .unwrap_or_default();
+        format!(
+            "line {}: {} -> {} lines",
+            line.saturating_add(1),
+            removed,
+            inserted
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    group: Group,
+    parent: usize,
+    redo: Option<usize>,
+    children: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct UndoHistory {
+    nodes: Vec<Node>,
+    current: usize,
+    open: bool,
+    saved: Option<usize>,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node {
+                group: Group {
+                    edits: Vec::new(),
+                    before: Position::default(),
+                    after: Position::default(),
+                    kind: EditKind::Other,
+                    time: SystemTime::now(),
+                },
+                parent: 0,
+                redo: None,
+                children: Vec::new(),
+            }],
+            current: 0,
+            open: false,
+            saved: Some(0),
+        }
+    }
+}
+
+impl UndoHistory {
+    pub fn record(&mut self, kind: EditKind, edit: Edit, before: Position, after: Position) {
+        if edit.removed == edit.inserted {
+            return;
+        }
+        let current = self.current;
+        match self.nodes.get_mut(current) {
+            Some(node) if self.open && current > 0 && node.group.continues(kind, &before) => {
+                node.group.push(edit);
+                node.group.after = after;
+                node.group.time = SystemTime::now();
+                if self.saved == Some(current) {
+                    self.saved = None;
+                }
+            }
+            _ => {
+                let index = self.nodes.len();
+                self.nodes.push(Node {
+                    group: Group {
+                        edits: vec![edit],
+                        before,
+                        after,
+                        kind,
+                        time: SystemTime::now(),
+                    },
+                    parent: current,
+                    redo: None,
+                    children: Vec::new(),
+                });
+                if let Some(node) = self.nodes.get_mut(current) {
+                    node.redo = Some(index);
+                    node.children.push(index);
+                }
+                self.current = index;
+                self.open = true;
+            }
+        }
+    }
+
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    pub fn undo(&mut self) -> Option<Group> {
+        self.open = false;
+        if self.current == 0 {
+            return None;
+        }
+        let node = self.nodes.get(self.current)?.clone();
+        if let Some(parent) = self.nodes.get_mut(node.parent) {
+            parent.redo = Some(self.current);
+        }
+        self.current = node.parent;
+        Some(node.group)
+    }
+
+    pub fn redo(&mut self) -> Option<Group> {
+        self.open = false;
+        let next = self.nodes.get(self.current)?.redo?;
+        self.current = next;
+        Some(self.nodes.get(next)?.group.clone())
+    }
+
+    pub fn travel(&mut self, travel: Travel) -> Vec<(Group, SearchDirection)> {
+        self.open = false;
+        let last = self.nodes.len().saturating_sub(1);
+        let target = match travel {
+            Travel::Steps(SearchDirection::Backword, steps) => self.current.saturating_sub(steps),
+            Travel::Steps(SearchDirection::Forward, steps) => {
+                self.current.saturating_add(steps).min(last)
+            }
+            Travel::Time(direction, duration) => {
+                let time = self.time(self.current);
+                let time = if direction == SearchDirection::Forward {
+                    time.checked_add(duration)
+                } else {
+                    time.checked_sub(duration)
+                }
+                .unwrap_or(UNIX_EPOCH);
+                let target = (0..=last)
+                    .rev()
+                    .find(|index| self.time(*index) <= time)
+                    .unwrap_or_default();
+                if direction == SearchDirection::Forward {
+                    target.max(self.current)
+                } else {
+                    target.min(self.current)
+                }
+            }
+        };
+        self.go_to(target)
+    }
+
+    fn time(&self, index: usize) -> SystemTime {
+        self.nodes
+            .get(index)
+            .map_or(UNIX_EPOCH, |node| node.group.time)
+    }
+
+    fn ancestors(&self, mut index: usize) -> Vec<usize> {
+        let mut ancestors = vec![index];
+        while index > 0 {
+            index = self.nodes.get(index).map_or(0, |node| node.parent);
+            ancestors.push(index);
+        }
+        ancestors
+    }
+
+    fn go_to(&mut self, target: usize) -> Vec<(Group, SearchDirection)> {
+        let path = self.ancestors(target);
+        let mut steps = Vec::new();
+        while !path.contains(&self.current) {
+            let Some(group) = self.undo() else {
+                break;
+            };
+            steps.push((group, SearchDirection::Backword));
+        }
+        let common = path
+            .iter()
+            .position(|index| *index == self.current)
+            .unwrap_or_default();
+        for index in path.iter().take(common).rev() {
+            let Some(node) = self.nodes.get(*index) else {
+                break;
+            };
+            let (parent, group) = (node.parent, node.group.clone());
+            if let Some(parent) = self.nodes.get_mut(parent) {
+                parent.redo = Some(*index);
+            }
+            steps.push((group, SearchDirection::Forward));
+        }
+        self.current = target;
+        steps
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.open = false;
+        self.saved = Some(self.current);
+    }
+
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.current)
+    }
+
+    pub fn tree(&self) -> (Vec<String>, usize) {
+        let mut lines = Vec::new();
+        let mut current = 0;
+        let now = SystemTime::now();
+        let mut stack = vec![(0, String::new(), String::new())];
+        while let Some((index, marker, prefix)) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else {
+                continue;
+            };
+            if index == self.current {
+                current = lines.len();
+            }
+            let label = if index == 0 {
+                "original".to_string()
+            } else {
+                format!(
+                    "{}  {}",
+                    age(now.duration_since(node.group.time).unwrap_or_default()),
+                    node.group.summary()
+                )
+            };
+            lines.push(format!(
+                "{}{}{} {:>4}  {}{}",
+                prefix,
+                marker,
+                if index == self.current { '@' } else { 'o' },
+                index,
+                label,
+                if self.saved == Some(index) {
+                    "  (saved)"
+                } else {
+                    ""
+                }
+            ));
+            let Some((newest, branches)) = node.children.split_last() else {
+                continue;
+            };
+            stack.push((*newest, String::new(), prefix.clone()));
+            for (position, child) in branches.iter().enumerate().rev() {
+                let last = position.saturating_add(1) == branches.len();
+                stack.push((
+                    *child,
+                    if last { "└─" } else { "├─" }.to_string(),
+                    format!("{}{}", prefix, if last { "  " } else { "│ " }),
+                ));
+            }
+        }
+        (lines, current)
+    }
+
+    pub fn write(&self, file_name: &str, text: &str, levels: usize) -> Result<(), Error> {
+        let path =
+            path(file_name).ok_or_else(|| Error::new(ErrorKind::NotFound, "no undo path"))?;
+        if self.nodes.len() <= 1 {
+            let _ = fs::remove_file(path);
+            return Ok(());
+        }
+        let (nodes, current) = if self.nodes.len() <= levels.saturating_add(1) {
+            (self.nodes.clone(), self.current)
+        } else {
+            self.branch(levels)
+        };
+        let history = Value::object(vec![
+            ("hash", Value::from(hash(text.as_bytes()))),
+            ("current", Value::from(current)),
+            (
+                "nodes",
+                Value::Array(nodes.iter().skip(1).map(Node::to_json).collect()),
+            ),
+        ]);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, history.to_string())
+    }
+
+    fn branch(&self, levels: usize) -> (Vec<Node>, usize) {
+        let mut indices: Vec<usize> = self.ancestors(self.current);
+        indices.pop();
+        indices.reverse();
+        indices.drain(..indices.len().saturating_sub(levels));
+        let current = indices.len();
+        let mut next = self.nodes.get(self.current).and_then(|node| node.redo);
+        while let Some(index) = next {
+            if indices.len() >= levels.saturating_mul(2) {
+                break;
+            }
+            indices.push(index);
+            next = self.nodes.get(index).and_then(|node| node.redo);
+        }
+        let mut nodes = Self::default().nodes;
+        if let Some(root) = nodes.first_mut() {
+            root.redo = (!indices.is_empty()).then_some(1);
+        }
+        for (position, index) in indices.iter().enumerate() {
+            let Some(node) = self.nodes.get(*index) else {
+                break;
+            };
+            let next = position.saturating_add(2);
+            nodes.push(Node {
+                group: node.group.clone(),
+                parent: position,
+                redo: (next <= indices.len()).then_some(next),
+                children: Vec::new(),
+            });
+        }
+        (nodes, current)
+    }
+
+    pub fn load(file_name: &str, text: &str) -> Option<Self> {
+        let history = json::parse(&fs::read_to_string(path(file_name)?).ok()?)?;
+        if history.get("hash")?.as_str()? != hash(text.as_bytes()) {
+            return None;
+        }
+        let mut nodes = Self::default().nodes;
+        for value in history.get("nodes")?.as_array()? {
+            nodes.push(Node::from_json(value)?);
+        }
+        for index in 1..nodes.len() {
+            let parent = nodes.get(index)?.parent;
+            if parent >= index {
+                return None;
+            }
+            nodes.get_mut(parent)?.children.push(index);
+        }
+        let current = history.get("current")?.as_usize()?;
+        (current < nodes.len()).then_some(Self {
+            nodes,
+            current,
+            open: false,
+            saved: Some(current),
+        })
+    }
+}
+
+impl Node {
+    fn to_json(&self) -> Value {
+        let lines = |lines: &[String]| {
+            Value::Array(
+                lines
+                    .iter()
+                    .map(|line| Value::from(line.as_str()))
+                    .collect(),
+            )
+        };
+        let position =
+            |position: &Position| Value::Array(vec![position.x.into(), position.y.into()]);
+        let time = self
+            .group
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Value::object(vec![
+            ("parent", Value::from(self.parent)),
+            ("redo", self.redo.map_or(Value::Null, Value::from)),
+            (
+                "time",
+                Value::from(usize::try_from(time).unwrap_or_default()),
+            ),
+            (
+                "edits",
+                Value::Array(
+                    self.group
+                        .edits
+                        .iter()
+                        .map(|edit| {
+                            Value::object(vec![
+                                ("start", Value::from(edit.start)),
+                                ("removed", lines(&edit.removed)),
+                                ("inserted", lines(&edit.inserted)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            ("before", position(&self.group.before)),
+            ("after", position(&self.group.after)),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let lines = |value: &Value| -> Option<Vec<String>> {
+            value
+                .as_array()?
+                .iter()
+                .map(|line| line.as_str().map(str::to_string))
+                .collect()
+        };
+        let position = |value: &Value| -> Option<Position> {
+            match value.as_array()? {
+                [x, y] => Some(Position {
+                    x: x.as_usize()?,
+                    y: y.as_usize()?,
+                }),
+                _ => None,
+            }
+        };
+        let edits = value
+            .get("edits")?
+            .as_array()?
+            .iter()
+            .map(|edit| {
+                Some(Edit {
+                    start: edit.get("start")?.as_usize()?,
+                    removed: lines(edit.get("removed")?)?,
+                    inserted: lines(edit.get("inserted")?)?,
+                })
+            })
+            .collect::<Option<Vec<Edit>>>()?;
+        let time = u64::try_from(value.get("time")?.as_usize()?).ok()?;
+        Some(Self {
+            group: Group {
+                edits,
+                before: position(value.get("before")?)?,
+                after: position(value.get("after")?)?,
+                kind: EditKind::Other,
+                time: UNIX_EPOCH
+                    .checked_add(Duration::from_secs(time))
+                    .unwrap_or(UNIX_EPOCH),
+            },
+            parent: value.get("parent")?.as_usize()?,
+            redo: value.get("redo")?.as_usize(),
+            children: Vec::new(),
+        })
+    }
+}
+
+fn age(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit.trim() {
+        "s" | "sec" | "secs" => number,
+        "m" | "min" | "mins" => number.checked_mul(60)?,
+        "h" | "hour" | "hours" => number.checked_mul(3600)?,
+        "d" | "day" | "days" => number.checked_mul(86400)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+fn path(file_name: &str) -> Option<PathBuf> {
+    Config::state_file("undo", file_name, "undo")
+}
+
+pub fn hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,154 @@
+use crate::Position;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Window {
+    pub buffer: usize,
+    pub cursor_position: Position,
+    pub offset: Position,
+    pub wrap_offset: usize,
+}
+
+#[derive(Debug)]
+pub enum Layout {
+    Window(usize),
+    Split {
+        direction: SplitDirection,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::Window(0)
+    }
+}
+
+impl Layout {
+    pub fn split(&mut self, window: usize, new_window: usize, direction: SplitDirection) {
+        match self {
+            Self::Window(id) if *id == window => {
+                *self = Self::Split {
+                    direction,
+                    first: Box::new(Self::Window(window)),
+                    second: Box::new(Self::Window(new_window)),
+                };
+            }
+            Self::Window(_) => (),
+            Self::Split { first, second, .. } => {
+                first.split(window, new_window, direction);
+                second.split(window, new_window, direction);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, window: usize) {
+        self.remove_leaf(window);
+        self.renumber(window);
+    }
+
+    fn remove_leaf(&mut self, window: usize) {
+        if let Self::Split { first, second, .. } = self {
+            let remaining = if matches!(**first, Self::Window(id) if id == window) {
+                std::mem::take(second.as_mut())
+            } else if matches!(**second, Self::Window(id) if id == window) {
+                std::mem::take(first.as_mut())
+            } else {
+                first.remove_leaf(window);
+                second.remove_leaf(window);
+                return;
+            };
+            *self = remaining;
+        }
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Self::Window(id) => {
+                if *id > removed {
+                    *id = id.saturating_sub(1);
+                }
+            }
+            Self::Split { first, second, .. } => {
+                first.renumber(removed);
+                second.renumber(removed);
+            }
+        }
+    }
+
+    pub fn windows(&self) -> Vec<usize> {
+        match self {
+            Self::Window(id) => vec![*id],
+            Self::Split { first, second, .. } => {
+                let mut windows = first.windows();
+                windows.append(&mut second.windows());
+                windows
+            }
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    pub fn arrange(
+        &self,
+        area: Rect,
+        windows: &mut Vec<(usize, Rect)>,
+        separators: &mut Vec<Rect>,
+    ) {
+        match self {
+            Self::Window(id) => windows.push((*id, area)),
+            Self::Split {
+                direction: SplitDirection::Horizontal,
+                first,
+                second,
+            } => {
+                let height = area.height / 2;
+                first.arrange(Rect { height, ..area }, windows, separators);
+                second.arrange(
+                    Rect {
+                        y: area.y + height,
+                        height: area.height - height,
+                        ..area
+                    },
+                    windows,
+                    separators,
+                );
+            }
+            Self::Split {
+                direction: SplitDirection::Vertical,
+                first,
+                second,
+            } => {
+                let width = area.width.saturating_sub(1) / 2;
+                first.arrange(Rect { width, ..area }, windows, separators);
+                separators.push(Rect {
+                    x: area.x + width,
+                    width: 1,
+                    ..area
+                });
+                second.arrange(
+                    Rect {
+                        x: area.x + width + 1,
+                        width: area.width.saturating_sub(width + 1),
+                        ..area
+                    },
+                    windows,
+                    separators,
+                );
+            }
+        }
+    }
+}